@@ -0,0 +1,73 @@
+//! Records what the binary was built from, for `--version` and
+//! `--version-json`, in `SLIDESHOW_BUILD_*` environment variables. Anything
+//! that can't be found, like the commit when building outside git, is
+//! `unknown`.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let commit = git(&["rev-parse", "--short=12", "HEAD"]);
+    // Rebuild when the checked-out commit changes, if there is one.
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        let head = git(&["symbolic-ref", "-q", "HEAD"]);
+        let watched = ["HEAD", head.as_deref().unwrap_or("HEAD"), "packed-refs"];
+        // Cargo rebuilds every time if a watched file doesn't exist.
+        for path in watched.iter().map(|path| git_dir.join(path)) {
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let mut features: Vec<_> = env::vars()
+        .filter_map(|(var, _)| {
+            var.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+
+    set("COMMIT", commit);
+    if features.is_empty() {
+        features.push("none".to_owned());
+    }
+    set("FEATURES", Some(features.join(",")));
+    set("PULLDOWN_CMARK", locked_version(&lock, "pulldown-cmark"));
+    set("HANDLEBARS", locked_version(&lock, "handlebars"));
+    set("TARGET", env::var("TARGET").ok());
+    set("RUSTC", output(Command::new(rustc).arg("--version")));
+}
+
+fn set(name: &str, value: Option<String>) {
+    println!(
+        "cargo:rustc-env=SLIDESHOW_BUILD_{}={}",
+        name,
+        value.as_deref().unwrap_or("unknown")
+    );
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    output(Command::new("git").args(args))
+}
+
+/// What `command` prints, if it succeeds.
+fn output(command: &mut Command) -> Option<String> {
+    let output = command.output().ok().filter(|o| o.status.success())?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    Some(output.trim().to_owned()).filter(|output| !output.is_empty())
+}
+
+/// The version of the package `name` in the lockfile `lock`.
+fn locked_version(lock: &str, name: &str) -> Option<String> {
+    let mut lines = lock.lines();
+    lines.find(|line| *line == format!("name = \"{}\"", name))?;
+    let version = lines.next()?.strip_prefix("version = \"")?;
+    Some(version.trim_end_matches('"').to_owned())
+}
@@ -0,0 +1,109 @@
+//! Abbreviations defined like `*[HTML]: HyperText Markup Language`, whose
+//! uses are rendered as `<abbr>`s.
+
+use handlebars::html_escape;
+use pulldown_cmark::Event;
+
+use crate::math::fence_marker;
+
+/// Abbreviations and their expansions, longest first so they're preferred
+/// over the shorter abbreviations they contain.
+#[derive(Debug, Default, Clone)]
+pub struct Abbreviations(Vec<(String, String)>);
+
+impl Abbreviations {
+    /// Removes the definition lines from Markdown `source`, adding their
+    /// abbreviations to ours, and returns the rest of the source. Lines in
+    /// fenced code blocks are left alone.
+    pub fn extract(&mut self, source: &str) -> String {
+        let mut ret = String::with_capacity(source.len());
+        let mut fence: Option<&str> = None;
+        for line in source.split_inclusive('\n') {
+            match (fence, fence_marker(line)) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if marker.starts_with(open) => fence = None,
+                _ => {}
+            }
+            match definition(line).filter(|_| fence.is_none()) {
+                Some((abbr, title)) => self.insert(abbr, title),
+                None => ret.push_str(line),
+            }
+        }
+        ret
+    }
+
+    fn insert(&mut self, abbr: &str, title: &str) {
+        self.0.retain(|(a, _)| a != abbr);
+        let i = self.0.partition_point(|(a, _)| a.len() >= abbr.len());
+        self.0.insert(i, (abbr.to_owned(), title.to_owned()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Splits `text` into the abbreviations used in it as whole words, as
+    /// `Some(title)`, and the text around them, if it uses any.
+    pub fn pieces(&self, text: &str) -> Option<Vec<(Option<String>, String)>> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+        while i < text.len() {
+            let found = if is_word_start(text, i) {
+                self.0.iter().find(|(abbr, _)| {
+                    text[i..].starts_with(abbr.as_str()) && is_word_end(text, i + abbr.len())
+                })
+            } else {
+                None
+            };
+            match found {
+                Some((abbr, title)) => {
+                    if start < i {
+                        pieces.push((None, text[start..i].to_owned()));
+                    }
+                    pieces.push((Some(title.clone()), abbr.clone()));
+                    i += abbr.len();
+                    start = i;
+                }
+                None => i += text[i..].chars().next().map_or(1, char::len_utf8),
+            }
+        }
+        if pieces.is_empty() {
+            return None;
+        }
+        if start < text.len() {
+            pieces.push((None, text[start..].to_owned()));
+        }
+        Some(pieces)
+    }
+}
+
+/// The events for an abbreviation, keeping its text as text so it's part of
+/// headings' slugs.
+pub fn events(abbr: String, title: &str) -> [Event<'static>; 3] {
+    let start = format!(r#"<abbr title="{}">"#, html_escape(title));
+    [
+        Event::Html(start.into()),
+        Event::Text(abbr.into()),
+        Event::Html("</abbr>".into()),
+    ]
+}
+
+/// Parses a `*[abbr]: title` definition line.
+fn definition(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix("*[")?;
+    let end = rest.find("]:")?;
+    let abbr = &rest[..end];
+    if abbr.trim().is_empty() || abbr.contains('[') || abbr.contains(']') {
+        return None;
+    }
+    Some((abbr, rest[end + 2..].trim()))
+}
+
+fn is_word_start(text: &str, i: usize) -> bool {
+    !text[..i].ends_with(char::is_alphanumeric)
+}
+
+fn is_word_end(text: &str, i: usize) -> bool {
+    !text[i..].starts_with(char::is_alphanumeric)
+}
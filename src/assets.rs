@@ -0,0 +1,57 @@
+//! Local files referenced from the slideshow's Markdown, like images.
+
+use std::path::{Component, Path, PathBuf};
+
+use crate::diagnostics::Diagnostics;
+
+/// Returns the file a link or image destination refers to, relative to the
+/// Markdown file, or `None` if it's a URL or an absolute path.
+pub fn local_path(dest: &str, diagnostics: &Diagnostics) -> Option<PathBuf> {
+    if dest.starts_with('/') || dest.starts_with('#') || has_scheme(dest) {
+        return None;
+    }
+    let dest = dest.split(['?', '#']).next()?;
+    let mut path = PathBuf::new();
+    for component in Path::new(dest).components() {
+        match component {
+            Component::Normal(c) => path.push(c),
+            Component::CurDir => {}
+            _ => {
+                diagnostics.warn(format_args!(
+                    "Not copying {:?}, which is outside of the input file's directory",
+                    dest
+                ));
+                return None;
+            }
+        }
+    }
+    if path.as_os_str().is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Prefixes a relative or root-relative `dest` with `base_url`, which ends
+/// with a slash. Returns `None` for URLs and `#anchors`, which are left alone.
+pub fn with_base_url(dest: &str, base_url: &str) -> Option<String> {
+    if dest.is_empty() || dest.starts_with('#') || dest.starts_with("//") || has_scheme(dest) {
+        return None;
+    }
+    let dest = dest.trim_start_matches("./").trim_start_matches('/');
+    Some(format!("{}{}", base_url, dest))
+}
+
+/// Does `dest` start with a URL scheme like `https:` or `data:`?
+fn has_scheme(dest: &str) -> bool {
+    match dest.find(':') {
+        Some(colon) => {
+            let scheme = &dest[..colon];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,147 @@
+//! HTML attributes attached to generated elements by directives like
+//! `<!-- .slide: class="dark" data-background="#222" -->` or groups like
+//! `{#id .class width=60%}`.
+
+use std::fmt;
+
+use handlebars::html_escape;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Attributes {
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+    /// Everything other than `id` and `class`, in the order given.
+    pub other: Vec<(String, String)>,
+}
+
+impl Attributes {
+    /// Parses a list of HTML-style attributes, like `class="a b" data-x=y
+    /// hidden`.
+    pub fn parse_html(s: &str) -> Result<Self, String> {
+        let mut ret = Self::default();
+        let mut rest = s.trim_start();
+        while !rest.is_empty() {
+            let key_len = rest.find(|c: char| !is_key_char(c)).unwrap_or(rest.len());
+            if key_len == 0 {
+                return Err(format!("Expected an attribute name at {:?}", rest));
+            }
+            let key = &rest[..key_len];
+            rest = &rest[key_len..];
+            let value = match rest.strip_prefix('=') {
+                Some(after) => {
+                    let (value, after) = parse_value(after)?;
+                    rest = after;
+                    value
+                }
+                None => String::new(),
+            };
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+                return Err(format!("Expected whitespace after {}, not {:?}", key, rest));
+            }
+            ret.insert(key, value);
+            rest = rest.trim_start();
+        }
+        Ok(ret)
+    }
+
+    /// Parses the inside of a Pandoc-style attribute group, like `#id .class
+    /// key=value`. A bare `key` is a boolean attribute, like `muted`.
+    pub fn parse_braced(s: &str) -> Result<Self, String> {
+        let mut ret = Self::default();
+        let mut rest = s.trim_start();
+        while !rest.is_empty() {
+            let end = rest
+                .find(|c: char| c.is_whitespace() || c == '=')
+                .unwrap_or(rest.len());
+            let (word, after) = rest.split_at(end);
+            rest = after;
+            if let Some(id) = word.strip_prefix('#') {
+                ret.id = Some(id.to_owned());
+            } else if let Some(class) = word.strip_prefix('.') {
+                ret.classes.push(class.to_owned());
+            } else if let Some(after) = rest.strip_prefix('=') {
+                if word.is_empty() || !word.chars().all(is_key_char) {
+                    return Err(format!("Expected an attribute name, not {:?}", word));
+                }
+                let (value, after) = parse_value(after)?;
+                ret.insert(word, value);
+                rest = after;
+            } else if !word.is_empty() && word.chars().all(is_key_char) {
+                ret.insert(word, String::new());
+            } else {
+                return Err(format!(
+                    "Expected `#id`, `.class`, `key=value`, or `key`, not {:?}",
+                    word
+                ));
+            }
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+                return Err(format!("Expected whitespace before {:?}", rest));
+            }
+            rest = rest.trim_start();
+        }
+        Ok(ret)
+    }
+
+    fn insert(&mut self, key: &str, value: String) {
+        match key {
+            "id" => self.id = Some(value),
+            "class" => self
+                .classes
+                .extend(value.split_whitespace().map(String::from)),
+            _ => self.other.push((key.to_owned(), value)),
+        }
+    }
+
+    /// Adds `other`'s attributes to these; classes are appended and any
+    /// other attributes replace ours.
+    pub fn merge(&mut self, other: Attributes) {
+        if other.id.is_some() {
+            self.id = other.id;
+        }
+        self.classes.extend(other.classes);
+        for (key, value) in other.other {
+            self.other.retain(|(k, _)| k != &key);
+            self.other.push((key, value));
+        }
+    }
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || "-_:.".contains(c)
+}
+
+/// Parses a quoted or bare attribute value, returning it and the text after
+/// it.
+pub fn parse_value(s: &str) -> Result<(String, &str), String> {
+    match s.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') => match s[1..].find(quote) {
+            Some(end) => Ok((s[1..end + 1].to_owned(), &s[end + 2..])),
+            None => Err(format!("Unterminated attribute value {}", s)),
+        },
+        _ => {
+            let end = s.find(char::is_whitespace).unwrap_or(s.len());
+            if end == 0 {
+                Err("Expected an attribute value after `=`".to_owned())
+            } else {
+                Ok((s[..end].to_owned(), &s[end..]))
+            }
+        }
+    }
+}
+
+/// Formats the attributes for use in an HTML tag, with a leading space if
+/// there are any.
+impl fmt::Display for Attributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.classes.is_empty() {
+            write!(f, r#" class="{}""#, html_escape(&self.classes.join(" ")))?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, r#" id="{}""#, html_escape(id))?;
+        }
+        for (key, value) in &self.other {
+            write!(f, r#" {}="{}""#, key, html_escape(value))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! Fenced code blocks with annotations in their info strings, like
+//! ```` ```rust {2,5-7} title=src/main.rs ````, and diffs, like
+//! ```` ```diff-rust ````.
+
+use std::ops::RangeInclusive;
+
+use crate::attributes;
+use crate::diagnostics::Diagnostics;
+use crate::highlight::{HighlightError, Highlighter};
+use handlebars::html_escape;
+use syntect::util::LinesWithEndings;
+
+/// A parsed code block info string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeInfo {
+    /// The language to highlight, without any `diff-` prefix.
+    pub lang: String,
+    /// Is the block a diff, with `+` and `-` lines?
+    pub diff: bool,
+    /// Lines to emphasize, 1-indexed.
+    pub highlighted: Vec<RangeInclusive<usize>>,
+    /// A caption from `title=` or `caption=`, like a filename.
+    pub title: Option<String>,
+    /// The number of the first line, if lines are numbered with `linenos` or
+    /// `linenos=42`.
+    pub line_numbers: Option<usize>,
+}
+
+impl CodeInfo {
+    pub fn parse(info: &str, diagnostics: &Diagnostics) -> Self {
+        let info = info.trim();
+        let lang_end = info
+            .find(|c: char| c.is_whitespace() || c == '{')
+            .unwrap_or(info.len());
+        let mut ret = Self {
+            lang: info[..lang_end].to_owned(),
+            ..Default::default()
+        };
+        if ret.lang == "diff" {
+            ret.lang.clear();
+            ret.diff = true;
+        } else if let Some(lang) = ret.lang.strip_prefix("diff-") {
+            ret.lang = lang.to_owned();
+            ret.diff = true;
+        }
+        let mut rest = info[lang_end..].trim_start();
+        while !rest.is_empty() {
+            if let Some(group) = rest.strip_prefix('{') {
+                let end = match group.find('}') {
+                    Some(end) => end,
+                    None => {
+                        diagnostics.warn(format_args!(
+                            "Ignoring unclosed `{{` in info string {:?}",
+                            info
+                        ));
+                        break;
+                    }
+                };
+                match parse_line_ranges(&group[..end]) {
+                    Ok(ranges) => ret.highlighted = ranges,
+                    Err(err) => diagnostics.warn(format_args!(
+                        "Ignoring line highlights in info string {:?}: {}",
+                        info, err
+                    )),
+                }
+                rest = &group[end + 1..];
+            } else {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '=' || c == '{')
+                    .unwrap_or(rest.len());
+                let (key, after) = rest.split_at(end);
+                rest = after;
+                if key == "linenos" {
+                    ret.line_numbers = Some(1);
+                }
+                if let Some(value) = rest.strip_prefix('=') {
+                    let (value, after) = match attributes::parse_value(value) {
+                        Ok(parsed) => parsed,
+                        Err(err) => {
+                            diagnostics.warn(format_args!(
+                                "Ignoring rest of info string {:?}: {}",
+                                info, err
+                            ));
+                            break;
+                        }
+                    };
+                    match key {
+                        "title" | "caption" => ret.title = Some(value),
+                        "linenos" => match value.parse() {
+                            Ok(start) => ret.line_numbers = Some(start),
+                            Err(_) => diagnostics.warn(format_args!(
+                                "Expected a starting line number in info string {:?}, not {:?}",
+                                info, value
+                            )),
+                        },
+                        _ => {}
+                    }
+                    rest = after;
+                }
+            }
+            rest = rest.trim_start();
+        }
+        ret
+    }
+
+    /// Does rendering this block need more than pulldown-cmark does?
+    pub fn is_annotated(&self) -> bool {
+        !self.highlighted.is_empty()
+            || self.title.is_some()
+            || self.diff
+            || self.line_numbers.is_some()
+    }
+
+    /// The language as written in the info string, like `diff-rust`.
+    pub fn full_lang(&self) -> String {
+        match (self.diff, self.lang.is_empty()) {
+            (true, true) => "diff".to_owned(),
+            (true, false) => format!("diff-{}", self.lang),
+            (false, _) => self.lang.clone(),
+        }
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted.iter().any(|range| range.contains(&line))
+    }
+}
+
+/// Parses a list of lines like `2,5-7`.
+fn parse_line_ranges(s: &str) -> Result<Vec<RangeInclusive<usize>>, String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Expected a line number, not {:?}", n))
+    };
+    s.split(',')
+        .map(|range| match range.find('-') {
+            Some(dash) => {
+                let (start, end) = (parse(&range[..dash])?, parse(&range[dash + 1..])?);
+                if start > end {
+                    Err(format!("Backwards line range {:?}", range.trim()))
+                } else {
+                    Ok(start..=end)
+                }
+            }
+            None => parse(range).map(|n| n..=n),
+        })
+        .collect()
+}
+
+/// Splits the diff markers off of the lines of `code`, returning each line's
+/// class and marker, and the code without them. Hunk headers like `@@ -1,2
+/// +1,3 @@` are entirely a marker.
+fn split_diff(code: &str) -> (Vec<(Option<&'static str>, &str)>, String) {
+    let mut markers = Vec::new();
+    let mut rest = String::with_capacity(code.len());
+    for line in LinesWithEndings::from(code) {
+        let (class, marker_len) = if line.starts_with("@@") {
+            (
+                Some("diff-hunk"),
+                line.trim_end_matches(&['\r', '\n'][..]).len(),
+            )
+        } else if line.starts_with('+') {
+            (Some("diff-add"), 1)
+        } else if line.starts_with('-') {
+            (Some("diff-del"), 1)
+        } else {
+            (None, 0)
+        };
+        markers.push((class, &line[..marker_len]));
+        rest.push_str(&line[marker_len..]);
+    }
+    (markers, rest)
+}
+
+/// Renders a block of code as `<pre><code>` HTML, highlighted if
+/// `highlighter` is given.
+pub fn render(
+    highlighter: Option<&Highlighter>,
+    info: &CodeInfo,
+    code: &str,
+    diagnostics: &Diagnostics,
+) -> Result<String, HighlightError> {
+    let (diff_markers, code) = if info.diff {
+        split_diff(code)
+    } else {
+        (Vec::new(), code.to_owned())
+    };
+    // A plain `diff` has nothing to highlight once its markers are split off.
+    let highlighter = highlighter.filter(|_| !(info.diff && info.lang.is_empty()));
+    let (pre, mut lines) = match highlighter {
+        Some(highlighter) => (
+            highlighter.pre_tag(),
+            highlighter.highlight_lines(&info.lang, &code, diagnostics)?,
+        ),
+        None => (
+            "<pre".to_owned(),
+            LinesWithEndings::from(code.as_str())
+                .map(html_escape)
+                .collect(),
+        ),
+    };
+    for (line, (class, marker)) in lines.iter_mut().zip(diff_markers) {
+        if let Some(class) = class {
+            *line = format!(
+                r#"<span class="{}">{}{}</span>"#,
+                class,
+                html_escape(marker),
+                line
+            );
+        }
+    }
+
+    for range in &info.highlighted {
+        if *range.end() > lines.len() {
+            diagnostics.warn(format_args!(
+                "Highlighted lines {}-{} are past the end of the {}-line code block",
+                range.start(),
+                range.end(),
+                lines.len()
+            ));
+        }
+    }
+
+    let mut html = String::new();
+    if let Some(title) = &info.title {
+        html.push_str(&format!(
+            "<div class=\"code-block\"><div class=\"code-title\">{}</div>",
+            html_escape(title)
+        ));
+    }
+    if let Some(start) = info.line_numbers {
+        // The numbers are in an element of their own so that copying the
+        // code doesn't copy them too.
+        html.push_str(
+            r#"<div class="line-numbered"><pre class="line-numbers" aria-hidden="true">"#,
+        );
+        for number in start..start + lines.len() {
+            html.push_str(&format!("<span>{}</span>\n", number));
+        }
+        html.push_str("</pre>");
+    }
+    html.push_str(&pre);
+    html.push_str("><code");
+    let lang = info.full_lang();
+    if !lang.is_empty() {
+        html.push_str(&format!(r#" class="language-{}""#, html_escape(&lang)));
+    }
+    html.push('>');
+    for (i, line) in lines.iter().enumerate() {
+        if info.is_highlighted(i + 1) {
+            html.push_str(r#"<span class="hl-line">"#);
+            html.push_str(line);
+            html.push_str("</span>");
+        } else {
+            html.push_str(line);
+        }
+    }
+    html.push_str("</code></pre>");
+    if info.line_numbers.is_some() {
+        html.push_str("</div>");
+    }
+    if info.title.is_some() {
+        html.push_str("</div>");
+    }
+    html.push('\n');
+    Ok(html)
+}
@@ -0,0 +1,192 @@
+//! `slideshow.toml` files of options for a deck, which the command line
+//! overrides, and a global `config.toml` of defaults, which they override.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+use toml::Value;
+
+/// The config file looked for next to the input file.
+pub const FILE_NAME: &str = "slideshow.toml";
+
+/// The config file looked for in the config directory.
+pub const GLOBAL_FILE_NAME: &str = "config.toml";
+
+/// Options which are paths, resolved relative to the config file.
+const PATHS: &[&str] = &[
+    "static-dir",
+    "template",
+    "data",
+    "handout-template",
+    "partials",
+    "partials-dir",
+    "theme-file",
+    "stats-json",
+    "notes-output",
+    "report",
+    "output",
+];
+
+/// Options with short forms, which the command line might give them as.
+const SHORTS: &[(&str, char)] = &[("watch", 'w')];
+
+#[derive(Error, Debug)]
+pub enum ConfigErr {
+    #[error("Error reading config file {0}: {1}")]
+    Read(PathBuf, #[source] io::Error),
+
+    #[error("Error parsing config file {0}: {1}")]
+    Parse(PathBuf, #[source] toml::de::Error),
+
+    #[error("Config file {path} gives `{key}` a nested {kind}, which can't be an option")]
+    Nested {
+        path: PathBuf,
+        key: String,
+        kind: &'static str,
+    },
+}
+
+pub struct Config {
+    pub path: PathBuf,
+    /// The options, by their keys, with their long names and the values to
+    /// give them. Flags have no values.
+    options: Vec<ConfigOption>,
+    /// The output directory, which is positional on the command line.
+    pub output_dir: Option<PathBuf>,
+}
+
+struct ConfigOption {
+    key: String,
+    long: String,
+    values: Vec<String>,
+}
+
+impl Config {
+    /// Reads the config file at `path`. Its keys are the names of `Opt`'s
+    /// fields.
+    pub fn load(path: &Path) -> Result<Self, ConfigErr> {
+        let source = fs::read_to_string(path).map_err(|e| ConfigErr::Read(path.to_owned(), e))?;
+        let table: toml::Table =
+            toml::from_str(&source).map_err(|e| ConfigErr::Parse(path.to_owned(), e))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut ret = Config {
+            path: path.to_owned(),
+            options: Vec::new(),
+            output_dir: None,
+        };
+        for (key, value) in table {
+            let long = match key.as_str() {
+                "vars" => "var".to_owned(),
+                "md_extensions" => "md-extension".to_owned(),
+                "profiles" => "profile".to_owned(),
+                "static_dirs" => "static-dir".to_owned(),
+                key => key.replace('_', "-"),
+            };
+            if long == "output-dir" {
+                if let Value::String(output_dir) = &value {
+                    ret.output_dir = Some(dir.join(output_dir));
+                    continue;
+                }
+            }
+            let nested = |kind| ConfigErr::Nested {
+                path: path.to_owned(),
+                key: key.clone(),
+                kind,
+            };
+            let values = match value {
+                Value::Boolean(false) => continue,
+                Value::Boolean(true) => Vec::new(),
+                Value::Array(values) => values
+                    .into_iter()
+                    .map(|value| scalar(value).ok_or_else(|| nested("list")))
+                    .collect::<Result<_, _>>()?,
+                // Tables are for `--var`s, like `[vars]` with `room = "A"`.
+                Value::Table(table) => table
+                    .into_iter()
+                    .map(|(key, value)| {
+                        scalar(value)
+                            .map(|value| format!("{}={}", key, value))
+                            .ok_or_else(|| nested("table"))
+                    })
+                    .collect::<Result<_, _>>()?,
+                value => vec![scalar(value).unwrap_or_default()],
+            };
+            let values = if PATHS.contains(&long.as_str()) {
+                values
+                    .into_iter()
+                    // `-` is standard output, not a file.
+                    .map(|value| match value.as_str() {
+                        "-" => value,
+                        _ => dir.join(value).to_string_lossy().into_owned(),
+                    })
+                    .collect()
+            } else {
+                values
+            };
+            ret.options.push(ConfigOption { key, long, values });
+        }
+        Ok(ret)
+    }
+
+    /// Arguments giving the options the file sets which `is_option` accepts
+    /// and `cli_args` don't give.
+    pub fn args(&self, cli_args: &[OsString], is_option: impl Fn(&str) -> bool) -> Vec<OsString> {
+        self.options
+            .iter()
+            .filter(|option| is_option(&option.long) && !given(cli_args, &option.long))
+            .flat_map(|option| {
+                if option.values.is_empty() {
+                    vec![format!("--{}", option.long).into()]
+                } else {
+                    option
+                        .values
+                        .iter()
+                        .map(|value| format!("--{}={}", option.long, value).into())
+                        .collect()
+                }
+            })
+            .collect()
+    }
+
+    /// The keys which `is_option` rejects.
+    pub fn unknown(&self, is_option: impl Fn(&str) -> bool) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|option| {
+                option.long == "input"
+                    || option.long == "config"
+                    || option.long == "no-config"
+                    || !is_option(&option.long)
+            })
+            .map(|option| option.key.as_str())
+            .collect()
+    }
+}
+
+/// A value as an argument, unless it's a list or table.
+fn scalar(value: Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s),
+        Value::Array(_) | Value::Table(_) => None,
+        value => Some(value.to_string()),
+    }
+}
+
+/// Does the command line give the option `long`?
+fn given(cli_args: &[OsString], long: &str) -> bool {
+    let short = SHORTS
+        .iter()
+        .find(|(name, _)| *name == long)
+        .map(|(_, short)| *short);
+    cli_args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .take_while(|arg| arg != "--")
+        .any(|arg| match arg.strip_prefix("--") {
+            Some(name) => name.split('=').next() == Some(long),
+            None => arg.starts_with('-') && short.is_some_and(|short| arg[1..].contains(short)),
+        })
+}
@@ -0,0 +1,181 @@
+//! Pandoc-style `:::name` fenced containers, which wrap their contents in a
+//! `<div>`, admonitions like `:::warning Deprecated API`, generic containers
+//! like `:::{.big .centered}`, and collapsible `:::details Summary` blocks.
+
+use handlebars::html_escape;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+use crate::attributes::Attributes;
+
+/// Container names rendered as admonitions even without a title.
+const ADMONITIONS: &[&str] = &[
+    "note",
+    "tip",
+    "hint",
+    "info",
+    "important",
+    "warning",
+    "caution",
+    "danger",
+];
+
+/// The opening marker of a container, like `:::callout{tip} Title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Container {
+    pub name: String,
+    /// Classes from a `{...}` group after the name.
+    classes: Vec<String>,
+    title: Option<String>,
+    /// Attributes from the `{#id .class}` group of a container without a
+    /// name.
+    attributes: Attributes,
+}
+
+/// Parses a line of a paragraph as a container marker: `Some(None)` for a
+/// closing `:::`, or the container being opened.
+pub fn parse_marker(line: &str) -> Option<Option<Container>> {
+    let rest = line.trim().strip_prefix(":::")?.trim_start();
+    let name_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, mut rest) = rest.split_at(name_len);
+    if name.is_empty() {
+        if let Some(group) = rest.strip_prefix('{') {
+            let (group, after) = group.split_once('}')?;
+            if !after.trim().is_empty() {
+                return None;
+            }
+            return Some(Some(Container {
+                name: String::new(),
+                classes: Vec::new(),
+                title: None,
+                attributes: Attributes::parse_braced(group).ok()?,
+            }));
+        }
+    }
+    let mut classes = Vec::new();
+    if let Some(group) = rest.strip_prefix('{') {
+        let end = group.find('}')?;
+        classes = group[..end]
+            .split_whitespace()
+            .map(|class| class.trim_start_matches('.').to_owned())
+            .collect();
+        rest = &group[end + 1..];
+    }
+    let title = rest.trim();
+    if name.is_empty() {
+        return if classes.is_empty() && title.is_empty() {
+            Some(None)
+        } else {
+            None
+        };
+    }
+    Some(Some(Container {
+        name: name.to_owned(),
+        classes,
+        title: Some(title.to_owned()).filter(|title| !title.is_empty()),
+        attributes: Attributes::default(),
+    }))
+}
+
+impl Container {
+    /// The kind of admonition this is, if any: a known kind like `note`, the
+    /// first class of a `callout{class}`, or any container with a title.
+    fn admonition(&self) -> Option<&str> {
+        match self.name.as_str() {
+            "callout" => Some(self.classes.first().map_or("callout", String::as_str)),
+            name if ADMONITIONS.contains(&name) || self.title.is_some() => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The HTML opening the container.
+    pub fn open_tag(&self) -> String {
+        if self.name.is_empty() {
+            return format!("<div{}>\n", self.attributes);
+        }
+        if self.name == "details" {
+            return self.details_tag();
+        }
+        let admonition = match self.admonition() {
+            Some(kind) => kind,
+            None => {
+                let classes = std::iter::once(&self.name)
+                    .chain(&self.classes)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                return format!("<div class=\"{}\">\n", html_escape(&classes));
+            }
+        };
+        let mut classes = vec!["admonition"];
+        if self.name != "callout" || self.classes.is_empty() {
+            classes.push(&self.name);
+        }
+        classes.extend(self.classes.iter().map(String::as_str));
+        let title = match &self.title {
+            Some(title) => inline_html(title),
+            None => html_escape(&capitalize(admonition)),
+        };
+        format!(
+            "<div class=\"{}\">\n<p class=\"admonition-title\">{}</p>\n",
+            html_escape(&classes.join(" ")),
+            title
+        )
+    }
+
+    /// The HTML opening a `:::details{open} Summary` block, which is
+    /// expanded to begin with if it has the `open` flag.
+    fn details_tag(&self) -> String {
+        let mut html = String::from("<details");
+        let classes: Vec<_> = self
+            .classes
+            .iter()
+            .filter(|class| *class != "open")
+            .map(String::as_str)
+            .collect();
+        if !classes.is_empty() {
+            html.push_str(&format!(" class=\"{}\"", html_escape(&classes.join(" "))));
+        }
+        if self.classes.iter().any(|class| class == "open") {
+            html.push_str(" open");
+        }
+        html.push_str(">\n");
+        if let Some(title) = &self.title {
+            html.push_str(&format!("<summary>{}</summary>\n", inline_html(title)));
+        }
+        html
+    }
+
+    /// The HTML closing the container.
+    pub fn close_tag(&self) -> &'static str {
+        if self.name == "details" {
+            "</details>\n"
+        } else {
+            "</div>\n"
+        }
+    }
+}
+
+/// Renders a container's title, which may have inline Markdown like
+/// `**bold**` text. Anything that parses as raw HTML is escaped, since the
+/// title's raw HTML was already text.
+fn inline_html(title: &str) -> String {
+    let mut ret = String::new();
+    let events =
+        Parser::new_ext(title, Options::ENABLE_STRIKETHROUGH).filter_map(|event| match event {
+            Event::Start(Tag::Paragraph) | Event::End(Tag::Paragraph) => None,
+            Event::Html(html) => Some(Event::Text(html)),
+            event => Some(event),
+        });
+    html::push_html(&mut ret, events);
+    ret.trim_end().to_owned()
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
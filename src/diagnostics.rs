@@ -0,0 +1,43 @@
+//! Collecting the warnings a build emits, for `--deny-warnings`.
+
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+use tracing::{event, Level};
+
+/// The warnings emitted while building. Clones share the same warnings, so
+/// one can be handed to each part of the build, including the template
+/// helpers, which have to be `Send`.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    warnings: Arc<Mutex<Vec<String>>>,
+}
+
+impl Diagnostics {
+    /// Logs a warning and keeps it.
+    pub fn warn(&self, message: impl Display) {
+        let message = message.to_string();
+        event!(Level::WARN, "{}", message);
+        self.warnings.lock().unwrap().push(message);
+    }
+
+    /// The warnings, in the order they were emitted, leaving none kept.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.warnings.lock().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_warnings() {
+        let diagnostics = Diagnostics::default();
+        let clone = diagnostics.clone();
+        diagnostics.warn("first");
+        clone.warn(format_args!("second {}", 2));
+        assert_eq!(clone.take(), vec!["first", "second 2"]);
+        assert!(diagnostics.take().is_empty());
+    }
+}
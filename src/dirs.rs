@@ -0,0 +1,51 @@
+//! Where global config files and caches go: `simple-slideshow` in
+//! `$XDG_CONFIG_HOME` and `$XDG_CACHE_HOME`, or the platform's usual
+//! directories for them if those aren't set.
+
+use std::env;
+use std::path::PathBuf;
+
+const NAME: &str = "simple-slideshow";
+
+/// The directory for global config files and templates.
+pub fn config_dir() -> Option<PathBuf> {
+    let platform = if cfg!(windows) {
+        absolute_var("APPDATA").map(|dir| dir.join(NAME).join("config"))
+    } else if cfg!(target_os = "macos") {
+        home(&["Library", "Application Support", NAME])
+    } else {
+        home(&[".config", NAME])
+    };
+    absolute_var("XDG_CONFIG_HOME")
+        .map(|dir| dir.join(NAME))
+        .or(platform)
+}
+
+/// The directory for caches, which can be deleted at any time.
+pub fn cache_dir() -> Option<PathBuf> {
+    let platform = if cfg!(windows) {
+        absolute_var("LOCALAPPDATA").map(|dir| dir.join(NAME).join("cache"))
+    } else if cfg!(target_os = "macos") {
+        home(&["Library", "Caches", NAME])
+    } else {
+        home(&[".cache", NAME])
+    };
+    absolute_var("XDG_CACHE_HOME")
+        .map(|dir| dir.join(NAME))
+        .or(platform)
+}
+
+/// A directory from an environment variable, which has to be absolute.
+fn absolute_var(var: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+}
+
+/// `components` in the home directory.
+fn home(components: &[&str]) -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    let mut dir = absolute_var(var)?;
+    dir.extend(components);
+    Some(dir)
+}
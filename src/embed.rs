@@ -0,0 +1,234 @@
+//! Inlining the stylesheets, scripts, and media a rendered slideshow refers
+//! to, for `--self-contained` output that works from a single file.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use regex::{Captures, Regex};
+
+use crate::assets;
+use crate::diagnostics::Diagnostics;
+
+pub struct Embedder<'a> {
+    /// Directories to look for referenced files in, in order.
+    pub roots: Vec<&'a Path>,
+    /// The slideshow's base URL, which references may start with.
+    pub base_url: Option<&'a str>,
+    /// Warn about inlining files larger than this many bytes.
+    pub warn_size: u64,
+    pub diagnostics: &'a Diagnostics,
+}
+
+impl Embedder<'_> {
+    /// Inlines the files referenced by `html`, returning it and the
+    /// references which couldn't be resolved, which are left alone.
+    pub fn embed(&self, html: &str) -> (String, Vec<String>) {
+        let mut unresolved = Vec::new();
+
+        let script = Regex::new(
+            r#"(?is)<script\b([^>]*?)\ssrc\s*=\s*(?:"([^"]*)"|'([^']*)')([^>]*)>\s*</script\s*>"#,
+        )
+        .unwrap();
+        let html = script.replace_all(html, |caps: &Captures| {
+            let dest = quoted(caps, 2);
+            match self.read(&self.roots, &dest, &mut unresolved) {
+                Some((_, bytes)) => format!(
+                    "<script{}{}>{}</script>",
+                    &caps[1],
+                    &caps[4],
+                    String::from_utf8_lossy(&bytes).replace("</script", "<\\/script")
+                ),
+                None => caps[0].to_owned(),
+            }
+        });
+
+        let link = Regex::new(r"(?is)<link\b[^>]*>").unwrap();
+        let html = link.replace_all(&html, |caps: &Captures| {
+            let tag = &caps[0];
+            let rel = attribute(tag, "rel").unwrap_or_default();
+            if !rel.split_whitespace().any(|rel| rel == "stylesheet") {
+                return self.embed_attributes(tag, &mut unresolved);
+            }
+            let dest = match attribute(tag, "href") {
+                Some(dest) => dest,
+                None => return tag.to_owned(),
+            };
+            match self.read(&self.roots, &dest, &mut unresolved) {
+                Some((path, css)) => {
+                    let css = String::from_utf8_lossy(&css);
+                    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+                    let css = self.embed_css(&css, dir, &mut unresolved);
+                    match attribute(tag, "media") {
+                        Some(media) => format!(r#"<style media="{}">{}</style>"#, media, css),
+                        None => format!("<style>{}</style>", css),
+                    }
+                }
+                None => tag.to_owned(),
+            }
+        });
+
+        let media = Regex::new(r"(?is)<(?:img|video|audio|source|track|input)\b[^>]*>").unwrap();
+        let html = media
+            .replace_all(&html, |caps: &Captures| {
+                self.embed_attributes(&caps[0], &mut unresolved)
+            })
+            .into_owned();
+
+        unresolved.dedup();
+        (html, unresolved)
+    }
+
+    /// Replaces the `src`, `href`, and `poster` attributes in an HTML `tag`
+    /// with `data:` URLs.
+    fn embed_attributes(&self, tag: &str, unresolved: &mut Vec<String>) -> String {
+        let attribute =
+            Regex::new(r#"(?is)(\s(?:src|href|poster)\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        attribute
+            .replace_all(tag, |caps: &Captures| {
+                let dest = quoted(caps, 2);
+                match self.read(&self.roots, &dest, unresolved) {
+                    Some((path, bytes)) => format!(r#"{}"{}""#, &caps[1], data_url(&path, &bytes)),
+                    None => caps[0].to_owned(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Replaces the `url(...)`s in a stylesheet in `dir`, like its fonts,
+    /// with `data:` URLs.
+    fn embed_css(&self, css: &str, dir: &Path, unresolved: &mut Vec<String>) -> String {
+        let url = Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)'"\s]*))\s*\)"#).unwrap();
+        url.replace_all(css, |caps: &Captures| {
+            let dest = caps
+                .get(1)
+                .or_else(|| caps.get(2))
+                .or_else(|| caps.get(3))
+                .map_or("", |dest| dest.as_str());
+            match self.read(&[dir], dest, unresolved) {
+                Some((path, bytes)) => format!(r#"url("{}")"#, data_url(&path, &bytes)),
+                None => caps[0].to_owned(),
+            }
+        })
+        .into_owned()
+    }
+
+    /// Reads the local file `dest` refers to, in the first of `roots` it's
+    /// in. URLs and `#anchors` are left alone; other references which can't
+    /// be found are added to `unresolved`.
+    fn read(
+        &self,
+        roots: &[&Path],
+        dest: &str,
+        unresolved: &mut Vec<String>,
+    ) -> Option<(PathBuf, Vec<u8>)> {
+        let local = self
+            .base_url
+            .and_then(|base_url| dest.strip_prefix(base_url))
+            .unwrap_or(dest)
+            .trim_start_matches('/');
+        let rel = match assets::local_path(local, self.diagnostics) {
+            Some(rel) => rel,
+            None => {
+                if !local.is_empty() && !dest.starts_with('#') && !dest.contains(':') {
+                    unresolved.push(dest.to_owned());
+                }
+                return None;
+            }
+        };
+        let found = roots
+            .iter()
+            .map(|root| root.join(&rel))
+            .find(|path| path.is_file())
+            .and_then(|path| match fs::read(&path) {
+                Ok(bytes) => Some((path, bytes)),
+                Err(err) => {
+                    self.diagnostics.warn(format_args!(
+                        "Failed to read {} to inline: {}",
+                        path.display(),
+                        err
+                    ));
+                    None
+                }
+            });
+        match &found {
+            Some((path, bytes)) if bytes.len() as u64 > self.warn_size => {
+                self.diagnostics.warn(format_args!(
+                    "Inlining {} from {}, which is {} KiB",
+                    dest,
+                    path.display(),
+                    bytes.len() / 1024
+                ));
+            }
+            Some(_) => {}
+            None => unresolved.push(dest.to_owned()),
+        }
+        found
+    }
+}
+
+/// The reference in the first non-empty group of `caps` from `group` on,
+/// which are the alternative quotings of an attribute's value.
+fn quoted<'c>(caps: &'c Captures, group: usize) -> Cow<'c, str> {
+    let value = caps
+        .get(group)
+        .or_else(|| caps.get(group + 1))
+        .map_or("", |value| value.as_str());
+    if value.contains("&amp;") {
+        Cow::Owned(value.replace("&amp;", "&"))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// The value of an attribute in an HTML `tag`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let pattern = format!(
+        r#"(?is)\s{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+        regex::escape(name)
+    );
+    let caps = Regex::new(&pattern).unwrap().captures(tag)?;
+    Some(quoted(&caps, 1).into_owned())
+}
+
+fn data_url(path: &Path, bytes: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        mime_type(path),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// The MIME type of a file, by its extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("ogg") | Some("ogv") => "video/ogg",
+        Some("mp3") => "audio/mpeg",
+        Some("wav") => "audio/wav",
+        Some("vtt") => "text/vtt",
+        Some("html") | Some("htm") => "text/html",
+        Some("json") => "application/json",
+        Some("txt") | Some("md") => "text/plain",
+        Some("pdf") => "application/pdf",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,38 @@
+//! GitHub-style emoji shortcodes, like `:rocket:`.
+
+/// Replaces the shortcodes in `text` with the emoji they name, if there are
+/// any. Unknown shortcodes are left as-is.
+pub fn replace(text: &str) -> Option<String> {
+    if !text.contains(':') {
+        return None;
+    }
+    let mut ret = String::with_capacity(text.len());
+    let mut replaced = false;
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        ret.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let emoji = after
+            .find(':')
+            .filter(|&end| end > 0 && after[..end].chars().all(is_shortcode_char))
+            .and_then(|end| Some((end, emojis::get_by_shortcode(&after[..end])?)));
+        match emoji {
+            Some((end, emoji)) => {
+                ret.push_str(emoji.as_str());
+                replaced = true;
+                rest = &after[end + 1..];
+            }
+            None => {
+                // The next colon might start a shortcode.
+                ret.push(':');
+                rest = after;
+            }
+        }
+    }
+    ret.push_str(rest);
+    Some(ret).filter(|_| replaced)
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+-".contains(c)
+}
@@ -0,0 +1,238 @@
+//! External filters, for `--filter`: commands which get the rendered slides
+//! as JSON on standard input, and write them back, changed, to standard
+//! output before the template is rendered.
+
+use std::io::{self, Read, Write};
+use std::process::{ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::front_matter::FrontMatter;
+use crate::hooks;
+
+/// The version of the JSON format. Filters written for one version keep
+/// working until it changes, so new fields don't change it, but removing or
+/// changing one does.
+pub const VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("Couldn't run the filter `{0}`: {1}")]
+    Spawn(String, #[source] io::Error),
+
+    #[error("Error passing the slides to the filter `{0}`: {1}")]
+    Io(String, #[source] io::Error),
+
+    #[error("The filter `{command}` failed with {status}: {stderr}")]
+    Failed {
+        command: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+
+    #[error("The filter `{0}` didn't finish within {1:?}")]
+    Timeout(String, Duration),
+
+    #[error("The filter `{0}` wrote invalid JSON: {1}")]
+    Json(String, #[source] serde_json::Error),
+
+    #[error("The filter `{command}` wrote {found} slides, but was given {expected}")]
+    SlideCount {
+        command: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// What filters read and write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub version: u32,
+    /// The input file's name.
+    pub input_file: String,
+    /// The slideshow's title, from its front matter or first heading.
+    pub title: String,
+    /// The front matter, data file, and `--var` variables the template gets.
+    pub metadata: FrontMatter,
+    pub slides: Vec<Slide>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Slide {
+    /// The slide's number in the rendered deck, from 1. Filters can't
+    /// renumber slides; it's ignored in what they write.
+    pub number: usize,
+    pub id: String,
+    /// The text of the slide's first heading.
+    pub title: Option<String>,
+    /// The slide's `<section>`.
+    pub html: String,
+    /// The slide's speaker notes as HTML, or nothing.
+    pub notes: String,
+}
+
+/// Passes `document` through `command`, run with the shell, which has
+/// `timeout` to finish. Filters can change anything but the number of
+/// slides.
+pub fn run(command: &str, document: &Document, timeout: Duration) -> Result<Document, FilterError> {
+    event!(Level::INFO, "Filtering the slides through `{}`", command);
+    let io_error = |e| FilterError::Io(command.to_owned(), e);
+    let input =
+        serde_json::to_vec(document).map_err(|e| FilterError::Json(command.to_owned(), e))?;
+    let mut child = hooks::shell(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| FilterError::Spawn(command.to_owned(), e))?;
+
+    // Reading and writing happen on their own threads, so a filter which
+    // writes before it's read everything can't deadlock with us.
+    let mut stdin = child.stdin.take();
+    let writer = thread::spawn(move || match stdin.as_mut() {
+        Some(stdin) => stdin.write_all(&input),
+        None => Ok(()),
+    });
+    let read_all = |output: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(mut output) = output {
+                output.read_to_end(&mut buffer)?;
+            }
+            Ok::<_, io::Error>(buffer)
+        })
+    };
+    let stdout = read_all(child.stdout.take().map(|s| Box::new(s) as _));
+    let stderr = read_all(child.stderr.take().map(|s| Box::new(s) as _));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(io_error)? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(FilterError::Timeout(command.to_owned(), timeout));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let written = writer.join().unwrap_or(Ok(()));
+    let stdout = stdout
+        .join()
+        .unwrap_or_else(|_| Ok(Vec::new()))
+        .map_err(io_error)?;
+    let stderr = stderr
+        .join()
+        .unwrap_or_else(|_| Ok(Vec::new()))
+        .map_err(io_error)?;
+    if !status.success() {
+        return Err(FilterError::Failed {
+            command: command.to_owned(),
+            status,
+            stderr: String::from_utf8_lossy(&stderr).trim().to_owned(),
+        });
+    }
+    // A filter which exits without reading everything closes the pipe on
+    // us, which is fine if it succeeded.
+    if let Err(err) = written {
+        if err.kind() != io::ErrorKind::BrokenPipe {
+            return Err(io_error(err));
+        }
+    }
+
+    let mut filtered: Document =
+        serde_json::from_slice(&stdout).map_err(|e| FilterError::Json(command.to_owned(), e))?;
+    if filtered.slides.len() != document.slides.len() {
+        return Err(FilterError::SlideCount {
+            command: command.to_owned(),
+            expected: document.slides.len(),
+            found: filtered.slides.len(),
+        });
+    }
+    for (slide, original) in filtered.slides.iter_mut().zip(&document.slides) {
+        slide.number = original.number;
+    }
+    filtered.version = VERSION;
+    Ok(filtered)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn document() -> Document {
+        Document {
+            version: VERSION,
+            input_file: "slides.md".to_owned(),
+            title: "Talk".to_owned(),
+            metadata: FrontMatter::new(),
+            slides: vec![Slide {
+                number: 1,
+                id: "slide-1".to_owned(),
+                title: None,
+                html: "<section class=\"slide\">Hi</section>\n".to_owned(),
+                notes: String::new(),
+            }],
+        }
+    }
+
+    fn filter(command: &str) -> Result<Document, FilterError> {
+        run(command, &document(), Duration::from_secs(10))
+    }
+
+    #[test]
+    fn changes_slides() {
+        let filtered = filter("sed s/Hi/Hello/").unwrap();
+        assert_eq!(
+            filtered.slides[0].html,
+            "<section class=\"slide\">Hello</section>\n"
+        );
+    }
+
+    #[test]
+    fn failure_has_stderr() {
+        match filter("cat >/dev/null; echo oops >&2; exit 2") {
+            Err(FilterError::Failed { status, stderr, .. }) => {
+                assert_eq!(status.code(), Some(2));
+                assert_eq!(stderr, "oops");
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_json() {
+        match filter("cat >/dev/null; echo '{'") {
+            Err(FilterError::Json(command, _)) => assert_eq!(command, "cat >/dev/null; echo '{'"),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn changed_slide_count() {
+        match filter(r#"sed 's/"slides":.*/"slides":[]}/'"#) {
+            Err(FilterError::SlideCount {
+                expected, found, ..
+            }) => assert_eq!((expected, found), (1, 0)),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let start = Instant::now();
+        match run("sleep 5", &document(), Duration::from_millis(100)) {
+            Err(err @ FilterError::Timeout(..)) => assert_eq!(
+                err.to_string(),
+                "The filter `sleep 5` didn't finish within 100ms"
+            ),
+            other => panic!("{:?}", other),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,119 @@
+//! Content hashes in static files' names, like `style.1a2b3c4d.css`, so
+//! browsers don't keep using stale copies; for `--fingerprint`.
+
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+
+use fnv::FnvHasher;
+use regex::{Captures, Regex};
+
+/// Fingerprinted file names, by the original names, both relative to the
+/// static files directory and separated by slashes.
+pub type Manifest = BTreeMap<String, String>;
+
+/// A static file, by its name relative to the static files directory.
+pub struct StaticFile {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// Renames `files` with hashes of their contents, rewriting the `url(...)`s
+/// in stylesheets to the new names. Returns the renamed files and the
+/// manifest of new names.
+pub fn fingerprint(files: Vec<StaticFile>) -> (Vec<StaticFile>, Manifest) {
+    let (stylesheets, others): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|file| file.name.ends_with(".css"));
+    let mut manifest = Manifest::new();
+    let mut ret = Vec::with_capacity(stylesheets.len() + others.len());
+    for file in others {
+        let name = fingerprinted_name(&file.name, &file.contents);
+        manifest.insert(file.name, name.clone());
+        ret.push(StaticFile {
+            name,
+            contents: file.contents,
+        });
+    }
+    // Stylesheets are hashed after they're rewritten, so that they change
+    // when the files they refer to do.
+    for file in stylesheets {
+        let css = rewrite_css(
+            &String::from_utf8_lossy(&file.contents),
+            &file.name,
+            &manifest,
+        );
+        let name = fingerprinted_name(&file.name, css.as_bytes());
+        manifest.insert(file.name, name.clone());
+        ret.push(StaticFile {
+            name,
+            contents: css.into_bytes(),
+        });
+    }
+    (ret, manifest)
+}
+
+/// Adds a hash of `contents` before the extension in `name`.
+fn fingerprinted_name(name: &str, contents: &[u8]) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(contents);
+    let hash = format!("{:016x}", hasher.finish());
+    let hash = &hash[..8];
+    let file_start = name.rfind('/').map_or(0, |i| i + 1);
+    match name[file_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let dot = file_start + dot;
+            format!("{}.{}{}", &name[..dot], hash, &name[dot..])
+        }
+        _ => format!("{}.{}", name, hash),
+    }
+}
+
+/// Rewrites the `url(...)`s in the stylesheet `name` which refer to files in
+/// `manifest`.
+fn rewrite_css(css: &str, name: &str, manifest: &Manifest) -> String {
+    let url = Regex::new(r#"url\(\s*(["']?)([^)"'\s]+)(["']?)\s*\)"#).unwrap();
+    let dir = name.rfind('/').map_or("", |i| &name[..i]);
+    url.replace_all(css, |caps: &Captures| {
+        let reference = &caps[2];
+        let end = reference.find(['?', '#']).unwrap_or(reference.len());
+        let (path, suffix) = reference.split_at(end);
+        match resolve(dir, path).and_then(|key| manifest.get(&key)) {
+            Some(fingerprinted) => {
+                let file = fingerprinted.rsplit('/').next().unwrap_or(fingerprinted);
+                let file_start = path.rfind('/').map_or(0, |i| i + 1);
+                format!(
+                    "url({}{}{}{}{})",
+                    &caps[1],
+                    &path[..file_start],
+                    file,
+                    suffix,
+                    &caps[3]
+                )
+            }
+            None => caps[0].to_owned(),
+        }
+    })
+    .into_owned()
+}
+
+/// The name relative to the static files directory of a `reference` in a
+/// file in `dir`, if it's a local path.
+fn resolve(dir: &str, reference: &str) -> Option<String> {
+    if reference.contains(':') || reference.starts_with("//") {
+        return None;
+    }
+    let mut parts: Vec<&str> = match reference.strip_prefix('/') {
+        Some(_) => Vec::new(),
+        None => dir.split('/').filter(|part| !part.is_empty()).collect(),
+    };
+    for part in reference.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
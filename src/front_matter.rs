@@ -0,0 +1,79 @@
+//! YAML front matter at the top of a slideshow, e.g.:
+//!
+//! ```markdown
+//! ---
+//! title: My talk
+//! author: Me
+//! ---
+//!
+//! # First slide
+//! ```
+
+use serde_json::{Map, Value};
+
+use crate::diagnostics::Diagnostics;
+
+/// Top-level front matter keys and their values, passed through to the
+/// template.
+pub type FrontMatter = Map<String, Value>;
+
+/// Splits the front matter off of `input`, returning the parsed front matter
+/// (empty if there isn't any) and the remaining Markdown.
+///
+/// Because `---` is also a slide separator, a leading `---`-delimited block
+/// is only treated as front matter if it parses as a YAML mapping; anything
+/// else is left alone and rendered as Markdown.
+pub fn split<'a>(input: &'a str, diagnostics: &Diagnostics) -> (FrontMatter, &'a str) {
+    match delimited(input) {
+        Some((yaml, rest)) => match parse(yaml, diagnostics) {
+            Some(front_matter) => (front_matter, rest),
+            None => (FrontMatter::new(), input),
+        },
+        None => (FrontMatter::new(), input),
+    }
+}
+
+/// Finds a block delimited by `---` lines at the start of `input`, returning
+/// the block's contents and the text after the closing delimiter.
+fn delimited(input: &str) -> Option<(&str, &str)> {
+    let input = input.trim_start_matches('\u{feff}');
+    let body = input
+        .strip_prefix("---\n")
+        .or_else(|| input.strip_prefix("---\r\n"))?;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((&body[..offset], &body[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn parse(yaml: &str, diagnostics: &Diagnostics) -> Option<FrontMatter> {
+    let value = match serde_yaml::from_str::<serde_yaml::Value>(yaml) {
+        Ok(value) => value,
+        Err(err) => {
+            diagnostics.warn(format_args!(
+                "Leading block isn't valid YAML, so it isn't front matter: {}",
+                err
+            ));
+            return None;
+        }
+    };
+    if !value.is_mapping() {
+        return None;
+    }
+    match serde_json::to_value(value) {
+        Ok(Value::Object(map)) => Some(map),
+        Ok(_) => None,
+        Err(err) => {
+            diagnostics.warn(format_args!(
+                "Front matter can't be passed to the template: {}",
+                err
+            ));
+            None
+        }
+    }
+}
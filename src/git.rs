@@ -0,0 +1,50 @@
+//! The git commit a slideshow is built from, for the template.
+
+use std::path::Path;
+use std::process::Command;
+
+use tracing::{event, Level};
+
+#[derive(Debug, Clone)]
+pub struct GitMetadata {
+    /// The abbreviated hash of the checked out commit.
+    pub commit: String,
+    /// Are there uncommitted changes to tracked files?
+    pub dirty: bool,
+    /// The checked out branch, unless `HEAD` is detached.
+    pub branch: Option<String>,
+}
+
+/// The metadata of the repository `dir` is in, if it's in one with a commit
+/// checked out and git is installed.
+pub fn metadata(dir: &Path) -> Option<GitMetadata> {
+    let commit = git(dir, &["rev-parse", "--short", "HEAD"])?;
+    let dirty = !git(dir, &["status", "--porcelain", "--untracked-files=no"])?.is_empty();
+    let branch = git(dir, &["symbolic-ref", "--short", "-q", "HEAD"]);
+    Some(GitMetadata {
+        commit,
+        dirty,
+        branch,
+    })
+}
+
+/// Runs git in `dir`, returning its trimmed output if it succeeds.
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = match Command::new("git").arg("-C").arg(dir).args(args).output() {
+        Ok(output) => output,
+        Err(err) => {
+            event!(Level::DEBUG, %err, "Couldn't run git");
+            return None;
+        }
+    };
+    if !output.status.success() {
+        event!(
+            Level::DEBUG,
+            ?args,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "git failed"
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
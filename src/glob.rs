@@ -0,0 +1,151 @@
+//! Expanding glob patterns like `slides/*.md` into input files, so they work
+//! the same whether or not the shell expands them.
+
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use thiserror::Error;
+use walkdir::WalkDir;
+
+#[derive(Error, Debug)]
+pub enum GlobErr {
+    #[error("Invalid glob pattern {0:?}: {1}")]
+    Pattern(String, #[source] regex::Error),
+
+    #[error("No files match {0:?}")]
+    NoMatches(String),
+
+    #[error("Error finding files matching {0:?}: {1}")]
+    Walk(String, #[source] walkdir::Error),
+}
+
+const SPECIAL: &[char] = &['*', '?', '[', '{'];
+
+/// Is `path` a glob pattern, rather than a file?
+pub fn is_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(SPECIAL)
+}
+
+/// The directory a pattern's matches are all in: its leading components
+/// without any wildcards.
+pub fn base(pattern: &str) -> PathBuf {
+    split(pattern).0
+}
+
+/// Splits a pattern into its base directory and the rest of it, with `/`
+/// between its components.
+fn split(pattern: &str) -> (PathBuf, String) {
+    let mut base = PathBuf::new();
+    let mut rest = Vec::new();
+    for component in Path::new(pattern).components() {
+        let name = component.as_os_str().to_string_lossy();
+        if rest.is_empty() && !name.contains(SPECIAL) {
+            base.push(component);
+        } else {
+            rest.push(name.into_owned());
+        }
+    }
+    if base.as_os_str().is_empty() {
+        base.push(".");
+    }
+    (base, rest.join("/"))
+}
+
+/// The files matching `pattern`, in order, leaving out any in `exclude`.
+///
+/// `*` and `?` match any characters and any one character in a file name,
+/// `**` matches any number of directories, `[abc]` and `[!abc]` match any
+/// (other) character of those, and `{a,b}` matches either `a` or `b`.
+pub fn expand(pattern: &str, exclude: &Path) -> Result<Vec<PathBuf>, GlobErr> {
+    let (base, rest) = split(pattern);
+    let regex = to_regex(&rest).map_err(|e| GlobErr::Pattern(pattern.to_owned(), e))?;
+
+    let exclude = exclude
+        .canonicalize()
+        .unwrap_or_else(|_| exclude.to_owned());
+    let mut walk = WalkDir::new(&base).follow_links(true).min_depth(1);
+    if !rest.contains("**") {
+        walk = walk.max_depth(rest.split('/').count());
+    }
+    let mut ret = Vec::new();
+    if base.exists() {
+        let walk = walk.into_iter().filter_entry(|entry| {
+            entry
+                .path()
+                .canonicalize()
+                .map_or(true, |path| !path.starts_with(&exclude))
+        });
+        for entry in walk {
+            let entry = entry.map_err(|e| GlobErr::Walk(pattern.to_owned(), e))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            // Match with `/` between components on every platform.
+            let relative = entry
+                .path()
+                .strip_prefix(&base)
+                .unwrap_or_else(|_| entry.path())
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if regex.is_match(&relative) {
+                ret.push(entry.into_path());
+            }
+        }
+    }
+    if ret.is_empty() {
+        return Err(GlobErr::NoMatches(pattern.to_owned()));
+    }
+    ret.sort();
+    Ok(ret)
+}
+
+/// A regex matching the paths `pattern` matches.
+fn to_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:[^/]+/)*");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => break,
+                        '-' => regex.push('-'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push(']');
+            }
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex)
+}
@@ -0,0 +1,582 @@
+//! Handlebars helpers for templates: `{{include "file.html"}}`, `{{inline
+//! "static/style.css"}}`, `{{markdown field}}`, `{{date_format date
+//! "%B %e, %Y"}}`, `{{slugify title}}`, `{{urlencode text}}`, `{{env
+//! "NAME"}}`, `{{asset "style.css"}}`, and `{{#inline_css "critical.css"}}`
+//! and `{{#inline_js "app.js"}}` blocks.
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderError,
+    Renderable,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::Value;
+use tracing::{event, Level};
+
+use crate::markdown::{self, RenderOptions};
+use crate::minify;
+use crate::slug::slugify;
+
+/// What `urlencode` escapes: everything but the characters
+/// `encodeURIComponent` leaves alone.
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// Registers the helpers. `include` paths are relative to `template_dir`,
+/// and `inline` paths must be within the static directory.
+pub fn register(reg: &mut Handlebars<'_>, template_dir: PathBuf, render_options: &RenderOptions) {
+    reg.register_helper(
+        "include",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let path = template_dir.join(path_param(h)?);
+                out.write(&read(&path)?)?;
+                Ok(())
+            },
+        ),
+    );
+
+    let static_dirs = render_options
+        .static_dirs
+        .iter()
+        .map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.clone()))
+        .collect::<Vec<_>>();
+    reg.register_helper(
+        "inline_css",
+        Box::new(InlineBlock {
+            static_dirs: static_dirs.clone(),
+            kind: InlineKind::Css,
+        }),
+    );
+    reg.register_helper(
+        "inline_js",
+        Box::new(InlineBlock {
+            static_dirs: static_dirs.clone(),
+            kind: InlineKind::Js,
+        }),
+    );
+    reg.register_helper(
+        "inline",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let name = path_param(h)?;
+                let outside = || {
+                    let dirs = static_dirs
+                        .iter()
+                        .map(|dir| dir.display().to_string())
+                        .collect::<Vec<_>>();
+                    RenderError::new(format!(
+                        "{} is outside of the static directory {}",
+                        name,
+                        dirs.join(", ")
+                    ))
+                };
+                // Relative paths are in the static directories, like
+                // `inline_css`'s, from the last one with the file.
+                let path = if Path::new(name).is_absolute() {
+                    PathBuf::from(name)
+                } else {
+                    let paths = static_dirs
+                        .iter()
+                        .rev()
+                        .filter_map(|dir| static_path(dir, name))
+                        .collect::<Vec<_>>();
+                    paths
+                        .iter()
+                        .find(|path| path.is_file())
+                        .or_else(|| paths.first())
+                        .cloned()
+                        .ok_or_else(outside)?
+                };
+                let canonical = path.canonicalize().map_err(|e| {
+                    RenderError::new(format!("Error reading {}: {}", path.display(), e))
+                })?;
+                if !static_dirs.iter().any(|dir| canonical.starts_with(dir)) {
+                    return Err(outside());
+                }
+                out.write(&read(&canonical)?)?;
+                Ok(())
+            },
+        ),
+    );
+
+    let allow_env = render_options.allow_env.clone();
+    reg.register_helper(
+        "env",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let name = h
+                    .param(0)
+                    .and_then(|param| param.value().as_str())
+                    .ok_or_else(|| RenderError::new("`env` needs a variable name"))?;
+                let allowed = match &allow_env {
+                    None => {
+                        return Err(RenderError::new(format!(
+                            "`env` can't read {} without `--allow-env`",
+                            name
+                        )))
+                    }
+                    Some(patterns) => {
+                        patterns.is_empty()
+                            || patterns.iter().any(|pattern| env_matches(pattern, name))
+                    }
+                };
+                if !allowed {
+                    return Err(RenderError::new(format!(
+                        "`env` can't read {}, which `--allow-env` doesn't allow",
+                        name
+                    )));
+                }
+                match env::var(name) {
+                    Ok(value) => out.write(&value)?,
+                    Err(err) => event!(Level::DEBUG, name, %err, "Environment variable not set"),
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    let fingerprints = render_options.fingerprints.clone();
+    reg.register_helper(
+        "asset",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let name = path_param(h)?;
+                let key = name.trim_start_matches('/');
+                match fingerprints.get(key) {
+                    Some(fingerprinted) => {
+                        out.write(&name[..name.len() - key.len()])?;
+                        out.write(fingerprinted)?;
+                    }
+                    None => out.write(name)?,
+                }
+                Ok(())
+            },
+        ),
+    );
+
+    let render_options = render_options.clone();
+    reg.register_helper(
+        "markdown",
+        Box::new(
+            move |h: &Helper,
+                  _: &Handlebars,
+                  _: &Context,
+                  _: &mut RenderContext,
+                  out: &mut dyn Output|
+                  -> HelperResult {
+                let param = h
+                    .param(0)
+                    .ok_or_else(|| RenderError::new("`markdown` needs a value to render"))?;
+                let markdown = match param.value() {
+                    Value::Null => return Ok(()),
+                    Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                };
+                out.write(&markdown::inline_markdown_html(&markdown, &render_options))?;
+                Ok(())
+            },
+        ),
+    );
+
+    reg.register_helper(
+        "date_format",
+        Box::new(
+            |h: &Helper,
+             _: &Handlebars,
+             _: &Context,
+             _: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let date = match h.param(0).map(|param| param.value()) {
+                    None | Some(Value::Null) => return Ok(()),
+                    Some(Value::String(date)) => date.as_str(),
+                    Some(value) => {
+                        return Err(RenderError::new(format!(
+                            "`date_format` needs a date, not {}",
+                            value
+                        )))
+                    }
+                };
+                let format = h
+                    .param(1)
+                    .and_then(|param| param.value().as_str())
+                    .ok_or_else(|| RenderError::new("`date_format` needs a format string"))?;
+                out.write(&date_format(date, format)?)?;
+                Ok(())
+            },
+        ),
+    );
+
+    reg.register_helper(
+        "slugify",
+        Box::new(
+            |h: &Helper,
+             _: &Handlebars,
+             _: &Context,
+             _: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                out.write(&slugify(&text_param(h)?))?;
+                Ok(())
+            },
+        ),
+    );
+
+    reg.register_helper(
+        "urlencode",
+        Box::new(
+            |h: &Helper,
+             _: &Handlebars,
+             _: &Context,
+             _: &mut RenderContext,
+             out: &mut dyn Output|
+             -> HelperResult {
+                let text = text_param(h)?;
+                out.write(&utf8_percent_encode(&text, URL_COMPONENT).to_string())?;
+                Ok(())
+            },
+        ),
+    );
+}
+
+#[derive(Debug, Clone, Copy)]
+enum InlineKind {
+    Css,
+    Js,
+}
+
+/// A block helper like `{{#inline_css "critical.css"}}{{/inline_css}}`,
+/// which writes a file in the static directory into a `<style>` or
+/// `<script>`, followed by the block's contents. Stylesheets are minified.
+struct InlineBlock {
+    static_dirs: Vec<PathBuf>,
+    kind: InlineKind,
+}
+
+impl HelperDef for InlineBlock {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        rc: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = path_param(h)?;
+        // The file in the last static directory with one, like the one
+        // that's copied.
+        let paths = self
+            .static_dirs
+            .iter()
+            .rev()
+            .map(|dir| static_path(dir, name))
+            .collect::<Option<Vec<_>>>()
+            .filter(|paths| !paths.is_empty())
+            .ok_or_else(|| {
+                RenderError::new(format!(
+                    "`{}` needs a path within the static directory, not {}",
+                    h.name(),
+                    name
+                ))
+            })?;
+        let path = paths
+            .iter()
+            .find(|path| path.is_file())
+            .unwrap_or(&paths[0]);
+        let source = read(path)?;
+        let (open, contents, close) = match self.kind {
+            InlineKind::Css => ("<style>", minify::css(&source), "</style>"),
+            InlineKind::Js => (
+                "<script>",
+                source.replace("</script", "<\\/script"),
+                "</script>",
+            ),
+        };
+        event!(
+            Level::INFO,
+            path = ?path,
+            bytes = contents.len(),
+            "Inlining {} ({} bytes)",
+            name,
+            contents.len()
+        );
+        out.write(open)?;
+        out.write(&contents)?;
+        if let Some(template) = h.template() {
+            template.render(r, ctx, rc, out)?;
+        }
+        out.write(close)?;
+        Ok(())
+    }
+}
+
+/// The file `name` refers to in `static_dir`, which it may start with, like
+/// `static/critical.css`. Paths leaving the static directory are refused.
+fn static_path(static_dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut rel = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(component) => rel.push(component),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    let path = static_dir.join(&rel);
+    let unprefixed = static_dir
+        .file_name()
+        .and_then(|dir| rel.strip_prefix(dir).ok())
+        .map(|rest| static_dir.join(rest));
+    match unprefixed {
+        Some(unprefixed) if !path.is_file() && unprefixed.is_file() => Some(unprefixed),
+        _ => Some(path),
+    }
+}
+
+/// Does an `--allow-env` pattern like `TALK_*` match an environment
+/// variable's `name`?
+fn env_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// The text given to a helper like `{{slugify title}}`, which is empty for
+/// missing values.
+fn text_param(h: &Helper) -> Result<String, RenderError> {
+    let param = h
+        .param(0)
+        .ok_or_else(|| RenderError::new(format!("`{}` needs a value", h.name())))?;
+    Ok(match param.value() {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    })
+}
+
+/// Formats an ISO date like `2024-09-12`, an RFC 3339 timestamp, or `today`
+/// with a `strftime`-style `format`.
+fn date_format(date: &str, format: &str) -> Result<String, RenderError> {
+    let mut ret = String::new();
+    // Formatting fails for invalid format strings, and for times of day in
+    // plain dates.
+    let written = if date == "today" {
+        write!(ret, "{}", Local::now().naive_local().date().format(format))
+    } else if let Ok(timestamp) = DateTime::parse_from_rfc3339(date) {
+        write!(ret, "{}", timestamp.format(format))
+    } else if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        write!(ret, "{}", date.format(format))
+    } else {
+        return Err(RenderError::new(format!(
+            "`date_format` expected a date like 2024-09-12, an RFC 3339 timestamp, or `today`, not {:?}",
+            date
+        )));
+    };
+    written.map_err(|_| {
+        RenderError::new(format!(
+            "`date_format` can't format {:?} with {:?}",
+            date, format
+        ))
+    })?;
+    Ok(ret)
+}
+
+/// The path given to a helper like `{{include "path"}}`.
+fn path_param<'h>(h: &'h Helper) -> Result<&'h str, RenderError> {
+    h.param(0)
+        .and_then(|param| param.value().as_str())
+        .ok_or_else(|| RenderError::new(format!("`{}` needs a path", h.name())))
+}
+
+fn read(path: &Path) -> Result<String, RenderError> {
+    fs::read_to_string(path)
+        .map_err(|e| RenderError::new(format!("Error reading {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    use serde_json::json;
+
+    /// A fresh directory for a test's files, with a `static` directory in it.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("builder-helpers-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("static")).unwrap();
+        dir
+    }
+
+    fn registry(dir: &Path) -> Handlebars<'static> {
+        let mut options = markdown::tests::options();
+        options.static_dirs = vec![dir.join("static")];
+        let mut reg = Handlebars::new();
+        register(&mut reg, dir.to_owned(), &options);
+        reg
+    }
+
+    #[test]
+    fn include_splices_file() {
+        let dir = test_dir("include");
+        fs::write(dir.join("footer.html"), "<footer>{{not rendered}}</footer>").unwrap();
+        let html = registry(&dir)
+            .render_template(r#"<body>{{include "footer.html"}}</body>"#, &json!({}))
+            .unwrap();
+        assert_eq!(html, "<body><footer>{{not rendered}}</footer></body>");
+    }
+
+    #[test]
+    fn include_missing_file_names_path() {
+        let dir = test_dir("include-missing");
+        let err = registry(&dir)
+            .render_template(r#"{{include "missing.html"}}"#, &json!({}))
+            .unwrap_err();
+        assert!(err.to_string().contains("missing.html"), "{}", err);
+    }
+
+    #[test]
+    fn inline_embeds_static_file() {
+        let dir = test_dir("inline");
+        let css = dir.join("static").join("style.css");
+        fs::write(&css, "body { color: red; }").unwrap();
+        let template = format!(r#"<style>{{{{inline "{}"}}}}</style>"#, css.display());
+        let html = registry(&dir)
+            .render_template(&template, &json!({}))
+            .unwrap();
+        assert_eq!(html, "<style>body { color: red; }</style>");
+    }
+
+    #[test]
+    fn inline_finds_relative_path_in_static_dir() {
+        let dir = test_dir("inline-relative");
+        fs::write(dir.join("static").join("style.css"), "body { color: red; }").unwrap();
+        // Rendered from somewhere other than the deck's directory.
+        assert_ne!(env::current_dir().unwrap(), dir);
+        let html = registry(&dir)
+            .render_template(
+                r#"<style>{{inline "static/style.css"}}</style>"#,
+                &json!({}),
+            )
+            .unwrap();
+        assert_eq!(html, "<style>body { color: red; }</style>");
+        let err = registry(&dir)
+            .render_template(r#"{{inline "static/../secret.txt"}}"#, &json!({}))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("outside of the static directory"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn inline_rejects_path_outside_static_dir() {
+        let dir = test_dir("inline-escape");
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let escape = dir.join("static").join("..").join("secret.txt");
+        let template = format!(r#"{{{{inline "{}"}}}}"#, escape.display());
+        let err = registry(&dir)
+            .render_template(&template, &json!({}))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("outside of the static directory"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn inline_missing_file_names_path() {
+        let dir = test_dir("inline-missing");
+        let missing = dir.join("static").join("missing.css");
+        let template = format!(r#"{{{{inline "{}"}}}}"#, missing.display());
+        let err = registry(&dir)
+            .render_template(&template, &json!({}))
+            .unwrap_err();
+        assert!(err.to_string().contains("missing.css"), "{}", err);
+    }
+
+    #[test]
+    fn markdown_renders_inline() {
+        let dir = test_dir("markdown");
+        let html = registry(&dir)
+            .render_template(
+                "<p>{{markdown tagline}}</p>",
+                &json!({"tagline": "A **bold** talk"}),
+            )
+            .unwrap();
+        assert_eq!(html, "<p>A <strong>bold</strong> talk</p>");
+    }
+
+    #[test]
+    fn slugify_matches_slide_ids() {
+        let dir = test_dir("slugify");
+        let title = "Café 🚀 au lait";
+        let html = registry(&dir)
+            .render_template("{{slugify title}}", &json!({ "title": title }))
+            .unwrap();
+        let slides = markdown::tests::slides(&format!("# {}", title));
+        assert_eq!(html, "cafe-au-lait");
+        assert!(slides[0].contains(r#"id="cafe-au-lait""#), "{}", slides[0]);
+    }
+
+    #[test]
+    fn slugify_missing_value_is_empty() {
+        let dir = test_dir("slugify-missing");
+        let html = registry(&dir)
+            .render_template("[{{slugify nothing}}]", &json!({}))
+            .unwrap();
+        assert_eq!(html, "[]");
+    }
+
+    #[test]
+    fn urlencode_escapes_component() {
+        let dir = test_dir("urlencode");
+        let html = registry(&dir)
+            .render_template(
+                "{{urlencode text}}",
+                &json!({"text": "Café & crème/slides?#1 (draft)"}),
+            )
+            .unwrap();
+        assert_eq!(
+            html,
+            "Caf%C3%A9%20%26%20cr%C3%A8me%2Fslides%3F%231%20(draft)"
+        );
+    }
+}
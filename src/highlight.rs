@@ -0,0 +1,213 @@
+//! Build-time syntax highlighting for fenced code blocks.
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use syntect::dumps;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Theme, ThemeSet};
+use syntect::html::{
+    append_highlighted_html_for_styled_line, css_for_theme_with_class_style,
+    line_tokens_to_classed_spans, ClassStyle, IncludeBackground,
+};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use thiserror::Error;
+use tracing::{event, Level};
+
+use crate::diagnostics::Diagnostics;
+use crate::dirs;
+
+/// How highlighted code is styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightStyle {
+    /// Tokens get CSS classes named after their scopes, styled by a separate
+    /// stylesheet.
+    Classes,
+    /// Tokens get inline `style` attributes from the theme.
+    Inline,
+}
+
+impl FromStr for HighlightStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classes" => Ok(HighlightStyle::Classes),
+            "inline" => Ok(HighlightStyle::Inline),
+            _ => Err(format!(
+                "Expected a highlighting style `classes` or `inline`, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HighlightError {
+    #[error("Unknown highlighting theme {name:?}; available themes are: {available}")]
+    UnknownTheme { name: String, available: String },
+
+    #[error("Error highlighting code: {0}")]
+    Syntect(#[from] syntect::Error),
+
+    #[error("Error parsing code for highlighting: {0}")]
+    Parse(#[from] syntect::parsing::ParsingError),
+}
+
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+    style: HighlightStyle,
+}
+
+impl Highlighter {
+    pub fn new(theme: &str, style: HighlightStyle) -> Result<Self, HighlightError> {
+        Ok(Self {
+            syntaxes: SyntaxSet::load_defaults_newlines(),
+            theme: load_theme(theme)?,
+            style,
+        })
+    }
+
+    fn syntax(&self, lang: &str, diagnostics: &Diagnostics) -> &SyntaxReference {
+        self.syntaxes.find_syntax_by_token(lang).unwrap_or_else(|| {
+            diagnostics.warn(format_args!(
+                "No syntax highlighting for language {:?}; using plain text",
+                lang
+            ));
+            self.syntaxes.find_syntax_plain_text()
+        })
+    }
+
+    /// Highlights `code` as `lang`, returning a balanced HTML fragment for
+    /// each line, including its trailing newline.
+    pub fn highlight_lines(
+        &self,
+        lang: &str,
+        code: &str,
+        diagnostics: &Diagnostics,
+    ) -> Result<Vec<String>, HighlightError> {
+        let syntax = self.syntax(lang, diagnostics);
+        match self.style {
+            HighlightStyle::Classes => self.classed_lines(syntax, code),
+            HighlightStyle::Inline => self.styled_lines(syntax, code),
+        }
+    }
+
+    fn classed_lines(
+        &self,
+        syntax: &SyntaxReference,
+        code: &str,
+    ) -> Result<Vec<String>, HighlightError> {
+        let mut state = ParseState::new(syntax);
+        let mut stack = ScopeStack::new();
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(code) {
+            let ops = state.parse_line(line, &self.syntaxes)?;
+            // Spans can cross lines, so reopen the ones still open from the
+            // previous line and close them all at the end of this one.
+            let mut html = String::new();
+            for scope in stack.as_slice() {
+                html.push_str(&format!(
+                    r#"<span class="{}">"#,
+                    scope.build_string().replace('.', " ")
+                ));
+            }
+            let (spans, _) =
+                line_tokens_to_classed_spans(line, &ops, ClassStyle::Spaced, &mut stack)?;
+            html.push_str(&spans);
+            html.push_str(&"</span>".repeat(stack.len()));
+            lines.push(html);
+        }
+        Ok(lines)
+    }
+
+    fn styled_lines(
+        &self,
+        syntax: &SyntaxReference,
+        code: &str,
+    ) -> Result<Vec<String>, HighlightError> {
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let background = IncludeBackground::IfDifferent(self.background());
+        let mut lines = Vec::new();
+        for line in LinesWithEndings::from(code) {
+            let regions = highlighter.highlight_line(line, &self.syntaxes)?;
+            let mut html = String::new();
+            append_highlighted_html_for_styled_line(&regions, background, &mut html)?;
+            lines.push(html);
+        }
+        Ok(lines)
+    }
+
+    fn background(&self) -> Color {
+        self.theme.settings.background.unwrap_or(Color::WHITE)
+    }
+
+    /// The opening `<pre>` tag for a highlighted block of code, without the
+    /// closing `>`.
+    pub fn pre_tag(&self) -> String {
+        match self.style {
+            HighlightStyle::Classes => r#"<pre class="highlight code""#.to_owned(),
+            HighlightStyle::Inline => {
+                let c = self.background();
+                format!(
+                    r#"<pre class="highlight" style="background-color:#{:02x}{:02x}{:02x};""#,
+                    c.r, c.g, c.b
+                )
+            }
+        }
+    }
+}
+
+/// Loads the built-in theme `name`, from the cache if it's there, rather
+/// than loading all of them.
+fn load_theme(name: &str) -> Result<Theme, HighlightError> {
+    let cached = cached_theme(name);
+    if let Some(theme) = cached
+        .as_ref()
+        .and_then(|path| dumps::from_dump_file(path).ok())
+    {
+        return Ok(theme);
+    }
+    let mut themes = ThemeSet::load_defaults().themes;
+    let theme = themes
+        .remove(name)
+        .ok_or_else(|| HighlightError::UnknownTheme {
+            name: name.to_owned(),
+            available: themes.keys().cloned().collect::<Vec<_>>().join(", "),
+        })?;
+    if let Some(path) = cached {
+        // The cache only saves time, so it doesn't matter if it can't be
+        // written.
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|err| err.to_string())
+            .and_then(|()| dumps::dump_to_file(&theme, &path).map_err(|err| err.to_string()));
+        if let Err(err) = written {
+            event!(Level::DEBUG, ?path, %err, "Couldn't cache highlighting theme");
+        }
+    }
+    Ok(theme)
+}
+
+/// Where the theme `name` is cached, for this version, if it can be.
+fn cached_theme(name: &str) -> Option<PathBuf> {
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    let dir = dirs::cache_dir()?
+        .join(env!("CARGO_PKG_VERSION"))
+        .join("themes");
+    Some(dir.join(format!("{}.themedump", name)))
+}
+
+/// The stylesheet for code highlighted with `HighlightStyle::Classes`.
+pub fn css(theme: &str) -> Result<String, HighlightError> {
+    Ok(css_for_theme_with_class_style(
+        &load_theme(theme)?,
+        ClassStyle::Spaced,
+    )?)
+}
@@ -0,0 +1,81 @@
+//! Running shell commands before and after building, for `--pre-build` and
+//! `--post-build`.
+
+use std::ffi::OsString;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+
+use thiserror::Error;
+use tracing::{event, Level};
+
+#[derive(Error, Debug)]
+pub enum HookErr {
+    #[error("Couldn't run `{0}`: {1}")]
+    Spawn(String, #[source] io::Error),
+
+    #[error("`{0}` failed with {1}")]
+    Failed(String, ExitStatus),
+}
+
+/// Runs `command` with the shell and the environment variables `env`,
+/// logging what it prints: standard output as info and standard error as
+/// warnings.
+pub fn run(command: &str, env: &[(&str, OsString)]) -> Result<(), HookErr> {
+    event!(Level::INFO, "Running `{}`", command);
+    let mut child = shell(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| HookErr::Spawn(command.to_owned(), e))?;
+    let output = vec![
+        child
+            .stdout
+            .take()
+            .map(|stdout| log_lines(stdout, command, Level::INFO)),
+        child
+            .stderr
+            .take()
+            .map(|stderr| log_lines(stderr, command, Level::WARN)),
+    ];
+    let status = child
+        .wait()
+        .map_err(|e| HookErr::Spawn(command.to_owned(), e))?;
+    for thread in output.into_iter().flatten() {
+        let _ = thread.join();
+    }
+    if status.success() {
+        Ok(())
+    } else {
+        Err(HookErr::Failed(command.to_owned(), status))
+    }
+}
+
+/// `command`, to be run with the shell.
+pub fn shell(command: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut shell = Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+/// Logs each line `command` writes to `output` at `level`, as it's written.
+fn log_lines(output: impl Read + Send + 'static, command: &str, level: Level) -> JoinHandle<()> {
+    let command = command.to_owned();
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            // `event!` needs a constant level.
+            if level == Level::WARN {
+                event!(Level::WARN, hook = %command, "{}", line);
+            } else {
+                event!(Level::INFO, hook = %command, "{}", line);
+            }
+        }
+    })
+}
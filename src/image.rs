@@ -0,0 +1,187 @@
+//! Images with attribute groups, like `![arch](arch.png){width=60% .center}`,
+//! and videos written like images, like `![demo](video:demo.mp4){loop}` or
+//! `![talk](youtube:dQw4w9WgXcQ)`.
+
+use pulldown_cmark::escape::{escape_href, escape_html};
+use pulldown_cmark::Event;
+
+use crate::attributes::Attributes;
+
+/// Converts an image's attribute group to attributes for its `<img>` tag.
+/// Sizes with units go in a `style` attribute, since the `width` and
+/// `height` attributes only take pixels, and unknown keys become `data-`
+/// attributes.
+fn img_attributes(attributes: Attributes) -> Attributes {
+    let mut style = Vec::new();
+    let mut other = Vec::new();
+    for (key, value) in attributes.other {
+        match key.as_str() {
+            "width" | "height" if value.parse::<u32>().is_err() => {
+                style.push(format!("{}:{}", key, value));
+            }
+            "width" | "height" => other.push((key, value)),
+            "style" => style.push(value),
+            _ if key.starts_with("data-") => other.push((key, value)),
+            _ => other.push((format!("data-{}", key), value)),
+        }
+    }
+    if !style.is_empty() {
+        other.push(("style".to_owned(), style.join(";")));
+    }
+    Attributes {
+        other,
+        ..attributes
+    }
+}
+
+/// The plain text of an image's alt text events.
+fn alt_text(alt: &[Event<'_>]) -> String {
+    let mut alt_text = String::new();
+    for event in alt {
+        match event {
+            Event::Text(text) | Event::Code(text) | Event::Html(text) => alt_text.push_str(text),
+            Event::SoftBreak | Event::HardBreak => alt_text.push(' '),
+            _ => {}
+        }
+    }
+    alt_text
+}
+
+/// Renders an `<img>` tag like pulldown-cmark does, with extra attributes.
+pub fn img_tag(dest: &str, title: &str, alt: &[Event<'_>], attributes: Attributes) -> String {
+    let alt_text = alt_text(alt);
+    // Writing to a `String` can't fail.
+    let mut html = String::from(r#"<img src=""#);
+    escape_href(&mut html, dest).unwrap();
+    html.push_str(r#"" alt=""#);
+    escape_html(&mut html, &alt_text).unwrap();
+    if !title.is_empty() {
+        html.push_str(r#"" title=""#);
+        escape_html(&mut html, title).unwrap();
+    }
+    html.push_str(&format!("\"{} />", img_attributes(attributes)));
+    html
+}
+
+/// Attributes of `<video>` elements which are kept as they are, rather than
+/// made into `data-` attributes.
+const VIDEO_ATTRIBUTES: &[&str] = &[
+    "autoplay",
+    "muted",
+    "loop",
+    "playsinline",
+    "poster",
+    "preload",
+];
+
+/// A video written like an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Video<'d> {
+    /// A YouTube video, by ID.
+    YouTube(&'d str),
+    /// A video file, by path or URL.
+    File(&'d str),
+}
+
+impl<'d> Video<'d> {
+    /// Parses an image destination like `youtube:dQw4w9WgXcQ` or
+    /// `video:demo.mp4`.
+    pub fn parse(dest: &'d str) -> Option<Self> {
+        if let Some(id) = dest.strip_prefix("youtube:") {
+            Some(Video::YouTube(id))
+        } else {
+            dest.strip_prefix("video:").map(Video::File)
+        }
+    }
+
+    /// Renders the video, with `alt` as its title or fallback text.
+    pub fn html(&self, alt: &[Event<'_>], attributes: Attributes) -> String {
+        let alt_text = alt_text(alt);
+        match self {
+            Video::YouTube(id) => youtube_iframe(id, &alt_text, attributes),
+            Video::File(src) => video_tag(src, &alt_text, attributes),
+        }
+    }
+}
+
+/// Renders a privacy-enhanced YouTube player. `autoplay`, `muted`, `loop`,
+/// and `start=seconds` attributes become player parameters.
+fn youtube_iframe(id: &str, title: &str, mut attributes: Attributes) -> String {
+    let mut params = Vec::new();
+    let mut has_size = false;
+    attributes.other.retain(|(key, value)| match key.as_str() {
+        "autoplay" => {
+            params.push("autoplay=1".to_owned());
+            false
+        }
+        "muted" => {
+            params.push("mute=1".to_owned());
+            false
+        }
+        "loop" => {
+            // YouTube only loops playlists, so make a playlist of one.
+            params.push(format!("loop=1&playlist={}", id));
+            false
+        }
+        "start" => {
+            params.push(format!("start={}", value));
+            false
+        }
+        "width" | "height" => {
+            has_size = true;
+            true
+        }
+        _ => true,
+    });
+    if !has_size {
+        attributes
+            .other
+            .push(("width".to_owned(), "560".to_owned()));
+        attributes
+            .other
+            .push(("height".to_owned(), "315".to_owned()));
+    }
+    let mut src = format!("https://www.youtube-nocookie.com/embed/{}", id);
+    if !params.is_empty() {
+        src.push('?');
+        src.push_str(&params.join("&"));
+    }
+    let mut html = String::from(r#"<iframe src=""#);
+    escape_href(&mut html, &src).unwrap();
+    html.push_str(r#"" title=""#);
+    escape_html(
+        &mut html,
+        if title.is_empty() {
+            "YouTube video"
+        } else {
+            title
+        },
+    )
+    .unwrap();
+    html.push_str(&format!(
+        "\"{} frameborder=\"0\" allow=\"autoplay; encrypted-media; picture-in-picture\" \
+         allowfullscreen></iframe>",
+        img_attributes(attributes)
+    ));
+    html
+}
+
+/// Renders a `<video controls>` element, with `alt` as the text shown by
+/// browsers that can't play it.
+fn video_tag(src: &str, alt: &str, attributes: Attributes) -> String {
+    let (video, other) = attributes
+        .other
+        .into_iter()
+        .partition(|(key, _)| VIDEO_ATTRIBUTES.contains(&key.as_str()));
+    let mut attributes = img_attributes(Attributes {
+        other,
+        ..attributes
+    });
+    attributes.other.extend::<Vec<_>>(video);
+    let mut html = String::from(r#"<video controls src=""#);
+    escape_href(&mut html, src).unwrap();
+    html.push_str(&format!("\"{}>", attributes));
+    escape_html(&mut html, alt).unwrap();
+    html.push_str("</video>");
+    html
+}
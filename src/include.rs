@@ -0,0 +1,153 @@
+//! `<!-- include: path.md -->` directives, which splice another Markdown file
+//! into the document.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use thiserror::Error;
+
+use crate::markdown::comment_body;
+
+/// How deeply includes can nest before we give up.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Error, Debug)]
+pub enum IncludeError {
+    #[error("Error reading included file {0}: {1}")]
+    Read(PathBuf, #[source] io::Error),
+
+    #[error("Include cycle: {0}")]
+    Cycle(String),
+
+    #[error("Includes nested more than {} deep in {0}", MAX_DEPTH)]
+    TooDeep(PathBuf),
+}
+
+/// The contents of every file included by a document.
+pub struct Includes {
+    /// Included files' contents, by canonical path.
+    files: HashMap<PathBuf, String>,
+}
+
+impl Includes {
+    /// Reads every file included by `source`, the contents of the file at
+    /// `path`, recursively. Their contents are passed through `protect`, like
+    /// `source` was, to replace things like math with placeholders.
+    pub fn load(
+        path: &Path,
+        source: &str,
+        options: Options,
+        protect: &dyn Fn(&str) -> String,
+    ) -> Result<Self, IncludeError> {
+        let mut ret = Self {
+            files: HashMap::new(),
+        };
+        ret.load_from(source, options, protect, &mut vec![path.to_owned()])?;
+        Ok(ret)
+    }
+
+    fn load_from(
+        &mut self,
+        source: &str,
+        options: Options,
+        protect: &dyn Fn(&str) -> String,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), IncludeError> {
+        let path = stack.last().expect("Include stack is never empty").clone();
+        for event in Parser::new_ext(source, options) {
+            let target = match include_target(&path, &event) {
+                Some(target) => target
+                    .canonicalize()
+                    .map_err(|e| IncludeError::Read(target, e))?,
+                None => continue,
+            };
+            if stack.contains(&target) {
+                stack.push(target);
+                let cycle = stack
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(IncludeError::Cycle(cycle));
+            }
+            if stack.len() > MAX_DEPTH {
+                return Err(IncludeError::TooDeep(path));
+            }
+            let source = match self.files.get(&target) {
+                Some(source) => source.clone(),
+                None => {
+                    let source = fs::read_to_string(&target)
+                        .map_err(|e| IncludeError::Read(target.clone(), e))?;
+                    let source = protect(&source);
+                    self.files.insert(target.clone(), source.clone());
+                    source
+                }
+            };
+            stack.push(target);
+            self.load_from(&source, options, protect, stack)?;
+            stack.pop();
+        }
+        Ok(())
+    }
+
+    /// Parses `source`, the contents of the file at `path`, replacing include
+    /// directives with the events of the files they include, and top-level
+    /// thematic breaks written as `vertical_delimiter` with `<!-- vertical
+    /// -->` directives.
+    pub fn events<'a>(
+        &'a self,
+        path: &Path,
+        source: &'a str,
+        options: Options,
+        vertical_delimiter: &str,
+    ) -> Vec<Event<'a>> {
+        let mut events = Vec::new();
+        // How many lists, block quotes, and footnote definitions we're in.
+        let mut depth = 0;
+        for (event, range) in Parser::new_ext(source, options).into_offset_iter() {
+            match event {
+                Event::Start(Tag::List(_))
+                | Event::Start(Tag::BlockQuote)
+                | Event::Start(Tag::FootnoteDefinition(_)) => depth += 1,
+                Event::End(Tag::List(_))
+                | Event::End(Tag::BlockQuote)
+                | Event::End(Tag::FootnoteDefinition(_)) => depth -= 1,
+                _ => {}
+            }
+            if let Event::Rule = event {
+                if depth == 0 && source[range].trim() == vertical_delimiter {
+                    events.push(Event::Html("<!-- vertical -->".into()));
+                    continue;
+                }
+            }
+            let included = include_target(path, &event)
+                .and_then(|target| target.canonicalize().ok())
+                .and_then(|target| Some((self.files.get(&target)?, target)));
+            match included {
+                Some((source, target)) => {
+                    events.extend(self.events(&target, source, options, vertical_delimiter))
+                }
+                None => events.push(event),
+            }
+        }
+        events
+    }
+
+    /// The canonical paths of the included files.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.keys().cloned().collect()
+    }
+}
+
+/// If `event` is an include directive in the file at `path`, the path of the
+/// file it includes.
+fn include_target(path: &Path, event: &Event<'_>) -> Option<PathBuf> {
+    let target = match event {
+        Event::Html(html) => comment_body(html)?.strip_prefix("include:")?.trim(),
+        _ => return None,
+    };
+    Some(path.parent().unwrap_or_else(|| Path::new("")).join(target))
+}
@@ -0,0 +1,150 @@
+//! `[[Ctrl+C]]` keyboard shortcuts, rendered as `<kbd>` key caps.
+//!
+//! Like math, shortcuts are swapped out of the Markdown source for
+//! placeholders before it's parsed, since Markdown splits brackets into
+//! separate text and can't tell `\[[escaped]]` brackets from the rest.
+
+use std::fmt::Write;
+
+use handlebars::html_escape;
+use pulldown_cmark::Event;
+
+use crate::math::{code_span_len, fence_marker};
+
+const START: char = '\u{E003}';
+const END: char = '\u{E004}';
+
+/// Replaces the shortcuts in Markdown `source` with placeholders. Shortcuts
+/// in code spans and fenced code blocks are left alone, as are brackets
+/// escaped like `\[[`.
+pub fn protect(source: &str) -> String {
+    let mut ret = String::with_capacity(source.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+        if i == 0 || source.as_bytes()[i - 1] == b'\n' {
+            let line = &rest[..rest.find('\n').map_or(rest.len(), |end| end + 1)];
+            match (fence, fence_marker(line)) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if marker.starts_with(open) => fence = None,
+                _ => {}
+            }
+            if fence.is_some() || fence_marker(line).is_some() {
+                ret.push_str(line);
+                i += line.len();
+                continue;
+            }
+        }
+
+        let len = if let Some(escaped) = rest.strip_prefix('\\') {
+            1 + escaped.chars().next().map_or(0, char::len_utf8)
+        } else if rest.starts_with('`') {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            code_span_len(rest, ticks).unwrap_or(ticks)
+        } else if let Some(keys) = rest.strip_prefix("[[") {
+            match keys
+                .find("]]")
+                .filter(|&end| !keys[..end].trim().is_empty() && !keys[..end].contains('\n'))
+            {
+                Some(end) => {
+                    ret.push(START);
+                    for byte in keys[..end].bytes() {
+                        // Writing to a `String` can't fail.
+                        write!(ret, "{:02x}", byte).unwrap();
+                    }
+                    ret.push(END);
+                    i += end + 4;
+                    continue;
+                }
+                None => 2,
+            }
+        } else {
+            rest.chars().next().map_or(1, char::len_utf8)
+        };
+        ret.push_str(&rest[..len]);
+        i += len;
+    }
+    ret
+}
+
+/// Does `text` contain any shortcut placeholders?
+pub fn is_protected(text: &str) -> bool {
+    text.contains(END)
+}
+
+/// Splits `text` into its shortcuts, as `Some(keys)`, and the text between
+/// them.
+fn pieces(text: &str) -> Vec<(Option<String>, String)> {
+    let mut ret = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(START) {
+        let end = match rest[start..].find(END) {
+            Some(end) => start + end,
+            None => break,
+        };
+        if start > 0 {
+            ret.push((None, rest[..start].to_owned()));
+        }
+        let hex = &rest[start + START.len_utf8()..end];
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect();
+        ret.push((
+            Some(String::from_utf8(bytes).unwrap_or_default()),
+            String::new(),
+        ));
+        rest = &rest[end + END.len_utf8()..];
+    }
+    if !rest.is_empty() {
+        ret.push((None, rest.to_owned()));
+    }
+    ret
+}
+
+/// Puts the shortcuts back into `text`, where they shouldn't be rendered.
+pub fn restore(text: &str) -> String {
+    pieces(text)
+        .into_iter()
+        .map(|(keys, text)| match keys {
+            Some(keys) => format!("[[{}]]", keys),
+            None => text,
+        })
+        .collect()
+}
+
+/// Splits a shortcut like `Ctrl+Shift++` into its keys.
+fn keys(shortcut: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let mut rest = shortcut;
+    while !rest.is_empty() {
+        // The first character is part of the key even if it's a `+`.
+        let first = rest.chars().next().map_or(0, char::len_utf8);
+        let end = rest[first..]
+            .find('+')
+            .map_or(rest.len(), |end| first + end);
+        keys.push(rest[..end].trim());
+        rest = rest[end..].strip_prefix('+').unwrap_or("");
+    }
+    keys
+}
+
+/// Renders the shortcuts in `text`, returning events for them and the text
+/// around them.
+pub fn render(text: &str) -> Vec<Event<'static>> {
+    pieces(text)
+        .into_iter()
+        .map(|(keys, text)| match keys {
+            Some(shortcut) => {
+                let html = self::keys(&shortcut)
+                    .into_iter()
+                    .map(|key| format!("<kbd>{}</kbd>", html_escape(key)))
+                    .collect::<Vec<_>>()
+                    .join("+");
+                Event::Html(html.into())
+            }
+            None => Event::Text(text.into()),
+        })
+        .collect()
+}
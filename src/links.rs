@@ -0,0 +1,114 @@
+//! Checking that the slideshow's links and images point somewhere.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pulldown_cmark::escape::{escape_href, escape_html};
+
+use crate::assets;
+use crate::diagnostics::Diagnostics;
+
+/// A link or image whose destination doesn't exist.
+#[derive(Debug, Clone)]
+pub struct BrokenLink {
+    pub slide: usize,
+    pub dest: String,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Broken link to {} on slide {}: {}",
+            self.dest, self.slide, self.reason
+        )
+    }
+}
+
+/// Is `dest` an `http(s)://` link to a site other than `base_url`'s?
+pub fn is_off_site(dest: &str, base_url: Option<&str>) -> bool {
+    match host(dest) {
+        Some(host) => base_url.and_then(self::host) != Some(host),
+        None => false,
+    }
+}
+
+/// The lowercased host (and port) of an `http(s)://` URL.
+fn host(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))?;
+    let end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+    let authority = &rest[..end];
+    // Drop any `user:password@`.
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    Some(host.to_ascii_lowercase())
+}
+
+/// The opening `<a>` tag of a link opening in a new tab.
+pub fn blank_link_tag(dest: &str, title: &str) -> String {
+    let mut html = String::from("<a href=\"");
+    escape_href(&mut html, dest).unwrap();
+    if !title.is_empty() {
+        html.push_str("\" title=\"");
+        escape_html(&mut html, title).unwrap();
+    }
+    html.push_str("\" target=\"_blank\" rel=\"noopener noreferrer\">");
+    html
+}
+
+pub struct LinkChecker<'a> {
+    pub input_dir: &'a Path,
+    pub static_dirs: &'a [PathBuf],
+    /// IDs of the slides, which `#anchor` links may refer to.
+    pub ids: HashSet<&'a str>,
+    /// Check `http(s)://` links with HEAD requests?
+    pub external: bool,
+    pub diagnostics: &'a Diagnostics,
+}
+
+impl LinkChecker<'_> {
+    /// Returns why `dest` is broken, if it is.
+    pub fn check(&self, dest: &str) -> Option<String> {
+        if let Some(id) = dest.strip_prefix('#') {
+            return if self.ids.contains(id) {
+                None
+            } else {
+                Some(format!("No slide has the ID {:?}", id))
+            };
+        }
+        if dest.starts_with("http://") || dest.starts_with("https://") {
+            return if self.external { check_url(dest) } else { None };
+        }
+        let path = assets::local_path(dest, self.diagnostics)?;
+        if self.input_dir.join(&path).exists()
+            || self.static_dirs.iter().any(|dir| dir.join(&path).exists())
+        {
+            None
+        } else {
+            Some(format!(
+                "{} isn't in the input file's directory or the static files directories",
+                path.display()
+            ))
+        }
+    }
+}
+
+fn check_url(url: &str) -> Option<String> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build();
+    let result = match agent.head(url).call() {
+        // Some servers don't support HEAD requests.
+        Err(ureq::Error::Status(405, _)) => agent.get(url).call(),
+        result => result,
+    };
+    match result {
+        Ok(_) => None,
+        Err(ureq::Error::Status(status, _)) => Some(format!("Server responded with {}", status)),
+        Err(err) => Some(err.to_string()),
+    }
+}
@@ -0,0 +1,59 @@
+//! Accessibility checks, like for images without alt text, enabled with
+//! `--lint`.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// What to do about lint findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Log them as warnings.
+    Warn,
+    /// Fail the build.
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            _ => Err(format!("Expected `warn` or `deny`, not {:?}", s)),
+        }
+    }
+}
+
+/// A problem found on a slide.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub slide: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Slide {}: {}", self.slide, self.message)
+    }
+}
+
+/// What's wrong with a link's visible `text`, if anything, which should say
+/// where it goes.
+pub fn link_text(text: &str) -> Option<String> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+    if text.is_empty() {
+        Some("Link has no text".to_owned())
+    } else if lower == "here" || lower == "click here" {
+        Some(format!("Link text {:?} doesn't say where it goes", text))
+    } else if ["http://", "https://", "www."]
+        .iter()
+        .any(|prefix| lower.starts_with(prefix))
+        && !text.contains(char::is_whitespace)
+    {
+        Some(format!("Link text is a bare URL: {}", text))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,124 @@
+//! Setting up logging, for `--trace-level`, `--log-format`, and `--color`.
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::Level;
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines, with the spans they're in.
+    Full,
+    /// Shorter human-readable lines.
+    Compact,
+    /// Multi-line, indented records.
+    Pretty,
+    /// A JSON object per line, with the event's fields and spans.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(LogFormat::Full),
+            "compact" => Ok(LogFormat::Compact),
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!(
+                "Expected a log format `full`, `compact`, `pretty`, or `json`, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+/// When to style log lines and errors with ANSI colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// When standard error is a terminal, unless `NO_COLOR` is set.
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!(
+                "Expected `auto`, `always`, or `never` for when to use colors, not {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl Color {
+    /// Should what's written to standard error be colored?
+    fn enabled(self) -> bool {
+        match self {
+            Color::Always => true,
+            Color::Never => false,
+            Color::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty())
+                    && io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+/// Whether `init` turned on colors.
+static COLORED: AtomicBool = AtomicBool::new(false);
+
+/// Logs events at `level` and above to standard error, in `format`, colored
+/// according to `color`.
+pub fn init(level: Level, format: LogFormat, color: Color) {
+    let colored = color.enabled();
+    COLORED.store(colored, Ordering::Relaxed);
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(colored)
+        .with_writer(io::stderr);
+    let result = match format {
+        LogFormat::Full => tracing::subscriber::set_global_default(builder.finish()),
+        LogFormat::Compact => tracing::subscriber::set_global_default(builder.compact().finish()),
+        LogFormat::Pretty => tracing::subscriber::set_global_default(builder.pretty().finish()),
+        LogFormat::Json => tracing::subscriber::set_global_default(
+            builder
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .finish(),
+        ),
+    };
+    if let Err(err) = result {
+        eprintln!("Couldn't set up logging: {}", err);
+    }
+}
+
+/// `text` in bold red, if colors are on, for errors.
+pub fn red(text: &str) -> String {
+    style("1;31", text)
+}
+
+/// `text` in bold, if colors are on.
+pub fn bold(text: &str) -> String {
+    style("1", text)
+}
+
+fn style(sgr: &str, text: &str) -> String {
+    if COLORED.load(Ordering::Relaxed) {
+        format!("\x1b[{}m{}\x1b[0m", sgr, text)
+    } else {
+        text.to_owned()
+    }
+}
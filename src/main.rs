@@ -1,92 +1,1340 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::error;
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::io::{self, Write};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf, StripPrefixError};
+use std::process;
+use std::time::Instant;
 
+use pulldown_cmark::Options;
+use structopt::clap::{ErrorKind, Shell};
 use structopt::StructOpt;
 use thiserror::Error;
-use tracing::{event, info, instrument, span, warn, Level};
-use tracing_subscriber;
+use tracing::{event, instrument, span, Level};
 
+mod abbr;
+mod assets;
+mod attributes;
+mod code;
+mod config;
+mod container;
+mod diagnostics;
+mod dirs;
+mod embed;
+mod emoji;
+mod filter;
+mod fingerprint;
+mod front_matter;
+mod git;
+mod glob;
+mod helpers;
+mod highlight;
+mod hooks;
+mod image;
+mod include;
+mod kbd;
+mod links;
+mod lint;
+mod logging;
 mod markdown;
+mod math;
+mod mermaid;
+mod minify;
+mod open;
+mod overlay;
+mod profile;
+mod qr;
+mod report;
+mod sanitize;
+mod scaffold;
+mod serve;
+mod slug;
+mod stats;
+mod theme;
+mod vars;
+mod version;
 
 #[derive(Debug, StructOpt)]
-#[structopt(about = "A Markdown-based slideshow rendering tool.")]
-struct Opt {
-    /// Log level.
+#[structopt(
+    about = "A Markdown-based slideshow rendering tool.",
+    long_version = version::LONG
+)]
+struct Cli {
+    /// Log level, which defaults to "warn" and overrides `-v` and `-q`.
     ///
     /// Can be an integer 1-5 or "error", "warn", "info", "debug", "trace",
     /// case-insensitive.
-    #[structopt(long, default_value = "warn")]
-    trace_level: Level,
+    #[structopt(long, global = true)]
+    trace_level: Option<Level>,
+
+    /// Log more: `-v` for info, `-vv` for debug, and `-vvv` for trace.
+    #[structopt(short, long, parse(from_occurrences), global = true)]
+    verbose: u8,
+
+    /// Only log errors.
+    #[structopt(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// How to write log lines: `full`, `compact`, `pretty`, or `json`, with
+    /// an object per line.
+    #[structopt(
+        long,
+        default_value = "full",
+        possible_values = &["full", "compact", "pretty", "json"],
+        global = true
+    )]
+    log_format: logging::LogFormat,
+
+    /// When to color log lines and errors: `auto` colors them if standard
+    /// error is a terminal and the `NO_COLOR` environment variable isn't
+    /// set.
+    #[structopt(
+        long,
+        default_value = "auto",
+        possible_values = &["auto", "always", "never"],
+        global = true
+    )]
+    color: logging::Color,
+
+    /// Print the built-in template, to start a custom one from.
+    #[structopt(long)]
+    print_default_template: bool,
+
+    /// Print what `--version` does as JSON: the version, the git commit
+    /// built from, the enabled cargo features, and the versions of the
+    /// Markdown and template libraries.
+    #[structopt(long)]
+    version_json: bool,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    fn level(&self) -> Level {
+        match (self.trace_level, self.quiet, self.verbose) {
+            (Some(level), _, _) => level,
+            (None, true, _) => Level::ERROR,
+            (None, false, 0) => Level::WARN,
+            (None, false, 1) => Level::INFO,
+            (None, false, 2) => Level::DEBUG,
+            (None, false, _) => Level::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Render the slideshow. `builder input.md` is short for `builder build
+    /// input.md`.
+    Build(Opt),
+
+    /// Render the slideshow, and render it again whenever its files change.
+    Watch {
+        #[structopt(flatten)]
+        opt: Opt,
+        #[structopt(flatten)]
+        watch: WatchOpt,
+    },
+
+    /// Render and watch the slideshow like `watch`, serving the output
+    /// directory over HTTP.
+    Serve {
+        #[structopt(flatten)]
+        opt: Opt,
+        #[structopt(flatten)]
+        watch: WatchOpt,
+        #[structopt(flatten)]
+        serve: ServeOpt,
+    },
 
-    /// Watch for changes to files and keep re-rendering?
-    #[structopt(short, long)]
-    watch: bool,
+    /// Start a new slideshow in a directory, with sample slides, the default
+    /// template, a stylesheet, and a `slideshow.toml`.
+    New(NewOpt),
 
+    /// Print a completion script for a shell, like `builder completions zsh
+    /// > _builder`.
+    Completions {
+        /// `bash`, `zsh`, `fish`, `powershell`, or `elvish`.
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        shell: Shell,
+    },
+}
+
+impl Command {
+    const NAMES: &'static [&'static str] =
+        &["build", "watch", "serve", "new", "completions", "help"];
+}
+
+#[derive(Debug, StructOpt)]
+struct WatchOpt {
     /// Debounce filesystem events to a given granularity, in milliseconds.
     #[structopt(long, default_value = "250")]
     debounce_ms: u64,
 
-    /// Directory of static files, copied unmodified into the output
-    /// directory.
-    #[structopt(long, parse(from_os_str), default_value = "static")]
-    static_dir: PathBuf,
+    /// Don't show build errors over the slideshow in the browser. Otherwise,
+    /// when a rebuild fails, the error is shown over the last slideshow
+    /// built until the next one succeeds.
+    #[structopt(long)]
+    no_error_overlay: bool,
+}
 
-    /// Slideshow template.
-    #[structopt(long, parse(from_os_str), default_value = "template.html")]
-    template: PathBuf,
+#[derive(Debug, StructOpt)]
+struct ServeOpt {
+    /// The port to serve the slideshow on.
+    #[structopt(long, default_value = "8000")]
+    port: u16,
 
-    /// Input Markdown file.
+    /// The address to serve the slideshow on. Use `0.0.0.0` to make it
+    /// reachable from other machines.
+    #[structopt(long, default_value = "127.0.0.1")]
+    host: String,
+}
+
+#[derive(Debug, StructOpt)]
+struct NewOpt {
+    /// The built-in theme to start `static/style.css` from: `light`, `dark`,
+    /// `solarized`, or `plain`.
+    #[structopt(long, default_value = "light", possible_values = theme::Theme::NAMES)]
+    theme: theme::Theme,
+
+    /// Write the slideshow's files even if the directory isn't empty,
+    /// overwriting any with the same names.
+    #[structopt(long)]
+    force: bool,
+
+    /// Directory to create the slideshow in.
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    dir: PathBuf,
+}
+
+/// Options for rendering a slideshow, shared by `build`, `watch`, and
+/// `serve`.
+#[derive(Debug, StructOpt)]
+struct Opt {
+    /// Directory of static files, copied unmodified into the output
+    /// directory. May be given more than once, like `--static-dir
+    /// ../shared-static --static-dir static`, with later directories' files
+    /// replacing earlier ones' with the same names.
+    #[structopt(
+        long = "static-dir",
+        parse(from_os_str),
+        default_value = "static",
+        number_of_values = 1
+    )]
+    static_dirs: Vec<PathBuf>,
+
+    /// What separates slides: `rule` for thematic breaks like `---`,
+    /// `comment` for `<!-- slide -->`, another HTML comment, or a /regex/
+    /// matching paragraphs. May be given more than once, or as a list like
+    /// `rule,comment`; defaults to `rule`. Without `rule`, thematic breaks
+    /// are rendered as `<hr>`s.
+    #[structopt(long, number_of_values = 1)]
+    slide_delimiter: Vec<markdown::SlideDelimiters>,
+
+    /// A thematic break that starts a new vertical slide in the current
+    /// stack rather than a new stack, like `<!-- vertical -->` does. Decks
+    /// with vertical slides are wrapped in `<section class="stack">`s.
+    #[structopt(long, default_value = "----")]
+    vertical_delimiter: String,
+
+    /// Start a new slide at each heading of this level or higher (`h1`
+    /// through `h6`), in addition to at horizontal rules.
+    #[structopt(long)]
+    slide_on: Option<markdown::HeadingLevel>,
+
+    /// Reveal list items one at a time on every slide. Without this, only
+    /// slides containing a `<!-- fragments -->` comment do so.
+    #[structopt(long)]
+    fragments: bool,
+
+    /// Reveal nested list items one at a time too, not just top-level ones.
+    #[structopt(long)]
+    nested_fragments: bool,
+
+    /// Keep slides with no content, e.g. from a leading or doubled `---`,
+    /// rather than dropping them.
+    #[structopt(long)]
+    keep_empty_slides: bool,
+
+    /// Render slides marked with `<!-- draft -->` or `<!-- .slide: hidden
+    /// -->` rather than leaving them out.
+    #[structopt(long)]
+    include_drafts: bool,
+
+    /// Only render these slides, like `1-5,12,20-`. Slides are renumbered,
+    /// with their numbers in the whole deck in `data-source-slide`
+    /// attributes.
+    #[structopt(long)]
+    slides: Option<markdown::SlideRanges>,
+
+    /// Markdown for a header on every slide, like the front matter's
+    /// `slide_header`. A slide can override it with `<!-- .slide:
+    /// header="..." -->`, or leave it out with `header=""`.
+    #[structopt(long)]
+    slide_header: Option<String>,
+
+    /// Markdown for a footer on every slide, like the front matter's
+    /// `slide_footer`; overridden like `--slide-header`.
+    #[structopt(long)]
+    slide_footer: Option<String>,
+
+    /// Give the template a variable, as `key=value`; may be given more than
+    /// once. Overrides the data file and front matter.
+    #[structopt(long = "var", number_of_values = 1)]
+    vars: Vec<vars::Var>,
+
+    /// A TOML, JSON, or YAML file of variables to give the template, which
+    /// may be tables reachable like `{{event.room}}`. Overrides the front
+    /// matter.
+    #[structopt(long, parse(from_os_str))]
+    data: Option<PathBuf>,
+
+    /// Don't give the template the build time as `{{build_timestamp}}`, so
+    /// that rebuilding unchanged input gives byte-identical output.
+    #[structopt(long)]
+    reproducible: bool,
+
+    /// Don't give the template the git commit the input file is at, as
+    /// `{{git_commit}}`, `{{git_branch}}`, and `{{git_dirty}}`.
+    #[structopt(long)]
+    no_git_metadata: bool,
+
+    /// Build for this profile, keeping `:::only{profile}` blocks and dropping
+    /// `:::not{profile}` blocks; may be given more than once. The template
+    /// gets the profiles as `{{profile}}`.
+    #[structopt(long = "profile", number_of_values = 1)]
+    profiles: Vec<String>,
+
+    /// Number headings like "2.3 Borrow Checker", counting each level across
+    /// the whole deck. Front matter can turn this off with `number_headings:
+    /// false`.
+    #[structopt(long)]
+    number_headings: bool,
+
+    /// Start with a title slide showing the front matter's `title`,
+    /// `author`, and `date`. Front matter can ask for one with `title_slide:
+    /// true` too.
+    #[structopt(long)]
+    title_slide: bool,
+
+    /// Enable a Markdown extension; may be given more than once.
+    ///
+    /// One of "footnotes", "tables", "strikethrough", "tasklists",
+    /// "smart-punctuation", "heading-attributes", "mark" for `==marked==`
+    /// text, or "abbreviations" for `*[HTML]: HyperText Markup Language`
+    /// definitions.
+    #[structopt(
+        long = "md-extension",
+        number_of_values = 1,
+        possible_values = markdown::EXTENSION_NAMES,
+        case_insensitive = true
+    )]
+    md_extensions: Vec<markdown::MarkdownExtension>,
+
+    /// Don't enable the "footnotes" and "tables" extensions by default.
+    #[structopt(long)]
+    no_default_extensions: bool,
+
+    /// Leave images with titles alone in paragraphs of their own, rather than
+    /// making them `<figure>`s captioned with their titles.
+    #[structopt(long)]
+    no_figures: bool,
+
+    /// Draw ```` ```mermaid ```` diagrams as inline SVGs with the Mermaid
+    /// CLI, `mmdc`, rather than leaving them for mermaid.js to draw.
+    #[structopt(long)]
+    mermaid_cli: bool,
+
+    /// Render `$...$` and `$$...$$` LaTeX math at build time with KaTeX.
+    /// The template still needs KaTeX's stylesheet and fonts; escape a
+    /// dollar sign that isn't math like `\$`.
+    #[structopt(long)]
+    math: bool,
+
+    /// Replace GitHub-style shortcodes like `:rocket:` with emoji.
+    #[structopt(long)]
+    emoji: bool,
+
+    /// Render keyboard shortcuts like `[[Ctrl+C]]` as
+    /// `<kbd>Ctrl</kbd>+<kbd>C</kbd>`. Write literal double brackets as
+    /// `\[[`.
+    #[structopt(long)]
+    kbd: bool,
+
+    /// With the `tasklists` extension, render task list items with disabled
+    /// checkboxes rather than as `<li class="task done">` and `<li
+    /// class="task todo">` for the template to style.
+    #[structopt(long)]
+    task_checkboxes: bool,
+
+    /// Keep HTML comments in the output. Without this, comments other than
+    /// directives like `<!-- notes: ... -->` are left out.
+    #[structopt(long)]
+    keep_comments: bool,
+
+    /// Run raw HTML in the Markdown through an allowlist sanitizer, removing
+    /// scripts and the like.
+    #[structopt(long)]
+    sanitize_html: bool,
+
+    /// Drop raw HTML in the Markdown entirely. Directives in comments still
+    /// work.
+    #[structopt(long, conflicts_with = "sanitize-html")]
+    no_raw_html: bool,
+
+    /// Warn about slides with more words than this, which might not fit on
+    /// the screen, and give them `data-overflow-risk` attributes. 0 disables
+    /// the check.
+    #[structopt(long, default_value = "150")]
+    max_words_per_slide: usize,
+
+    /// Like `--max-words-per-slide`, for lines of code.
+    #[structopt(long, default_value = "25")]
+    max_code_lines_per_slide: usize,
 
-    /// Output directory.
-    #[structopt(parse(from_os_str), default_value = "out")]
+    /// Like `--max-words-per-slide`, for images.
+    #[structopt(long, default_value = "4")]
+    max_images_per_slide: usize,
+
+    /// Number the lines of every fenced code block. Without this, only blocks
+    /// with `linenos` (or `linenos=42`, to start at 42) in their info strings
+    /// are numbered.
+    #[structopt(long)]
+    code_line_numbers: bool,
+
+    /// Syntax-highlight fenced code blocks at build time, either with CSS
+    /// `classes` (styled by a `highlight.css` written to the output
+    /// directory) or `inline` styles.
+    #[structopt(long)]
+    highlight: Option<highlight::HighlightStyle>,
+
+    /// Theme to highlight code with.
+    #[structopt(long, default_value = "InspiredGitHub")]
+    highlight_theme: String,
+
+    /// Report accessibility problems, like images without alt text, links
+    /// reading "here", skipped heading levels, and slides without headings.
+    /// With `--lint=deny`, any problems fail the build.
+    #[structopt(long, require_equals = true)]
+    lint: Option<Option<lint::LintLevel>>,
+
+    /// Fail the build if there are any warnings, listing them all once it's
+    /// done. When watching, they're listed after each update instead.
+    #[structopt(long)]
+    deny_warnings: bool,
+
+    /// The warnings emitted so far, for `--deny-warnings`.
+    #[structopt(skip)]
+    diagnostics: diagnostics::Diagnostics,
+
+    /// Report links and images pointing to local files or slides that don't
+    /// exist, and exit unsuccessfully if there are any.
+    #[structopt(long)]
+    check_links: bool,
+
+    /// Don't open `http(s)://` links to other sites in new tabs.
+    #[structopt(long)]
+    no_external_blank: bool,
+
+    /// The URL the slideshow is published at, like
+    /// `https://example.com/talks/rustconf/`. Relative links and images are
+    /// prefixed with it, links under it aren't opened in new tabs, and the
+    /// template gets it as `{{base_url}}`, always ending with a slash.
+    #[structopt(long)]
+    base_url: Option<String>,
+
+    /// With `--check-links`, check `http(s)://` links with HEAD requests too.
+    #[structopt(long)]
+    check_external_links: bool,
+
+    /// Print statistics about the slides, like how many words they have and
+    /// how long they might take to present, after building.
+    #[structopt(long)]
+    stats: bool,
+
+    /// Also write the `--stats` statistics to this JSON file.
+    #[structopt(long, parse(from_os_str))]
+    stats_json: Option<PathBuf>,
+
+    /// The speaking rate to estimate the presentation's length with.
+    #[structopt(long, default_value = "130")]
+    words_per_minute: u32,
+
+    /// Also write each slide's speaker notes to this Markdown file.
+    #[structopt(long, parse(from_os_str))]
+    notes_output: Option<PathBuf>,
+
+    /// After building, write a JSON report to this file of the files
+    /// written and copied, the slides, the warnings, and how long each part
+    /// of the build took. It's written again after every update when
+    /// watching.
+    #[structopt(long, parse(from_os_str))]
+    report: Option<PathBuf>,
+
+    /// What's been built, for `--report`.
+    #[structopt(skip)]
+    build_report: RefCell<report::Report>,
+
+    /// Slideshow template. A bare file name is looked for in the input file's
+    /// directory, the current directory, and then the config directory,
+    /// `$XDG_CONFIG_HOME/simple-slideshow`. Defaults to `template.html` if
+    /// there's one in those places, or else a built-in template, which
+    /// `--print-default-template` prints.
+    #[structopt(long, parse(from_os_str))]
+    template: Option<PathBuf>,
+
+    /// Print the path of the template that would be used, and exit.
+    #[structopt(long)]
+    print_template_path: bool,
+
+    /// Let the template read environment variables with `{{env "NAME"}}`.
+    /// Give names to allow only those, like `--allow-env=TALK_*,CI`; a
+    /// trailing `*` matches any suffix.
+    #[structopt(long, require_equals = true, use_delimiter = true)]
+    allow_env: Option<Vec<String>>,
+
+    /// A second template to render the slides with, into `handout.html`; like
+    /// a printable document with the speaker notes showing.
+    #[structopt(long, parse(from_os_str))]
+    handout_template: Option<PathBuf>,
+
+    /// Directory of Handlebars partials, each `name.hbs` or `name.html`
+    /// usable as `{{> name}}`. A `title-slide` partial replaces the generated
+    /// title slide's markup, and `layout-name` partials replace the
+    /// `<section>`s of slides with `<!-- .slide: layout="name" -->`. Defaults
+    /// to the `partials` directory next to the template, if there is one.
+    #[structopt(long = "partials-dir", alias = "partials", parse(from_os_str))]
+    partials: Option<PathBuf>,
+
+    /// Fail when the template uses a variable that isn't defined, rather
+    /// than rendering it as nothing. Front matter keys count as defined.
+    #[structopt(long)]
+    strict_templates: bool,
+
+    /// A command to pass the rendered slides through before the template is
+    /// rendered, like Pandoc's filters. It reads the slides, their metadata,
+    /// and the slideshow's title as JSON from standard input, and writes them
+    /// back, changed, to standard output. Can be given more than once, to
+    /// filter the slides through each command in order.
+    #[structopt(long = "filter", number_of_values = 1)]
+    filters: Vec<String>,
+
+    /// How long each `--filter` has to finish, in seconds.
+    #[structopt(long, default_value = "30")]
+    filter_timeout: u64,
+
+    /// A built-in theme for the default template, written to `theme.css` in
+    /// the output directory: `light`, `dark`, `solarized`, or `plain`.
+    /// Templates get its name as `{{theme}}`.
+    #[structopt(long, default_value = "light", possible_values = theme::Theme::NAMES)]
+    theme: theme::Theme,
+
+    /// A stylesheet to write to `theme.css` instead of a built-in theme.
+    /// Templates get its file name without the extension as `{{theme}}`.
+    #[structopt(long, parse(from_os_str))]
+    theme_file: Option<PathBuf>,
+
+    /// Write an `index.html` which works on its own, even from `file://`,
+    /// with the stylesheets, scripts, and media it refers to inlined, rather
+    /// than copying the static files directory.
+    #[structopt(long)]
+    self_contained: bool,
+
+    /// With `--self-contained`, warn about inlining files larger than this
+    /// many KiB.
+    #[structopt(long, default_value = "1024")]
+    inline_warning_kib: u64,
+
+    /// Minify the written HTML, dropping comments and whitespace between
+    /// tags and minifying inline stylesheets. Code blocks are kept as they
+    /// are. Comments starting with `<!--!` are kept too.
+    #[structopt(long)]
+    minify: bool,
+
+    /// Add hashes of their contents to the names of the static files copied
+    /// into the output directory, like `style.1a2b3c4d.css`, so browsers
+    /// don't use stale copies. Templates refer to them with `{{asset
+    /// "style.css"}}`, and stylesheets' `url(...)`s are rewritten.
+    #[structopt(long)]
+    fingerprint: bool,
+
+    /// The fingerprinted static files' names, by their original names, once
+    /// they're copied.
+    #[structopt(skip)]
+    fingerprints: RefCell<fingerprint::Manifest>,
+
+    /// A TOML file of options, named like the long options with underscores
+    /// (`static_dir = "assets"`, `md_extension = ["mark"]`), which the
+    /// command line overrides. Relative paths in it are relative to it.
+    /// Defaults to `slideshow.toml` next to the input file, if there is one.
+    /// Either way, it overrides the global `config.toml` in
+    /// `$XDG_CONFIG_HOME/simple-slideshow`, if there is one.
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Don't read any config files, the global one included, so only the
+    /// command line's options are used.
+    #[structopt(long, conflicts_with = "config")]
+    no_config: bool,
+
+    /// Write the slideshow to standard output with `--output -`, rather
+    /// than into the output directory, which is left alone along with the
+    /// static files. Or write it to a file like `--output
+    /// talks/rustconf.html`, which gives the output directory and
+    /// `--output-name`.
+    #[structopt(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// The slideshow's file name in the output directory, rather than
+    /// `index.html`, like `--output-name rustconf.html`. The handout is
+    /// written next to it as `rustconf.handout.html`. Only for a single
+    /// input file.
+    #[structopt(long)]
+    output_name: Option<String>,
+
+    /// Delete everything in the output directory before building, so files
+    /// that aren't part of the slideshow anymore don't linger there.
+    #[structopt(long, conflicts_with = "prune")]
+    clean: bool,
+
+    /// Delete the files in the output directory which the build didn't
+    /// write, after building.
+    #[structopt(long)]
+    prune: bool,
+
+    /// Open the slideshow in a browser once it's built; with `serve`, open
+    /// its URL.
+    #[structopt(long)]
+    open: bool,
+
+    /// A shell command to run before building, like `--pre-build 'sass
+    /// static-src/ static/'`. If it fails, the slideshow isn't built. Can be
+    /// given more than once. Commands get the environment variables
+    /// `SLIDESHOW_INPUT`, `SLIDESHOW_OUTPUT_DIR`, and
+    /// `SLIDESHOW_OUTPUT_FILE`, with several inputs and output files
+    /// separated like `$PATH`. When watching, they're run before each
+    /// rebuild.
+    #[structopt(long, number_of_values = 1)]
+    pre_build: Vec<String>,
+
+    /// A shell command to run after building, like `--post-build 'rsync -r
+    /// "$SLIDESHOW_OUTPUT_DIR" host:talks'`, with the same environment
+    /// variables as `--pre-build`. Can be given more than once. When
+    /// watching, they're run after each update.
+    #[structopt(long, number_of_values = 1)]
+    post_build: Vec<String>,
+
+    /// Fail the build if a `--post-build` command fails, rather than only
+    /// reporting it.
+    #[structopt(long)]
+    strict_hooks: bool,
+
+    /// The files written into the output directory, for `--prune`.
+    #[structopt(skip)]
+    written: RefCell<HashSet<PathBuf>>,
+
+    /// The slideshows showing a build error while watching, and whether
+    /// they're only the error, with no slideshow under it.
+    #[structopt(skip)]
+    overlaid: RefCell<Vec<(PathBuf, bool)>>,
+
+    /// With several input files, write each slideshow to `<name>.html` in
+    /// the output directory, rather than `<name>/index.html`.
+    #[structopt(long)]
+    flat_decks: bool,
+
+    /// Also render the files matching a glob pattern, like
+    /// `--input-glob 'decks/**/*.md'`. Can be given more than once.
+    #[structopt(long, number_of_values = 1)]
+    input_glob: Vec<String>,
+
+    /// Input Markdown files, or `-` for standard input, and then the output
+    /// directory, which defaults to `out`. The last path is the output
+    /// directory, unless it's the only input or ends with `.md` or
+    /// `.markdown`. Several input files are rendered as separate slideshows,
+    /// with an `index.html` listing them. Quoted glob patterns like
+    /// `'slides/*.md'` are expanded to the files matching them.
+    #[structopt(parse(from_os_str), required_unless = "input-glob", min_values = 1)]
+    paths: Vec<PathBuf>,
+
+    /// The input files and patterns from `paths`.
+    #[structopt(skip)]
+    input_paths: Vec<PathBuf>,
+
+    /// The input files, with patterns expanded.
+    #[structopt(skip)]
+    inputs: Vec<PathBuf>,
+
+    /// The output directory, from `paths`.
+    #[structopt(skip)]
     output_dir: PathBuf,
 }
 
+/// The start of the `index.html` listing several decks, which is followed
+/// by a list item linking to each.
+const DECK_LIST_START: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Slideshows</title>
+<link rel="stylesheet" href="theme.css">
+<style>
+body { max-width: 40em; margin: 2em auto; padding: 0 1em; }
+</style>
+</head>
+<body>
+<h1>Slideshows</h1>
+<ul>
+"#;
+
+/// One of the slideshows being built, from one of the input files.
+#[derive(Debug)]
+struct Deck {
+    input: PathBuf,
+    /// The input file's name without its extension.
+    name: String,
+    /// The directory the slideshow and the files it refers to are written
+    /// to: the output directory, or one in it for one of several decks.
+    dir: PathBuf,
+    /// The slideshow's file name in `dir`.
+    file_name: String,
+    /// The path from `dir` to the output directory.
+    root: String,
+}
+
+impl Deck {
+    fn input_dir(&self) -> &Path {
+        self.input.parent().unwrap_or(&self.input)
+    }
+
+    fn output_file(&self) -> PathBuf {
+        self.dir.join(&self.file_name)
+    }
+
+    fn handout_file(&self) -> PathBuf {
+        if self.file_name == "index.html" {
+            self.dir.join("handout.html")
+        } else {
+            let stem = Path::new(&self.file_name).file_stem().unwrap_or_default();
+            self.dir
+                .join(format!("{}.handout.html", stem.to_string_lossy()))
+        }
+    }
+
+    fn reads_stdin(&self) -> bool {
+        self.input == Path::new("-")
+    }
+}
+
+/// The exit status for errors rendering a slideshow.
+const EXIT_RENDER: i32 = 1;
+/// The exit status for errors in the arguments, including paths in them
+/// that don't exist.
+const EXIT_USAGE: i32 = 2;
+/// The exit status for errors which stop `watch` or `serve`.
+const EXIT_WATCH: i32 = 3;
+
 fn main() {
     if let Err(e) = main_inner() {
-        println!("{}", e);
+        print_error(&*e);
+        process::exit(exit_code(&*e));
+    }
+}
+
+/// Prints `err` to standard error, with the errors that caused it on
+/// indented lines under it.
+fn print_error(err: &(dyn error::Error + 'static)) {
+    for (depth, line) in error_lines(err).into_iter().enumerate() {
+        if depth == 0 {
+            eprintln!("{} {}", logging::red("error:"), line);
+        } else {
+            eprintln!(
+                "{:indent$}{} {}",
+                "",
+                logging::bold("caused by:"),
+                line,
+                indent = 2 * depth
+            );
+        }
     }
 }
 
+/// The messages of `err` and each error that caused it.
+fn error_lines(err: &(dyn error::Error + 'static)) -> Vec<String> {
+    let mut messages = vec![err.to_string()];
+    let mut source = err.source();
+    while let Some(err) = source {
+        messages.push(err.to_string());
+        source = err.source();
+    }
+    // Messages usually end with their sources' messages, like `Error
+    // reading slides.md: No such file`, which go on their own lines instead.
+    let mut lines = Vec::new();
+    for (i, message) in messages.iter().enumerate() {
+        let line = match messages.get(i + 1) {
+            Some(next) if next == message => continue,
+            Some(next) => message
+                .strip_suffix(next.as_str())
+                .and_then(|message| message.strip_suffix(": "))
+                .unwrap_or(message),
+            None => message,
+        };
+        lines.push(line.to_owned());
+    }
+    lines
+}
+
+fn exit_code(err: &(dyn error::Error + 'static)) -> i32 {
+    if err.is::<PathErr>() || err.is::<config::ConfigErr>() || err.is::<glob::GlobErr>() {
+        EXIT_USAGE
+    } else if err.is::<WatchErr>() {
+        EXIT_WATCH
+    } else {
+        EXIT_RENDER
+    }
+}
+
+/// Exits with an error in the arguments, like clap's `Error::exit` but with
+/// `EXIT_USAGE`.
+fn exit_usage(err: structopt::clap::Error) -> ! {
+    if err.use_stderr() {
+        eprintln!("{}", err.message);
+        process::exit(EXIT_USAGE);
+    }
+    // `--help` and `--version` aren't errors.
+    println!("{}", err.message);
+    process::exit(0);
+}
+
 fn main_inner() -> Result<(), Box<dyn error::Error>> {
-    use tracing_subscriber::fmt::format::Format;
+    let cli_args = with_default_command(env::args_os().collect());
+    let matches = Cli::clap()
+        .get_matches_from_safe(&cli_args)
+        .unwrap_or_else(|err| exit_usage(err));
+    let cli = Cli::from_clap(&matches);
+    logging::init(cli.level(), cli.log_format, cli.color);
+    if cli.print_default_template {
+        print!("{}", markdown::DEFAULT_TEMPLATE);
+        return Ok(());
+    }
+    if cli.version_json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&version::Version::get())?
+        );
+        return Ok(());
+    }
+    let command = match cli.command {
+        Some(Command::New(new)) => {
+            scaffold::new(&new.dir, new.theme, new.force)?;
+            return Ok(());
+        }
+        Some(Command::Completions { shell }) => {
+            // Complete the name the binary was run as, which might not be
+            // `builder` once it's installed.
+            let name = cli_args
+                .first()
+                .map(Path::new)
+                .and_then(Path::file_stem)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "builder".to_owned());
+            Cli::clap().gen_completions_to(name, shell, &mut io::stdout());
+            return Ok(());
+        }
+        Some(command) => command,
+        None => {
+            Cli::clap().print_help()?;
+            println!();
+            return Ok(());
+        }
+    };
 
-    let opt = {
-        let mut opt = Opt::from_args();
-        opt.static_dir = opt
-            .static_dir
-            .canonicalize()
-            .expect("Canonicalize static_dir");
-        opt.template = opt.template.canonicalize().expect("Canonicalize template");
-        opt.input = opt.input.canonicalize().expect("Canonicalize input");
+    // The config files' options are added to the command line's, which is
+    // parsed again with them. The command line overrides the deck's config
+    // file, which overrides the global one.
+    let (name, cli_opt) = command.opt();
+    let config_path = match &cli_opt.config {
+        _ if cli_opt.no_config => None,
+        Some(config) => Some(config.clone()),
+        None => Some(
+            cli_opt
+                .paths
+                .first()
+                .map(|path| match path.to_str() {
+                    Some(pattern) if glob::is_pattern(path) => glob::base(pattern),
+                    _ => path.parent().unwrap_or_else(|| Path::new("")).to_owned(),
+                })
+                .unwrap_or_default()
+                .join(config::FILE_NAME),
+        )
+        .filter(|config| config.is_file()),
+    };
+    let global_path = dirs::config_dir()
+        .map(|dir| dir.join(config::GLOBAL_FILE_NAME))
+        .filter(|config| !cli_opt.no_config && config.is_file());
+    let configs = config_path
+        .into_iter()
+        .chain(global_path)
+        .map(|path| config::Config::load(&path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let command = match configs.as_slice() {
+        [] => command,
+        configs => {
+            // Each file's options, for the options earlier ones don't give.
+            let mut config_args = Vec::new();
+            for config in configs {
+                let given = config_args
+                    .iter()
+                    .chain(&cli_args[1..])
+                    .cloned()
+                    .collect::<Vec<_>>();
+                config_args.extend(config.args(&given, |long| is_option(name, long)));
+            }
+            let mut args = cli_args.clone();
+            let end = args
+                .iter()
+                .position(|arg| arg == "--")
+                .unwrap_or(args.len());
+            args.splice(end..end, config_args);
+            let matches = Cli::clap()
+                .get_matches_from_safe(args)
+                .unwrap_or_else(|err| exit_usage(err));
+            match Cli::from_clap(&matches).command {
+                Some(command) => command,
+                None => unreachable!("The command line gave a command before"),
+            }
+        }
+    };
+    let (mut opt, mode) = command.into_opt();
+    let mut paths = opt.paths.clone();
+    let output_dir = match paths.last() {
+        Some(last)
+            if (paths.len() > 1 || !opt.input_glob.is_empty())
+                && !is_markdown(last)
+                && !glob::is_pattern(last) =>
+        {
+            paths.pop()
+        }
+        _ => None,
+    };
+    if let Some(name) = &opt.output_name {
+        if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+            exit_usage(structopt::clap::Error::with_description(
+                &format!(
+                    "`--output-name` takes a file name, not {:?}; give a path with `--output`",
+                    name
+                ),
+                structopt::clap::ErrorKind::InvalidValue,
+            ));
+        }
+    }
+    opt.output_dir = match opt.output.clone().filter(|output| output != Path::new("-")) {
+        Some(output) => {
+            let name = output.file_name().map(|name| name.to_string_lossy());
+            if opt.output_name.is_some() || output_dir.is_some() {
+                exit_usage(structopt::clap::Error::with_description(
+                    "`--output` with a path can't be given with `--output-name` \
+                     or an output directory",
+                    structopt::clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+            match name {
+                Some(name) if !output.to_string_lossy().ends_with(['/', '\\']) => {
+                    opt.output_name = Some(name.into_owned());
+                }
+                _ => exit_usage(structopt::clap::Error::with_description(
+                    &format!("`--output` takes a file, not {}", output.display()),
+                    structopt::clap::ErrorKind::InvalidValue,
+                )),
+            }
+            match output.parent() {
+                Some(dir) if dir != Path::new("") => dir.to_owned(),
+                _ => PathBuf::from("."),
+            }
+        }
+        None => output_dir.unwrap_or_else(|| {
+            configs
+                .iter()
+                .find_map(|config| config.output_dir.clone())
+                .unwrap_or_else(|| PathBuf::from("out"))
+        }),
+    };
+    if let Some(key) = opt.duplicate_var() {
+        exit_usage(structopt::clap::Error::with_description(
+            &format!("`--var {}` is given more than once", key),
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    let reads_stdin = paths.iter().any(|input| input == Path::new("-"));
+    if reads_stdin && (paths.len() > 1 || !opt.input_glob.is_empty()) {
+        exit_usage(structopt::clap::Error::with_description(
+            "Standard input can't be rendered along with other input files",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    if !reads_stdin {
+        for path in &mut paths {
+            if !glob::is_pattern(path) {
+                *path = path
+                    .canonicalize()
+                    .map_err(|e| PathErr::Input(path.clone(), e))?;
+            }
+        }
+    }
+    opt.input_paths = paths;
+    opt.inputs = opt.find_inputs()?;
+    if opt.output_name.is_some() && opt.inputs.len() > 1 {
+        exit_usage(structopt::clap::Error::with_description(
+            "`--output` and `--output-name` are only for a single input file",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    if opt.open && opt.writes_stdout() {
+        exit_usage(structopt::clap::Error::with_description(
+            "`--open` can't open a slideshow written to standard output",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    if opt.writes_stdout() && opt.inputs.len() > 1 {
+        exit_usage(structopt::clap::Error::with_description(
+            "Only one slideshow can be written to standard output",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    if !matches!(mode, Mode::Build) && (reads_stdin || opt.writes_stdout()) {
+        exit_usage(structopt::clap::Error::with_description(
+            &format!(
+                "`{}` can't watch standard input or write to standard output",
+                name
+            ),
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+    opt.template = match opt.template.take() {
+        Some(template) if template.components().count() > 1 => Some(
+            template
+                .canonicalize()
+                .map_err(|e| BuildErr::Template(template, e))?,
+        ),
+        Some(template) => Some(
+            opt.find_template(&template)
+                .ok_or_else(|| BuildErr::TemplateNotFound(template))?,
+        ),
+        None => opt.find_template(Path::new("template.html")),
+    };
+    if opt.print_template_path {
+        match &opt.template {
+            Some(template) => println!("{}", template.display()),
+            None => println!("(built-in)"),
+        }
+        return Ok(());
+    }
+    if !opt.writes_stdout() {
+        let mut static_dirs = Vec::with_capacity(opt.static_dirs.len());
+        for static_dir in opt.static_dirs {
+            if static_dir.exists() {
+                static_dirs.push(canonicalize_option(static_dir, "static-dir")?);
+            } else {
+                opt.diagnostics.warn(format_args!(
+                    "The static files directory {} doesn't exist, so its files aren't copied; \
+                     give another one with `--static-dir`",
+                    static_dir.display()
+                ));
+            }
+        }
+        opt.static_dirs = static_dirs;
+        let created = !opt.output_dir.exists();
+        make_output(&opt.output_dir).map_err(|e| PathErr::OutputDir(opt.output_dir.clone(), e))?;
         opt.output_dir = opt
             .output_dir
             .canonicalize()
-            .expect("Canonicalize output_dir");
-        opt
+            .map_err(|e| PathErr::OutputDir(opt.output_dir.clone(), e))?;
+        if let Err(err) = opt.check_paths() {
+            if created {
+                let _ = fs::remove_dir(&opt.output_dir);
+            }
+            return Err(err.into());
+        }
+    }
+    opt.partials = match opt.partials {
+        Some(partials) => Some(canonicalize_option(partials, "partials-dir")?),
+        None => {
+            let dir = match &opt.template {
+                Some(template) => template.parent().map(Path::to_owned),
+                None => env::current_dir().ok(),
+            };
+            dir.map(|dir| dir.join("partials"))
+                .filter(|partials| partials.is_dir())
+        }
     };
+    opt.handout_template = opt
+        .handout_template
+        .map(|handout_template| canonicalize_option(handout_template, "handout-template"))
+        .transpose()?;
+    opt.data = opt
+        .data
+        .map(|data| canonicalize_option(data, "data"))
+        .transpose()?;
+    opt.theme_file = opt
+        .theme_file
+        .map(|theme_file| canonicalize_option(theme_file, "theme-file"))
+        .transpose()?;
+    if let Some(report) = &opt.report {
+        // Absolute like the output directory, so `--prune` keeps it.
+        let dir = match report.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        if let (Ok(dir), Some(name)) = (dir.canonicalize(), report.file_name()) {
+            opt.report = Some(dir.join(name));
+        }
+    }
+    // The output directory can't hold the inputs or static files, which
+    // `check_paths` makes sure of, but it can still be the root.
+    if (opt.clean || opt.prune) && !opt.writes_stdout() && opt.output_dir.parent().is_none() {
+        exit_usage(structopt::clap::Error::with_description(
+            &format!(
+                "Refusing to delete files in the output directory {}, because it's the root \
+                 directory",
+                opt.output_dir.display(),
+            ),
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
 
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(opt.trace_level.clone())
-        // .event_format(Format::default().compact())
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("setting tracing default subscriber failed");
+    for config in &configs {
+        event!(Level::INFO, "Using config file {}", config.path.display());
+        let unknown = config.unknown(|long| {
+            ["build", "watch", "serve"]
+                .iter()
+                .any(|name| is_option(name, long))
+        });
+        for key in unknown {
+            opt.diagnostics.warn(format_args!(
+                "Unknown option {:?} in config file {}",
+                key,
+                config.path.display()
+            ));
+        }
+    }
+    match &opt.template {
+        Some(template) => event!(Level::INFO, "Using template {}", template.display()),
+        None => event!(Level::INFO, "Using the built-in template"),
+    }
 
-    if opt.watch {
-        opt.watch()?;
-    } else {
-        opt.render()?;
+    let decks = opt.decks(&opt.inputs);
+    if let Some(name) = duplicate_name(&decks) {
+        exit_usage(structopt::clap::Error::with_description(
+            &format!("Several input files are named {:?}", name),
+            structopt::clap::ErrorKind::ArgumentConflict,
+        ));
+    }
+
+    match mode {
+        Mode::Build => {
+            opt.run_pre_build(&decks)?;
+            let rendered = opt.render(&decks)?;
+            opt.write_report(&decks, &rendered)?;
+            let broken_links: usize = rendered.iter().map(|res| res.broken_links.len()).sum();
+            if broken_links > 0 {
+                return Err(BuildErr::BrokenLinks(broken_links).into());
+            }
+            let lint: usize = rendered.iter().map(|res| res.lint.len()).sum();
+            if opt.lint_level() == Some(lint::LintLevel::Deny) && lint > 0 {
+                return Err(BuildErr::Lint(lint).into());
+            }
+            opt.check_warnings()?;
+            opt.run_post_build(&decks)?;
+            if opt.open {
+                open::open(opt.opened_file(&decks));
+            }
+        }
+        Mode::Watch(watch) => {
+            let open = opt.open.then(|| opt.opened_file(&decks).into_os_string());
+            opt.watch(&watch, decks, open)?
+        }
+        Mode::Serve(watch, serve) => {
+            let addr = serve::spawn(opt.output_dir.clone(), (serve.host.as_str(), serve.port))
+                .map_err(|err| WatchErr::Serve(serve.host.clone(), serve.port, err))?;
+            let file_name = match decks.as_slice() {
+                [deck] if deck.file_name != "index.html" => deck.file_name.as_str(),
+                _ => "",
+            };
+            let url = format!("http://{}/{}", addr, file_name);
+            println!("Serving the slideshow at {}", url);
+            opt.watch(&watch, decks, opt.open.then(|| url.into()))?;
+        }
     }
     Ok(())
 }
 
+/// What to do with the slideshow once the options are worked out.
+enum Mode {
+    Build,
+    Watch(WatchOpt),
+    Serve(WatchOpt, ServeOpt),
+}
+
+impl Command {
+    /// The name of the command and its rendering options, unless it's `new`.
+    fn opt(&self) -> (&'static str, &Opt) {
+        match self {
+            Command::Build(opt) => ("build", opt),
+            Command::Watch { opt, .. } => ("watch", opt),
+            Command::Serve { opt, .. } => ("serve", opt),
+            Command::New(_) | Command::Completions { .. } => {
+                unreachable!("`new` and `completions` don't render a slideshow")
+            }
+        }
+    }
+
+    fn into_opt(self) -> (Opt, Mode) {
+        match self {
+            Command::Build(opt) => (opt, Mode::Build),
+            Command::Watch { opt, watch } => (opt, Mode::Watch(watch)),
+            Command::Serve { opt, watch, serve } => (opt, Mode::Serve(watch, serve)),
+            Command::New(_) | Command::Completions { .. } => {
+                unreachable!("`new` and `completions` don't render a slideshow")
+            }
+        }
+    }
+}
+
+/// Adds `build` to command lines without a command, like `builder
+/// input.md`, or `watch` in place of the old `--watch` flag.
+fn with_default_command(mut args: Vec<OsString>) -> Vec<OsString> {
+    // Skip the global options, which may come before the command.
+    let mut i = 1;
+    while let Some(arg) = args.get(i).map(|arg| arg.to_string_lossy()) {
+        if arg == "--trace-level" || arg == "--log-format" || arg == "--color" {
+            i += 2;
+        } else if arg.starts_with("--trace-level=")
+            || arg.starts_with("--log-format=")
+            || arg.starts_with("--color=")
+            || arg == "--verbose"
+            || arg == "--quiet"
+            // Short flags like `-vv`.
+            || arg.strip_prefix('-').is_some_and(|flags| {
+                !flags.is_empty() && flags.chars().all(|c| c == 'v' || c == 'q')
+            })
+        {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    let first = match args.get(i) {
+        Some(first) => first.to_string_lossy(),
+        None => return args,
+    };
+    let top_level = [
+        "-h",
+        "--help",
+        "-V",
+        "--version",
+        "--print-default-template",
+        "--version-json",
+    ];
+    if Command::NAMES.contains(&first.as_ref()) || top_level.contains(&first.as_ref()) {
+        return args;
+    }
+    let end = args
+        .iter()
+        .position(|arg| arg == "--")
+        .unwrap_or(args.len());
+    match args[..end]
+        .iter()
+        .position(|arg| arg == "-w" || arg == "--watch")
+    {
+        Some(watch) => {
+            args.remove(watch);
+            args.insert(i, "watch".into());
+        }
+        None => args.insert(i, "build".into()),
+    }
+    args
+}
+
+/// The absolute path of a file or directory given with the option `--long`.
+fn canonicalize_option(path: PathBuf, long: &'static str) -> Result<PathBuf, PathErr> {
+    path.canonicalize()
+        .map_err(|err| PathErr::Option { path, long, err })
+}
+
+/// The name of a deck which has the same name as another, which would have
+/// it overwrite the other.
+fn duplicate_name(decks: &[Deck]) -> Option<&str> {
+    decks
+        .iter()
+        .enumerate()
+        .find(|(i, deck)| decks[..*i].iter().any(|other| other.name == deck.name))
+        .map(|(_, deck)| deck.name.as_str())
+}
+
+/// Does `path` look like an input file, rather than the output directory?
+fn is_markdown(path: &Path) -> bool {
+    path == Path::new("-")
+        || path
+            .extension()
+            .is_some_and(|extension| extension == "md" || extension == "markdown")
+}
+
+/// Is `long` the name of one of the options of the command `name`?
+fn is_option(name: &str, long: &str) -> bool {
+    let args = ["builder".to_owned(), name.to_owned(), format!("--{}", long)];
+    match Cli::clap().get_matches_from_safe(&args) {
+        Err(err) => err.kind != ErrorKind::UnknownArgument,
+        Ok(_) => true,
+    }
+}
+
+#[derive(Error, Debug)]
+enum PathErr {
+    #[error("Couldn't find input file {0}: {1}")]
+    Input(PathBuf, #[source] io::Error),
+
+    #[error("Couldn't find {path}, given with `--{long}`: {err}")]
+    Option {
+        path: PathBuf,
+        long: &'static str,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("Couldn't create output directory {0}: {1}")]
+    OutputDir(PathBuf, #[source] io::Error),
+
+    #[error(
+        "The output directory {0} contains the input file {1}, so building would write over \
+         the sources; give an output directory outside the input's directory, like `out`"
+    )]
+    OutputHasInput(PathBuf, PathBuf),
+
+    #[error(
+        "The output directory {0} contains the static files directory {1}, so the static files \
+         would be copied over themselves; move the static files out of the output directory, \
+         or give another output directory"
+    )]
+    OutputHasStatic(PathBuf, PathBuf),
+
+    #[error(
+        "The static files directory {0} contains the output directory {1}, so each build would \
+         be copied into the next; give an output directory outside the static files directory"
+    )]
+    StaticHasOutput(PathBuf, PathBuf),
+}
+
 #[derive(Error, Debug)]
 enum CopyStaticErr {
+    #[error("{0} isn't in any of the static files directories")]
+    NotStatic(PathBuf),
+
     #[error("Error travering static files directory: {0}")]
     WalkDir(#[from] walkdir::Error),
 
@@ -97,26 +1345,89 @@ enum CopyStaticErr {
     Copy {
         from: PathBuf,
         to: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("Error traversing static files directory, while creating {dir}: {err}")]
+    CreateDir {
+        dir: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("Error reading static file {path}: {err}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+
+    #[error("Error writing static file {path}: {err}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        err: io::Error,
+    },
+}
+
+#[derive(Error, Debug)]
+enum BuildErr {
+    #[error("{0}")]
+    CopyStatic(#[from] CopyStaticErr),
+
+    #[error("{0}")]
+    Render(#[from] markdown::RenderError),
+
+    #[error("Error reading template {0}, given with `--template`: {1}")]
+    Template(PathBuf, #[source] io::Error),
+
+    #[error(
+        "Couldn't find template {0} in the input file's directory, the current directory, \
+         or the config directory, $XDG_CONFIG_HOME/simple-slideshow"
+    )]
+    TemplateNotFound(PathBuf),
+
+    #[error("Error creating output file {0}: {1}")]
+    OutputFile(PathBuf, #[source] io::Error),
+
+    #[error("Error writing output file {0}: {1}")]
+    OutputWrite(PathBuf, #[source] io::Error),
+
+    #[error("Error reading standard input: {0}")]
+    Stdin(#[source] io::Error),
+
+    #[error("Error writing to standard output: {0}")]
+    Stdout(#[source] io::Error),
+
+    #[error("{0}")]
+    Highlight(#[from] highlight::HighlightError),
+
+    #[error("Error copying {from} to {to}: {err}")]
+    CopyAsset {
+        from: PathBuf,
+        to: PathBuf,
+        #[source]
         err: io::Error,
     },
 
-    #[error("Error traversing static files directory, while creating {dir}: {err}")]
-    CreateDir { dir: PathBuf, err: io::Error },
-}
+    #[error("Error deleting {0} from the output directory: {1}")]
+    Clean(PathBuf, #[source] io::Error),
 
-#[derive(Error, Debug)]
-enum BuildErr {
-    #[error("{0}")]
-    CopyStatic(#[from] CopyStaticErr),
+    #[error("Error reading the output directory: {0}")]
+    ReadOutput(#[from] walkdir::Error),
 
-    #[error("{0}")]
-    Render(#[from] markdown::RenderError),
+    #[error("Found {0} broken link(s)")]
+    BrokenLinks(usize),
 
-    #[error("Error creating output file {0}: {1}")]
-    OutputFile(PathBuf, io::Error),
+    #[error("Found {0} accessibility problem(s)")]
+    Lint(usize),
 
-    #[error("Error writing output file {0}: {1}")]
-    OutputWrite(PathBuf, io::Error),
+    #[error("Found {0} warning(s), which --deny-warnings makes errors")]
+    Warnings(usize),
+
+    #[error("{0}")]
+    Hook(#[from] hooks::HookErr),
 }
 
 #[derive(Error, Debug)]
@@ -128,34 +1439,132 @@ enum WatchErr {
     Recv(#[from] std::sync::mpsc::RecvError),
 
     #[error("{0}")]
-    Notify(notify::Error, Option<PathBuf>),
+    Notify(#[source] notify::Error, Option<PathBuf>),
+
+    #[error("Couldn't serve the slideshow on {0}:{1}: {2}")]
+    Serve(String, u16, #[source] io::Error),
 
     #[error("{0}")]
     Build(#[from] BuildErr),
 }
 
 impl Opt {
-    fn output_file(&self) -> PathBuf {
-        self.output_dir.join("index.html")
+    /// The input files, with the patterns among them and `--input-glob`s
+    /// expanded to the files matching them, leaving out any in the output
+    /// directory.
+    fn find_inputs(&self) -> Result<Vec<PathBuf>, glob::GlobErr> {
+        let mut inputs = Vec::new();
+        for path in &self.input_paths {
+            match path.to_str() {
+                Some(pattern) if glob::is_pattern(path) => {
+                    inputs.extend(glob::expand(pattern, &self.output_dir)?)
+                }
+                _ => inputs.push(path.clone()),
+            }
+        }
+        for pattern in &self.input_glob {
+            inputs.extend(glob::expand(pattern, &self.output_dir)?);
+        }
+        let mut ret: Vec<PathBuf> = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let input = input.canonicalize().unwrap_or(input);
+            if !ret.contains(&input) {
+                ret.push(input);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Does the input include glob patterns, whose matches might change?
+    fn has_patterns(&self) -> bool {
+        !self.input_glob.is_empty() || self.input_paths.iter().any(|path| glob::is_pattern(path))
+    }
+
+    /// The file to open for `--open`: the slideshow, or the list of them.
+    fn opened_file(&self, decks: &[Deck]) -> PathBuf {
+        match decks {
+            [deck] => deck.output_file(),
+            _ => self.output_dir.join("index.html"),
+        }
+    }
+
+    /// The slideshows to build from `inputs`: one into the output directory,
+    /// or several into their own directories or files in it.
+    fn decks(&self, inputs: &[PathBuf]) -> Vec<Deck> {
+        inputs
+            .iter()
+            .map(|input| {
+                let name = input
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let (dir, file_name, root) = if inputs.len() == 1 {
+                    let file_name = self.output_name.as_deref().unwrap_or("index.html");
+                    (self.output_dir.clone(), file_name.to_owned(), "")
+                } else if self.flat_decks {
+                    (self.output_dir.clone(), format!("{}.html", name), "")
+                } else {
+                    (self.output_dir.join(&name), "index.html".to_owned(), "../")
+                };
+                Deck {
+                    input: input.clone(),
+                    name,
+                    dir,
+                    file_name,
+                    root: root.to_owned(),
+                }
+            })
+            .collect()
+    }
+
+    /// The index of the static directory `path` is in, if it's in one. With
+    /// one directory in another, it's in the inner one.
+    fn static_root(&self, path: &Path) -> Option<usize> {
+        self.static_dirs
+            .iter()
+            .enumerate()
+            .filter(|(_, dir)| path.starts_with(dir))
+            .max_by_key(|(_, dir)| dir.components().count())
+            .map(|(i, _)| i)
     }
 
     #[instrument(skip(self), err)]
     fn copy_single_static(&self, path: PathBuf) -> Result<(), CopyStaticErr> {
-        let rel = path.strip_prefix(&self.static_dir)?;
+        let root = self
+            .static_root(&path)
+            .ok_or_else(|| CopyStaticErr::NotStatic(path.clone()))?;
+        let rel = path.strip_prefix(&self.static_dirs[root])?;
+        // Files in later directories replace this one.
+        if let Some(dir) = self.static_dirs[root + 1..]
+            .iter()
+            .find(|dir| path.is_file() && dir.join(rel).is_file())
+        {
+            event!(
+                Level::INFO,
+                ?path,
+                "Not copying, because it's replaced by {}",
+                dir.join(rel).display()
+            );
+            return Ok(());
+        }
         let dest = self.output_dir.join(rel);
         if path.is_dir() {
+            self.wrote(&dest);
             if !dest.exists() {
                 event!(Level::INFO, created_dir = ?dest);
                 fs::create_dir_all(&dest)
                     .map_err(|e| CopyStaticErr::CreateDir { dir: dest, err: e })?;
             }
         } else {
+            self.wrote(&dest);
             event!(Level::INFO, from = ?path, to = ?dest);
-            fs::copy(&path, &dest).map_err(|e| CopyStaticErr::Copy {
-                from: path,
-                to: dest,
+            let bytes = fs::copy(&path, &dest).map_err(|e| CopyStaticErr::Copy {
+                from: path.clone(),
+                to: dest.clone(),
                 err: e,
             })?;
+            self.build_report.borrow_mut().copied(&path, &dest, bytes);
         }
         Ok(())
     }
@@ -164,10 +1573,120 @@ impl Opt {
     fn copy_static(&self) -> Result<(), CopyStaticErr> {
         use walkdir::WalkDir;
 
-        for entry in WalkDir::new(&self.static_dir).follow_links(true) {
-            let path = entry?.into_path();
-            event!(Level::INFO, ?path);
-            self.copy_single_static(path)?;
+        let mut files: Vec<fingerprint::StaticFile> = Vec::new();
+        // The fingerprinted files' paths, by their names.
+        let mut sources = HashMap::new();
+        for (i, static_dir) in self.static_dirs.iter().enumerate() {
+            for entry in WalkDir::new(static_dir).follow_links(true) {
+                let path = entry?.into_path();
+                event!(Level::INFO, ?path);
+                let rel = path.strip_prefix(static_dir)?;
+                if path.is_file() {
+                    if let Some(earlier) = self.static_dirs[..i]
+                        .iter()
+                        .map(|dir| dir.join(rel))
+                        .find(|earlier| earlier.is_file())
+                    {
+                        event!(
+                            Level::INFO,
+                            "{} replaces {}",
+                            path.display(),
+                            earlier.display()
+                        );
+                    }
+                }
+                if self.fingerprint && path.is_file() {
+                    let name = rel
+                        .components()
+                        .map(|component| component.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    let contents = fs::read(&path).map_err(|err| CopyStaticErr::Read {
+                        path: path.clone(),
+                        err,
+                    })?;
+                    files.retain(|file| file.name != name);
+                    sources.insert(name.clone(), path);
+                    files.push(fingerprint::StaticFile { name, contents });
+                } else {
+                    self.copy_single_static(path)?;
+                }
+            }
+        }
+        if self.fingerprint {
+            let (files, manifest) = fingerprint::fingerprint(files);
+            let names = manifest
+                .iter()
+                .map(|(name, fingerprinted)| (fingerprinted, &sources[name]))
+                .collect::<HashMap<_, _>>();
+            for file in files {
+                let dest = self.output_dir.join(&file.name);
+                self.wrote(&dest);
+                event!(Level::INFO, to = ?dest);
+                fs::write(&dest, &file.contents).map_err(|err| CopyStaticErr::Write {
+                    path: dest.clone(),
+                    err,
+                })?;
+                if let Some(source) = names.get(&file.name) {
+                    self.build_report.borrow_mut().copied(
+                        source,
+                        &dest,
+                        file.contents.len() as u64,
+                    );
+                }
+            }
+            *self.fingerprints.borrow_mut() = manifest;
+        }
+        Ok(())
+    }
+
+    /// Copies a changed static file into the output directory. Fingerprinted
+    /// files are all copied again, and the slideshow laid out with their new
+    /// names.
+    fn update_static(
+        &self,
+        path: PathBuf,
+        decks: &[Deck],
+        rendered: &[markdown::Rendered],
+    ) -> Result<(), BuildErr> {
+        if self.fingerprint {
+            self.copy_static()?;
+            for (deck, rendered) in decks.iter().zip(rendered) {
+                self.write_index(deck, rendered)?;
+                self.write_handout(deck, rendered)?;
+            }
+            Ok(())
+        } else {
+            Ok(self.copy_single_static(path)?)
+        }
+    }
+
+    /// Checks the output directory doesn't overlap the input files or the
+    /// static files directories, which would have building write over the
+    /// sources, or watching rebuild forever. The paths have to be absolute.
+    fn check_paths(&self) -> Result<(), PathErr> {
+        let output_dir = &self.output_dir;
+        for input in &self.inputs {
+            if input
+                .parent()
+                .is_some_and(|dir| dir.starts_with(output_dir))
+            {
+                return Err(PathErr::OutputHasInput(output_dir.clone(), input.clone()));
+            }
+        }
+        for static_dir in &self.static_dirs {
+            if static_dir.starts_with(output_dir) {
+                return Err(PathErr::OutputHasStatic(
+                    output_dir.clone(),
+                    static_dir.clone(),
+                ));
+            }
+            if output_dir.starts_with(static_dir) {
+                return Err(PathErr::StaticHasOutput(
+                    static_dir.clone(),
+                    output_dir.clone(),
+                ));
+            }
         }
         Ok(())
     }
@@ -176,51 +1695,782 @@ impl Opt {
         make_output(&self.output_dir).map_err(|e| BuildErr::OutputFile(self.output_dir.clone(), e))
     }
 
-    fn render(&self) -> Result<(), BuildErr> {
-        self.copy_static()?;
+    fn render(&self, decks: &[Deck]) -> Result<Vec<markdown::Rendered>, BuildErr> {
+        *self.build_report.borrow_mut() = report::Report::default();
+        if self.writes_stdout() {
+            let rendered = self.timed("render", || self.write_markdown_file(&decks[0]))?;
+            return Ok(vec![rendered]);
+        }
+        if self.clean {
+            self.timed("clean", || self.clean_output())?;
+        }
+        self.warn_static_collisions(decks);
+        if !self.self_contained {
+            self.timed("static", || self.copy_static())?;
+        }
         self.make_output_dir()?;
-        self.write_markdown_file()?;
+        self.write_highlight_css()?;
+        self.write_theme_css()?;
+        let rendered = self.timed("render", || {
+            decks
+                .iter()
+                .map(|deck| self.write_markdown_file(deck))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        self.write_deck_list(decks, &rendered)?;
+        if let Some(report) = &self.report {
+            self.wrote(report);
+        }
+        if self.prune {
+            self.timed("prune", || self.prune_output())?;
+        }
+        Ok(rendered)
+    }
+
+    /// Runs `f`, noting how long it took in the report as the phase `name`.
+    fn timed<T>(&self, name: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let ret = f();
+        self.build_report.borrow_mut().phase(name, start.elapsed());
+        ret
+    }
+
+    /// Writes the `--report`, with the slides and warnings in `rendered`.
+    fn write_report(
+        &self,
+        decks: &[Deck],
+        rendered: &[markdown::Rendered],
+    ) -> Result<(), BuildErr> {
+        let output = match &self.report {
+            Some(output) => output,
+            None => return Ok(()),
+        };
+        let mut report = self.build_report.borrow_mut();
+        report.slides = rendered.iter().map(|res| res.stats.slides).sum();
+        report.warnings.clear();
+        for (deck, res) in decks.iter().zip(rendered) {
+            let warning = |kind, slide, message| report::Warning {
+                kind,
+                input: deck.input.display().to_string(),
+                slide,
+                message,
+            };
+            let lint = res.lint.iter().map(|finding| {
+                warning(
+                    report::WarningKind::Lint,
+                    finding.slide,
+                    finding.message.clone(),
+                )
+            });
+            let overflow = res.overflow.iter().map(|finding| {
+                warning(
+                    report::WarningKind::Overflow,
+                    finding.slide,
+                    finding.message.clone(),
+                )
+            });
+            let broken_links = res.broken_links.iter().map(|link| {
+                warning(
+                    report::WarningKind::BrokenLink,
+                    link.slide,
+                    format!("Broken link to {}: {}", link.dest, link.reason),
+                )
+            });
+            let warnings = lint.chain(overflow).chain(broken_links).collect::<Vec<_>>();
+            report.warnings.extend(warnings);
+        }
+        // Paths are written as strings, so serializing can't fail.
+        let json = serde_json::to_string_pretty(&*report).unwrap();
+        fs::write(output, json).map_err(|e| BuildErr::OutputWrite(output.clone(), e))
+    }
+
+    /// Warns about static files with the same names as the slideshows, which
+    /// the slideshows replace. Fingerprinted files get new names.
+    fn warn_static_collisions(&self, decks: &[Deck]) {
+        if self.fingerprint {
+            return;
+        }
+        let outputs = decks
+            .iter()
+            .flat_map(|deck| [deck.output_file(), deck.handout_file()])
+            .chain((decks.len() > 1).then(|| self.output_dir.join("index.html")));
+        for output in outputs {
+            let rel = match output.strip_prefix(&self.output_dir) {
+                Ok(rel) => rel,
+                Err(_) => continue,
+            };
+            for static_dir in &self.static_dirs {
+                let path = static_dir.join(rel);
+                if path.is_file() {
+                    self.diagnostics.warn(format_args!(
+                        "The static file {} has the same name as the slideshow {}, which replaces it",
+                        path.display(),
+                        output.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    /// With `--deny-warnings`, fails if there were any warnings since this
+    /// was last called, listing them.
+    fn check_warnings(&self) -> Result<(), BuildErr> {
+        let warnings = self.diagnostics.take();
+        if !self.deny_warnings || warnings.is_empty() {
+            return Ok(());
+        }
+        for warning in &warnings {
+            eprintln!("warning: {}", warning);
+        }
+        Err(BuildErr::Warnings(warnings.len()))
+    }
+
+    /// The environment variables for `--pre-build` and `--post-build`
+    /// commands.
+    fn hook_env(&self, decks: &[Deck]) -> Vec<(&'static str, OsString)> {
+        let join = |paths: Vec<PathBuf>| match paths.as_slice() {
+            [path] => path.clone().into_os_string(),
+            paths => env::join_paths(paths).unwrap_or_default(),
+        };
+        let output_files = if self.writes_stdout() {
+            vec![PathBuf::from("-")]
+        } else {
+            decks.iter().map(Deck::output_file).collect()
+        };
+        vec![
+            (
+                "SLIDESHOW_INPUT",
+                join(decks.iter().map(|deck| deck.input.clone()).collect()),
+            ),
+            (
+                "SLIDESHOW_OUTPUT_DIR",
+                self.output_dir.clone().into_os_string(),
+            ),
+            ("SLIDESHOW_OUTPUT_FILE", join(output_files)),
+        ]
+    }
+
+    /// Runs the `--pre-build` commands, stopping at the first that fails.
+    fn run_pre_build(&self, decks: &[Deck]) -> Result<(), BuildErr> {
+        let env = self.hook_env(decks);
+        for command in &self.pre_build {
+            hooks::run(command, &env)?;
+        }
+        Ok(())
+    }
+
+    /// Runs the `--post-build` commands. Failures are only logged, unless
+    /// `--strict-hooks` is given.
+    fn run_post_build(&self, decks: &[Deck]) -> Result<(), BuildErr> {
+        let env = self.hook_env(decks);
+        for command in &self.post_build {
+            if let Err(err) = hooks::run(command, &env) {
+                if self.strict_hooks {
+                    return Err(err.into());
+                }
+                event!(Level::ERROR, "{}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Notes that `path` is part of the output, for `--prune`.
+    fn wrote(&self, path: &Path) {
+        if self.prune {
+            self.written.borrow_mut().insert(path.to_owned());
+        }
+    }
+
+    /// Deletes everything in the output directory, without following
+    /// symlinks out of it.
+    fn clean_output(&self) -> Result<(), BuildErr> {
+        let entries = fs::read_dir(&self.output_dir)
+            .map_err(|e| BuildErr::Clean(self.output_dir.clone(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| BuildErr::Clean(self.output_dir.clone(), e))?;
+            let path = entry.path();
+            // `file_type` is the symlink's own type, so symlinks to
+            // directories are removed rather than followed.
+            let result = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&path),
+                _ => fs::remove_file(&path),
+            };
+            result.map_err(|e| BuildErr::Clean(path.clone(), e))?;
+            event!(Level::INFO, removed = ?path);
+        }
+        Ok(())
+    }
+
+    /// Deletes the files in the output directory the build didn't write,
+    /// and the directories left empty.
+    fn prune_output(&self) -> Result<(), BuildErr> {
+        use walkdir::WalkDir;
+
+        let written = self.written.borrow();
+        for entry in WalkDir::new(&self.output_dir)
+            .min_depth(1)
+            .contents_first(true)
+        {
+            let entry = entry?;
+            let path = entry.path();
+            if written.contains(path) {
+                continue;
+            }
+            if entry.file_type().is_dir() {
+                let empty = fs::read_dir(path)
+                    .map_err(|e| BuildErr::Clean(path.to_owned(), e))?
+                    .next()
+                    .is_none();
+                if !empty || written.iter().any(|file| file.starts_with(path)) {
+                    continue;
+                }
+                fs::remove_dir(path).map_err(|e| BuildErr::Clean(path.to_owned(), e))?;
+            } else {
+                fs::remove_file(path).map_err(|e| BuildErr::Clean(path.to_owned(), e))?;
+            }
+            event!(Level::INFO, removed = ?path);
+        }
+        Ok(())
+    }
+
+    /// With several decks, writes an `index.html` linking to each of them.
+    fn write_deck_list(
+        &self,
+        decks: &[Deck],
+        rendered: &[markdown::Rendered],
+    ) -> Result<(), BuildErr> {
+        if decks.len() < 2 {
+            return Ok(());
+        }
+        let mut html = String::from(DECK_LIST_START);
+        for (deck, rendered) in decks.iter().zip(rendered) {
+            let href = if self.flat_decks {
+                deck.file_name.clone()
+            } else {
+                format!("{}/", deck.name)
+            };
+            html.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                handlebars::html_escape(&href),
+                handlebars::html_escape(rendered.title())
+            ));
+        }
+        html.push_str("</ul>\n</body>\n</html>\n");
+        let html = self.minify(Cow::Owned(html));
+        let output = self.output_dir.join("index.html");
+        self.wrote(&output);
+        self.build_report
+            .borrow_mut()
+            .output(&output, html.len() as u64);
+        fs::write(&output, html.as_bytes()).map_err(|e| BuildErr::OutputWrite(output, e))
+    }
+
+    fn write_theme_css(&self) -> Result<(), BuildErr> {
+        let output = self.output_dir.join("theme.css");
+        self.wrote(&output);
+        match &self.theme_file {
+            Some(theme_file) => {
+                fs::copy(theme_file, &output).map_err(|err| BuildErr::CopyAsset {
+                    from: theme_file.clone(),
+                    to: output.clone(),
+                    err,
+                })?;
+            }
+            None => {
+                fs::write(&output, self.theme.css())
+                    .map_err(|e| BuildErr::OutputWrite(output, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The name of the theme, for the template.
+    fn theme_name(&self) -> String {
+        match &self.theme_file {
+            Some(theme_file) => theme_file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+            None => self.theme.name().to_owned(),
+        }
+    }
+
+    fn write_highlight_css(&self) -> Result<(), BuildErr> {
+        if self.highlight != Some(highlight::HighlightStyle::Classes) {
+            return Ok(());
+        }
+        let css = highlight::css(&self.highlight_theme)?;
+        let output = self.output_dir.join("highlight.css");
+        self.wrote(&output);
+        fs::write(&output, css).map_err(|e| BuildErr::OutputWrite(output, e))?;
         Ok(())
     }
 
-    fn render_markdown_string(&self) -> Result<String, BuildErr> {
-        Ok(markdown::render(&self.input, &self.template)?)
+    fn slide_delimiters(&self) -> Vec<markdown::SlideDelimiter> {
+        if self.slide_delimiter.is_empty() {
+            return vec![markdown::SlideDelimiter::Rule];
+        }
+        self.slide_delimiter
+            .iter()
+            .flat_map(|delimiters| delimiters.0.clone())
+            .collect()
+    }
+
+    fn extensions(&self) -> Options {
+        let mut extensions = if self.no_default_extensions {
+            Options::empty()
+        } else {
+            markdown::DEFAULT_EXTENSIONS
+        };
+        for extension in &self.md_extensions {
+            if let markdown::MarkdownExtension::Parser(options) = extension {
+                extensions.insert(*options);
+            }
+        }
+        extensions
+    }
+
+    fn render_options(&self, deck: &Deck) -> markdown::RenderOptions {
+        markdown::RenderOptions {
+            slide_delimiters: self.slide_delimiters(),
+            vertical_delimiter: self.vertical_delimiter.clone(),
+            slide_on: self.slide_on,
+            fragments: self.fragments,
+            nested_fragments: self.nested_fragments,
+            keep_empty_slides: self.keep_empty_slides,
+            include_drafts: self.include_drafts,
+            slides: self.slides.clone(),
+            slide_header: self.slide_header.clone(),
+            slide_footer: self.slide_footer.clone(),
+            data: self.data.clone(),
+            vars: self.vars.clone(),
+            reproducible: self.reproducible,
+            git_metadata: !self.no_git_metadata,
+            profiles: self.profiles.clone(),
+            theme: self.theme_name(),
+            number_headings: self.number_headings,
+            title_slide: self.title_slide,
+            partials: self.partials.clone(),
+            strict_templates: self.strict_templates,
+            allow_env: self.allow_env.clone(),
+            fingerprints: self.fingerprints.borrow().clone(),
+            figures: !self.no_figures,
+            mermaid_cli: self.mermaid_cli,
+            math: self.math,
+            emoji: self.emoji,
+            keep_comments: self.keep_comments,
+            mark: self
+                .md_extensions
+                .contains(&markdown::MarkdownExtension::Mark),
+            abbreviations: self
+                .md_extensions
+                .contains(&markdown::MarkdownExtension::Abbreviations),
+            kbd: self.kbd,
+            task_checkboxes: self.task_checkboxes,
+            external_blank: !self.no_external_blank,
+            base_url: self
+                .base_url
+                .as_ref()
+                .filter(|base_url| !base_url.is_empty())
+                .map(|base_url| {
+                    let base_url = if base_url.ends_with('/') {
+                        base_url.clone()
+                    } else {
+                        format!("{}/", base_url)
+                    };
+                    match deck.dir.strip_prefix(&self.output_dir) {
+                        Ok(dir) if dir != Path::new("") => {
+                            format!("{}{}/", base_url, dir.to_string_lossy())
+                        }
+                        _ => base_url,
+                    }
+                }),
+            // Self-contained slideshows have their static files inlined from
+            // wherever they are.
+            root: if self.self_contained {
+                String::new()
+            } else {
+                deck.root.clone()
+            },
+            raw_html: if self.no_raw_html {
+                sanitize::RawHtml::Drop
+            } else if self.sanitize_html {
+                sanitize::RawHtml::Sanitize
+            } else {
+                sanitize::RawHtml::Keep
+            },
+            extensions: self.extensions(),
+            words_per_minute: self.words_per_minute,
+            max_words: self.max_words_per_slide,
+            max_code_lines: self.max_code_lines_per_slide,
+            max_images: self.max_images_per_slide,
+            code_line_numbers: self.code_line_numbers,
+            highlight: self.highlight,
+            highlight_theme: self.highlight_theme.clone(),
+            static_dirs: self.static_dirs.clone(),
+            lint: self.lint.is_some(),
+            check_links: self.check_links,
+            check_external_links: self.check_external_links,
+            filters: self.filters.clone(),
+            filter_timeout: std::time::Duration::from_secs(self.filter_timeout),
+            diagnostics: self.diagnostics.clone(),
+        }
+    }
+
+    fn render_markdown(&self, deck: &Deck) -> Result<markdown::Rendered, BuildErr> {
+        if deck.reads_stdin() {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(BuildErr::Stdin)?;
+            return Ok(markdown::render_source(
+                &input,
+                &deck.input,
+                self.template.as_deref(),
+                &self.render_options(deck),
+            )?);
+        }
+        Ok(markdown::render(
+            &deck.input,
+            self.template.as_deref(),
+            &self.render_options(deck),
+        )?)
+    }
+
+    fn writes_stdout(&self) -> bool {
+        self.output.as_deref() == Some(Path::new("-"))
+    }
+
+    /// Writes the rendered slideshow and copies the files it references into
+    /// the output directory.
+    fn write_markdown_file(&self, deck: &Deck) -> Result<markdown::Rendered, BuildErr> {
+        let res = self.render_markdown(deck)?;
+        if self.writes_stdout() {
+            let html = if self.self_contained {
+                Cow::Owned(self.embed(deck, &res.html))
+            } else {
+                Cow::Borrowed(res.html.as_str())
+            };
+            let html = self.minify(html);
+            io::stdout()
+                .write_all(html.as_bytes())
+                .map_err(BuildErr::Stdout)?;
+        } else {
+            make_output(&deck.dir).map_err(|e| BuildErr::OutputFile(deck.dir.clone(), e))?;
+            self.write_html(deck, deck.output_file(), &res.html)?;
+            self.write_handout(deck, &res)?;
+        }
+        if let Some(output) = &self.notes_output {
+            let output = self.deck_file(deck, output);
+            self.wrote(&output);
+            fs::write(&output, &res.notes).map_err(|e| BuildErr::OutputWrite(output, e))?;
+        }
+        if self.stats {
+            eprint!("{}", res.stats);
+        }
+        if let Some(output) = &self.stats_json {
+            // Serializing plain numbers can't fail.
+            let json = serde_json::to_string_pretty(&res.stats).unwrap();
+            let output = self.deck_file(deck, output);
+            self.wrote(&output);
+            fs::write(&output, json).map_err(|e| BuildErr::OutputWrite(output, e))?;
+        }
+        if !self.self_contained && !self.writes_stdout() {
+            for asset in &res.assets {
+                self.copy_asset(deck, asset)?;
+            }
+        }
+        for link in &res.broken_links {
+            event!(Level::ERROR, "{}", link);
+        }
+        for finding in &res.lint {
+            if self.lint_level() == Some(lint::LintLevel::Deny) {
+                event!(Level::ERROR, "{}", finding);
+            } else {
+                self.diagnostics.warn(finding);
+            }
+        }
+        Ok(res)
+    }
+
+    /// An output file like `--notes-output` for `deck`, which with several
+    /// decks has the deck's name added, like `notes.intro.md`.
+    fn deck_file(&self, deck: &Deck, path: &Path) -> PathBuf {
+        let single = self.output_name.as_deref().unwrap_or("index.html");
+        if deck.output_file() == self.output_dir.join(single) {
+            return path.to_owned();
+        }
+        let mut file_name = path.file_stem().unwrap_or_default().to_owned();
+        file_name.push(".");
+        file_name.push(&deck.name);
+        if let Some(extension) = path.extension() {
+            file_name.push(".");
+            file_name.push(extension);
+        }
+        path.with_file_name(file_name)
     }
 
-    fn write_markdown_file(&self) -> Result<(), BuildErr> {
-        let res = self.render_markdown_string()?;
-        let output = self.output_file();
+    /// Writes a rendered template to `output`, with the files it refers to
+    /// inlined if the output is self-contained.
+    fn write_html(&self, deck: &Deck, output: PathBuf, html: &str) -> Result<(), BuildErr> {
+        let html = if self.self_contained {
+            Cow::Owned(self.embed(deck, html))
+        } else {
+            Cow::Borrowed(html)
+        };
+        let html = self.minify(html);
+        self.wrote(&output);
+        self.build_report
+            .borrow_mut()
+            .output(&output, html.len() as u64);
         let mut file =
             File::create(&output).map_err(|e| BuildErr::OutputFile(output.clone(), e))?;
-        write!(&mut file, "{}", res).map_err(|e| BuildErr::OutputWrite(output, e))?;
+        write!(&mut file, "{}", html).map_err(|e| BuildErr::OutputWrite(output, e))?;
         Ok(())
     }
 
-    #[instrument(skip(self))]
-    fn watch(&self) -> Result<(), WatchErr> {
+    /// `html`, minified with `--minify`. If it can't be, it's left as it is.
+    fn minify<'h>(&self, html: Cow<'h, str>) -> Cow<'h, str> {
+        if !self.minify {
+            return html;
+        }
+        match minify::html(&html) {
+            Ok(minified) => Cow::Owned(minified),
+            Err(err) => {
+                self.diagnostics.warn(format_args!(
+                    "Couldn't minify the slideshow, so it's written as it is: {}",
+                    err
+                ));
+                html
+            }
+        }
+    }
+
+    /// Renders the slides with the template again, like after it changes.
+    fn write_index(&self, deck: &Deck, rendered: &markdown::Rendered) -> Result<(), BuildErr> {
+        let html =
+            rendered.render_template(self.template.as_deref(), &self.render_options(deck))?;
+        self.write_html(deck, deck.output_file(), &html)
+    }
+
+    /// Renders the slides with the handout template, if there is one.
+    fn write_handout(&self, deck: &Deck, rendered: &markdown::Rendered) -> Result<(), BuildErr> {
+        if let Some(template) = &self.handout_template {
+            let html = rendered.render_template(Some(template), &self.render_options(deck))?;
+            self.write_html(deck, deck.handout_file(), &html)?;
+        }
+        Ok(())
+    }
+
+    /// Inlines the files `html` refers to, which are relative to the input
+    /// file, in the static files directory, or generated in the output
+    /// directory, like `theme.css`.
+    fn embed(&self, deck: &Deck, html: &str) -> String {
+        let embedder = embed::Embedder {
+            roots: Some(deck.input_dir())
+                .into_iter()
+                .chain(self.static_dirs.iter().rev().map(PathBuf::as_path))
+                .chain(Some(self.output_dir.as_path()))
+                .collect(),
+            base_url: self.base_url.as_deref(),
+            warn_size: self.inline_warning_kib * 1024,
+            diagnostics: &self.diagnostics,
+        };
+        let (html, unresolved) = embedder.embed(html);
+        if !unresolved.is_empty() {
+            self.diagnostics.warn(format_args!(
+                "Couldn't find {} file(s) to inline: {}",
+                unresolved.len(),
+                unresolved.join(", ")
+            ));
+        }
+        html
+    }
+
+    /// A key given to `--var` more than once, if any.
+    fn duplicate_var(&self) -> Option<&str> {
+        self.vars
+            .iter()
+            .enumerate()
+            .find(|(i, var)| self.vars[..*i].iter().any(|other| other.key == var.key))
+            .map(|(_, var)| var.key.as_str())
+    }
+
+    fn lint_level(&self) -> Option<lint::LintLevel> {
+        self.lint
+            .map(|level| level.unwrap_or(lint::LintLevel::Warn))
+    }
+
+    /// Looks for the template `name` in the input file's directory, the
+    /// current directory, and then the config directory.
+    fn find_template(&self, name: &Path) -> Option<PathBuf> {
+        let input_dir = self.inputs[0].parent().unwrap_or(&self.inputs[0]);
+        let dirs = [
+            Some(input_dir.to_owned()),
+            env::current_dir().ok(),
+            dirs::config_dir(),
+        ];
+        dirs.iter()
+            .flatten()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .and_then(|path| path.canonicalize().ok())
+    }
+
+    /// Copies a file referenced by the Markdown, relative to the input file's
+    /// directory, into the same place in the output directory.
+    #[instrument(skip(self), err)]
+    fn copy_asset(&self, deck: &Deck, asset: &Path) -> Result<(), BuildErr> {
+        let from = deck.input_dir().join(asset);
+        let to = deck.dir.join(asset);
+        if let Some(dir) = to.parent() {
+            make_output(dir).map_err(|e| BuildErr::OutputFile(dir.to_owned(), e))?;
+        }
+        self.wrote(&to);
+        event!(Level::INFO, from = ?from, to = ?to);
+        let bytes = fs::copy(&from, &to).map_err(|err| BuildErr::CopyAsset {
+            from: from.clone(),
+            to: to.clone(),
+            err,
+        })?;
+        self.build_report.borrow_mut().copied(&from, &to, bytes);
+        Ok(())
+    }
+
+    /// Starts watching any of `rendered`'s assets and includes that aren't
+    /// in `watched` yet.
+    fn watch_files(
+        &self,
+        watcher: &mut impl notify::Watcher,
+        deck: &Deck,
+        watched: &mut WatchedFiles,
+        rendered: &markdown::Rendered,
+    ) {
+        for asset in &rendered.assets {
+            if watched.assets.contains(asset) {
+                continue;
+            }
+            let path = deck.input_dir().join(asset);
+            if let Err(err) = watcher.watch(&path, notify::RecursiveMode::NonRecursive) {
+                event!(Level::WARN, ?path, %err, "Failed to watch referenced file");
+            }
+            watched.assets.push(asset.clone());
+        }
+        for path in &rendered.includes {
+            if watched.includes.contains(path) {
+                continue;
+            }
+            if let Err(err) = watcher.watch(path, notify::RecursiveMode::NonRecursive) {
+                event!(Level::WARN, ?path, %err, "Failed to watch included file");
+            }
+            watched.includes.push(path.clone());
+        }
+    }
+
+    /// Re-renders a deck's Markdown and watches any newly referenced files.
+    fn rewrite_markdown_file(
+        &self,
+        watcher: &mut impl notify::Watcher,
+        deck: &Deck,
+        watched: &mut WatchedFiles,
+    ) -> Result<markdown::Rendered, BuildErr> {
+        let rendered = self.write_markdown_file(deck)?;
+        self.watch_files(watcher, deck, watched, &rendered);
+        Ok(rendered)
+    }
+
+    /// Builds the slideshows and rebuilds them as files change, opening
+    /// `open` once they're first built. Build errors are reported and
+    /// watching goes on, so only the watcher failing returns.
+    #[instrument(skip(self, decks))]
+    fn watch(
+        &self,
+        watch: &WatchOpt,
+        mut decks: Vec<Deck>,
+        open: Option<OsString>,
+    ) -> Result<(), WatchErr> {
         use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
         use std::time::Duration;
 
-        self.render()?;
+        // Until the slideshows have been built once, they're built from
+        // scratch on every change.
+        let mut built = false;
+        let mut rendered = match self.build(&decks) {
+            Ok(rendered) => {
+                built = true;
+                rendered
+            }
+            Err(err) => {
+                self.build_failed(&err, &decks, !watch.no_error_overlay);
+                Vec::new()
+            }
+        };
+        if let Some(open) = open {
+            open::open(open);
+        }
 
         let (tx, rx) = std::sync::mpsc::channel();
-        let mut watcher = watcher(tx, Duration::from_millis(self.debounce_ms)).unwrap();
-
-        watcher
-            .watch(&self.static_dir, RecursiveMode::Recursive)
-            .unwrap();
-        watcher
-            .watch(
-                &self.input.parent().unwrap_or(&self.input),
-                RecursiveMode::NonRecursive,
-            )
-            .unwrap();
-        watcher
-            .watch(
-                &self.template.parent().unwrap_or(&self.template),
-                RecursiveMode::NonRecursive,
-            )
-            .unwrap();
+        let mut watcher = watcher(tx, Duration::from_millis(watch.debounce_ms))
+            .map_err(|err| WatchErr::Notify(err, None))?;
+        let notify_err = |path: &Path| {
+            let path = path.to_owned();
+            move |err| WatchErr::Notify(err, Some(path))
+        };
+
+        for static_dir in &self.static_dirs {
+            watcher
+                .watch(static_dir, RecursiveMode::Recursive)
+                .map_err(notify_err(static_dir))?;
+        }
+        for deck in &decks {
+            let input_dir = deck.input_dir();
+            watcher
+                .watch(input_dir, RecursiveMode::NonRecursive)
+                .map_err(notify_err(input_dir))?;
+        }
+        // New files matching the patterns are picked up when they're created.
+        let patterns = self
+            .input_paths
+            .iter()
+            .filter_map(|path| path.to_str())
+            .filter(|path| glob::is_pattern(Path::new(path)))
+            .chain(self.input_glob.iter().map(String::as_str));
+        for pattern in patterns {
+            let base = glob::base(pattern);
+            if let Err(err) = watcher.watch(&base, RecursiveMode::Recursive) {
+                event!(Level::WARN, ?base, %err, "Failed to watch input directory");
+            }
+        }
+        for template in self.template.iter().chain(&self.handout_template) {
+            let dir = template.parent().unwrap_or(template);
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(notify_err(dir))?;
+        }
+        if let Some(partials) = &self.partials {
+            watcher
+                .watch(partials, RecursiveMode::NonRecursive)
+                .map_err(notify_err(partials))?;
+        }
+        if let Some(data) = &self.data {
+            watcher
+                .watch(data, RecursiveMode::NonRecursive)
+                .map_err(notify_err(data))?;
+        }
+        if let Some(theme_file) = &self.theme_file {
+            watcher
+                .watch(theme_file, RecursiveMode::NonRecursive)
+                .map_err(notify_err(theme_file))?;
+        }
+
+        let mut watched = decks
+            .iter()
+            .map(|_| WatchedFiles::default())
+            .collect::<Vec<_>>();
+        for ((deck, watched), rendered) in decks.iter().zip(&mut watched).zip(&rendered) {
+            self.watch_files(&mut watcher, deck, watched, rendered);
+        }
 
         event!(Level::INFO, "initialized filesystem watcher");
 
@@ -233,42 +2483,303 @@ impl Opt {
             let span = span!(Level::INFO, "filesystem event", event = ?event);
             let _guard = span.enter();
             event!(Level::INFO, ?event);
-            match event {
-                DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => {
-                    if path.starts_with(&self.static_dir) {
-                        self.copy_single_static(path)?;
-                    } else if &path == &self.input || &path == &self.template {
-                        self.write_markdown_file()?;
-                    }
+            if let DebouncedEvent::Error(err, path) = event {
+                if let Some(path) = &path {
+                    event!(Level::ERROR, ?path);
                 }
-                DebouncedEvent::Chmod(path) => {
-                    if path.starts_with(&self.static_dir) {
-                        self.copy_single_static(path)?;
-                    } else {
-                        self.write_markdown_file()?;
+                return Err(WatchErr::Notify(err, path));
+            }
+            let rescan = matches!(
+                event,
+                DebouncedEvent::Create(_)
+                    | DebouncedEvent::Remove(_)
+                    | DebouncedEvent::Rename(_, _)
+                    | DebouncedEvent::Rescan
+            );
+            let start = Instant::now();
+            self.build_report.borrow_mut().phases.clear();
+            if rescan && self.has_patterns() {
+                match self.find_inputs() {
+                    Ok(inputs) if inputs.iter().ne(decks.iter().map(|deck| &deck.input)) => {
+                        let new_decks = self.decks(&inputs);
+                        if let Some(name) = duplicate_name(&new_decks) {
+                            event!(Level::ERROR, "Several input files are named {:?}", name);
+                            continue;
+                        }
+                        event!(Level::INFO, ?inputs, "Input files changed");
+                        decks = new_decks;
+                        for deck in &decks {
+                            if let Err(err) =
+                                watcher.watch(deck.input_dir(), RecursiveMode::NonRecursive)
+                            {
+                                event!(Level::WARN, %err, "Failed to watch input directory");
+                            }
+                        }
+                        watched = decks.iter().map(|_| WatchedFiles::default()).collect();
+                        // They're all rewritten, under their new names.
+                        self.build_report.borrow_mut().outputs.clear();
+                        rendered.clear();
                     }
+                    Ok(_) => {}
+                    Err(err) => event!(Level::WARN, %err),
                 }
-                DebouncedEvent::Remove(path) => {
-                    event!(Level::WARN, "remove (unimplemented)");
-                }
-                DebouncedEvent::Rename(from, to) => {
-                    event!(Level::WARN, "rename (unimplemented)");
+            }
+            let updated = if built {
+                self.update(&event, &mut watcher, &decks, &mut watched, &mut rendered)
+            } else {
+                self.build(&decks).map(|new| {
+                    built = true;
+                    rendered = new;
+                    for ((deck, watched), rendered) in decks.iter().zip(&mut watched).zip(&rendered)
+                    {
+                        self.watch_files(&mut watcher, deck, watched, rendered);
+                    }
+                    true
+                })
+            };
+            match updated {
+                Ok(true) => {
+                    self.clear_errors();
+                    // Printed even with `--quiet`, to show it's still
+                    // watching.
+                    println!("Updated the slideshow in {}ms", start.elapsed().as_millis());
                 }
-                DebouncedEvent::Rescan => {
-                    event!(Level::INFO, "rescanning watched files");
+                Ok(false) => {}
+                Err(err) => self.build_failed(&err, &decks, !watch.no_error_overlay),
+            }
+        }
+    }
+
+    /// Builds all the slideshows from scratch, for watching.
+    fn build(&self, decks: &[Deck]) -> Result<Vec<markdown::Rendered>, BuildErr> {
+        self.run_pre_build(decks)?;
+        let rendered = self.render(decks)?;
+        self.finish_update(decks, &rendered)?;
+        Ok(rendered)
+    }
+
+    /// Writes the report and runs the `--post-build` commands once the
+    /// slideshows are built or updated, for watching.
+    fn finish_update(
+        &self,
+        decks: &[Deck],
+        rendered: &[markdown::Rendered],
+    ) -> Result<(), BuildErr> {
+        self.write_report(decks, rendered)?;
+        if let Err(err) = self.check_warnings() {
+            event!(Level::ERROR, "{}", err);
+        }
+        if let Err(err) = self.run_post_build(decks) {
+            event!(Level::ERROR, "{}", err);
+        }
+        Ok(())
+    }
+
+    /// Updates the slideshows after the filesystem `event`, rebuilding them
+    /// if need be. Returns whether anything was written.
+    fn update(
+        &self,
+        event: &notify::DebouncedEvent,
+        watcher: &mut impl notify::Watcher,
+        decks: &[Deck],
+        watched: &mut [WatchedFiles],
+        rendered: &mut Vec<markdown::Rendered>,
+    ) -> Result<bool, BuildErr> {
+        let start = Instant::now();
+        // Slideshows which haven't been rendered, because their input files
+        // are new or failed to build, are rebuilt whatever changed.
+        let mut rebuild: Vec<_> = (0..decks.len()).map(|i| i >= rendered.len()).collect();
+        let mut updated = self.write_changed(event, decks, rendered, watched, &mut rebuild)?;
+        if rebuild.contains(&true) {
+            self.rebuild(watcher, decks, watched, rendered, &rebuild)?;
+            updated = true;
+        }
+        if updated {
+            self.build_report
+                .borrow_mut()
+                .phase("update", start.elapsed());
+            self.finish_update(decks, rendered)?;
+        }
+        Ok(updated)
+    }
+
+    /// Writes what changed with the filesystem `event` that doesn't need the
+    /// slideshows rebuilding, like static files, and marks the slideshows
+    /// which do to `rebuild`. Returns whether anything was written.
+    fn write_changed(
+        &self,
+        event: &notify::DebouncedEvent,
+        decks: &[Deck],
+        rendered: &[markdown::Rendered],
+        watched: &[WatchedFiles],
+        rebuild: &mut [bool],
+    ) -> Result<bool, BuildErr> {
+        use notify::DebouncedEvent;
+
+        let mut updated = false;
+        match event {
+            DebouncedEvent::Create(path) | DebouncedEvent::Write(path) => {
+                if self.theme_file.as_ref() == Some(path) {
+                    self.write_theme_css()?;
+                    updated = true;
                 }
-                DebouncedEvent::Error(err, path) => {
-                    if let Some(path) = &path {
-                        event!(Level::ERROR, ?path);
+                if self.self_contained {
+                    // Everything is inlined into the slideshows, so any
+                    // change means rebuilding them.
+                    rebuild.fill(true);
+                } else if self.static_root(path).is_some() {
+                    self.update_static(path.clone(), decks, rendered)?;
+                    updated = true;
+                } else if self.template.as_ref() == Some(path) {
+                    // The slides haven't changed, so they're only laid out
+                    // again.
+                    for (deck, rendered) in decks.iter().zip(rendered) {
+                        self.write_index(deck, rendered)?;
+                    }
+                    updated = true;
+                } else if self.handout_template.as_ref() == Some(path) {
+                    for (deck, rendered) in decks.iter().zip(rendered) {
+                        self.write_handout(deck, rendered)?;
+                    }
+                    updated = true;
+                } else if self
+                    .partials
+                    .as_ref()
+                    .is_some_and(|partials| path.starts_with(partials))
+                    || self.data.as_ref() == Some(path)
+                {
+                    rebuild.fill(true);
+                } else {
+                    for (i, deck) in decks.iter().enumerate() {
+                        if *path == deck.input || watched[i].includes.contains(path) {
+                            rebuild[i] = true;
+                        } else if let Some(asset) = watched[i]
+                            .assets
+                            .iter()
+                            .find(|asset| deck.input_dir().join(asset) == *path)
+                        {
+                            self.copy_asset(deck, asset)?;
+                            updated = true;
+                        }
                     }
-                    return Err(WatchErr::Notify(err, path));
                 }
-                _ => {
-                    event!(Level::DEBUG, "unhandled event");
+            }
+            DebouncedEvent::Chmod(path) => {
+                if self.static_root(path).is_some() && !self.self_contained {
+                    self.update_static(path.clone(), decks, rendered)?;
+                    updated = true;
+                } else {
+                    rebuild.fill(true);
                 }
             }
+            DebouncedEvent::Remove(_path) => {
+                event!(Level::WARN, "remove (unimplemented)");
+            }
+            DebouncedEvent::Rename(_from, _to) => {
+                event!(Level::WARN, "rename (unimplemented)");
+            }
+            DebouncedEvent::Rescan => {
+                event!(Level::INFO, "rescanning watched files");
+            }
+            _ => {
+                event!(Level::DEBUG, "unhandled event");
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Re-renders the slideshows marked to `rebuild`, replacing what was
+    /// rendered of them before.
+    fn rebuild(
+        &self,
+        watcher: &mut impl notify::Watcher,
+        decks: &[Deck],
+        watched: &mut [WatchedFiles],
+        rendered: &mut Vec<markdown::Rendered>,
+        rebuild: &[bool],
+    ) -> Result<(), BuildErr> {
+        self.run_pre_build(decks)?;
+        let render_start = Instant::now();
+        for (i, deck) in decks.iter().enumerate().filter(|(i, _)| rebuild[*i]) {
+            let res = self.rewrite_markdown_file(watcher, deck, &mut watched[i])?;
+            match rendered.get_mut(i) {
+                Some(rendered) => *rendered = res,
+                None => rendered.push(res),
+            }
         }
+        self.build_report
+            .borrow_mut()
+            .phase("render", render_start.elapsed());
+        // Titles might have changed.
+        self.write_deck_list(decks, rendered)
     }
+
+    /// Reports an error building the slideshows while watching, which goes
+    /// on until the next change, showing it over the slideshows if
+    /// `overlay` is set.
+    fn build_failed(&self, err: &BuildErr, decks: &[Deck], overlay: bool) {
+        print_error(err);
+        eprintln!("Still watching for changes");
+        if overlay {
+            self.show_error(err, decks);
+        }
+    }
+
+    /// Shows `err` over each slideshow, or in place of it if it hasn't been
+    /// built yet.
+    fn show_error(&self, err: &BuildErr, decks: &[Deck]) {
+        let location = std::iter::successors(Some(err as &(dyn error::Error + 'static)), |err| {
+            err.source()
+        })
+        .find_map(|err| err.downcast_ref::<markdown::RenderError>()?.location())
+        .map(|location| location.to_string());
+        let lines = error_lines(err);
+        let lines: Vec<_> = lines.iter().map(String::as_str).collect();
+        let html = overlay::overlay(&lines, location.as_deref());
+        let mut overlaid = self.overlaid.borrow_mut();
+        for deck in decks {
+            let path = deck.output_file();
+            let shown = overlaid.iter().find(|(shown, _)| *shown == path);
+            let alone = shown.map_or_else(|| !path.is_file(), |(_, alone)| *alone);
+            let page = if alone {
+                make_output(&deck.dir).map(|()| overlay::page(&html))
+            } else {
+                fs::read_to_string(&path).map(|page| overlay::inject(&page, &html))
+            };
+            match page.and_then(|page| fs::write(&path, page)) {
+                Ok(()) if shown.is_none() => overlaid.push((path, alone)),
+                Ok(()) => {}
+                Err(err) => event!(Level::WARN, ?path, %err, "Failed to show the error"),
+            }
+        }
+    }
+
+    /// Takes away the errors shown over the slideshows, once they build.
+    fn clear_errors(&self) {
+        for (path, alone) in self.overlaid.take() {
+            let page = match fs::read_to_string(&path) {
+                Ok(page) => page,
+                Err(_) => continue,
+            };
+            // Slideshows rewritten since don't have the error.
+            let result = match overlay::remove(&page) {
+                None => Ok(()),
+                Some(_) if alone => fs::remove_file(&path),
+                Some(page) => fs::write(&path, page),
+            };
+            if let Err(err) = result {
+                event!(Level::WARN, ?path, %err, "Failed to take the error away");
+            }
+        }
+    }
+}
+
+/// The files a deck refers to which are being watched.
+#[derive(Default)]
+struct WatchedFiles {
+    /// Assets, relative to the deck's input file.
+    assets: Vec<PathBuf>,
+    includes: Vec<PathBuf>,
 }
 
 #[instrument(err)]
@@ -1,135 +1,3187 @@
-use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, Read};
+use std::iter;
+use std::mem;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::string::FromUtf8Error;
+use std::time::Duration;
+use std::vec;
 
-use handlebars::{Handlebars, TemplateRenderError};
-use pulldown_cmark::{html, Event, Options, Parser};
+use chrono::{SecondsFormat, Utc};
+use handlebars::{
+    html_escape, Handlebars, RenderError as HandlebarsRenderError, TemplateError,
+    TemplateRenderError,
+};
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use qrcode::types::QrError;
+use regex::Regex;
 use serde::Serialize;
 use thiserror::Error;
 use tracing::{event, instrument, span, Level};
 
+use crate::abbr::{self, Abbreviations};
+use crate::assets;
+use crate::attributes::Attributes;
+use crate::code::{self, CodeInfo};
+use crate::container::{self, Container};
+use crate::diagnostics::Diagnostics;
+use crate::emoji;
+use crate::filter::{self, FilterError};
+use crate::fingerprint::Manifest;
+use crate::front_matter::{self, FrontMatter};
+use crate::git;
+use crate::helpers;
+use crate::highlight::{HighlightError, HighlightStyle, Highlighter};
+use crate::image::{self, Video};
+use crate::include::{IncludeError, Includes};
+use crate::kbd;
+use crate::links::{self, BrokenLink, LinkChecker};
+use crate::lint::{self, Finding};
+use crate::math;
+use crate::mermaid::{self, MermaidError};
+use crate::profile;
+use crate::qr;
+use crate::sanitize::{self, RawHtml};
+use crate::slug::{slugify, Slugs};
+use crate::stats::{SlideCounts, Stats};
+use crate::vars::{self, DataError, Var};
+
 #[derive(Error, Debug)]
 pub enum RenderError {
     #[error("Error reading {0}: {1}")]
-    Read(PathBuf, io::Error),
+    Read(PathBuf, #[source] io::Error),
 
     #[error("Error rendering template: {0}")]
-    Render(#[from] TemplateRenderError),
+    Render(#[source] Box<TemplateRenderError>),
+
+    #[error(
+        "Template uses undefined variable {name:?}{}",
+        .line.map(|line| format!(" on line {}", line)).unwrap_or_default()
+    )]
+    UndefinedVariable {
+        name: String,
+        template: Option<String>,
+        line: Option<usize>,
+    },
+
+    #[error(
+        "The template doesn't include the slides; it needs `{{{{{{content}}}}}}`, or to loop \
+         over `slides` with `{{{{{{html}}}}}}`"
+    )]
+    NoContent,
+
+    #[error("Slide {slide} uses the layout {name:?}, but there's no `layout-{name}` partial")]
+    UnknownLayout { name: String, slide: usize },
+
+    #[error("Error in partial {0}: {1}")]
+    Partial(PathBuf, #[source] Box<TemplateError>),
 
     #[error("Template produced invalid UTF-8: {0}")]
     Utf8(#[from] FromUtf8Error),
+
+    #[error("{0}")]
+    Highlight(#[from] HighlightError),
+
+    #[error("{0}")]
+    Include(#[from] IncludeError),
+
+    #[error("{0}")]
+    Data(#[from] DataError),
+
+    #[error("Image {path} on slide {slide} doesn't exist")]
+    MissingImage { path: PathBuf, slide: usize },
+
+    #[error("Error drawing Mermaid diagram on slide {slide}: {err}")]
+    Mermaid {
+        slide: u32,
+        #[source]
+        err: MermaidError,
+    },
+
+    #[error("Error drawing QR code for {url} on slide {slide}: {err}")]
+    QrCode {
+        url: String,
+        slide: u32,
+        #[source]
+        err: QrError,
+    },
+
+    #[error("None of the deck's {0} slides are selected")]
+    NoSlidesSelected(usize),
+
+    #[error("{0}")]
+    Filter(#[from] FilterError),
+}
+
+impl From<TemplateRenderError> for RenderError {
+    fn from(e: TemplateRenderError) -> Self {
+        if let TemplateRenderError::RenderError(err) = &e {
+            // Strict mode's errors only say which variable is missing in
+            // their descriptions, like `Variable "author" not found in strict
+            // mode.`
+            if let Some(name) = err
+                .desc
+                .strip_prefix("Variable \"")
+                .and_then(|rest| rest.strip_suffix("\" not found in strict mode."))
+            {
+                return RenderError::UndefinedVariable {
+                    name: name.to_owned(),
+                    template: err.template_name.clone(),
+                    line: err.line_no,
+                };
+            }
+        }
+        RenderError::Render(Box::new(e))
+    }
+}
+
+impl RenderError {
+    /// The file the error is in, and the line if it's known.
+    pub fn location(&self) -> Option<Location> {
+        let location = |file: &str, line| {
+            Some(Location {
+                file: file.to_owned(),
+                line,
+            })
+        };
+        match self {
+            RenderError::Read(path, _)
+            | RenderError::Include(IncludeError::Read(path, _))
+            | RenderError::Include(IncludeError::TooDeep(path)) => {
+                location(&path.display().to_string(), None)
+            }
+            RenderError::Partial(path, err) => location(&path.display().to_string(), err.line_no),
+            RenderError::UndefinedVariable {
+                template: Some(template),
+                line,
+                ..
+            } => location(template, *line),
+            RenderError::Render(err) => match &**err {
+                TemplateRenderError::TemplateError(TemplateError {
+                    template_name: Some(template),
+                    line_no,
+                    ..
+                }) => location(template, *line_no),
+                TemplateRenderError::RenderError(HandlebarsRenderError {
+                    template_name: Some(template),
+                    line_no,
+                    ..
+                }) => location(template, *line_no),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Where an error is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}, line {}", self.file, line),
+            None => write!(f, "{}", self.file),
+        }
+    }
+}
+
+/// A heading level, written `h1` through `h6`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadingLevel(pub u32);
+
+impl FromStr for HeadingLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "h1" => Ok(HeadingLevel(1)),
+            "h2" => Ok(HeadingLevel(2)),
+            "h3" => Ok(HeadingLevel(3)),
+            "h4" => Ok(HeadingLevel(4)),
+            "h5" => Ok(HeadingLevel(5)),
+            "h6" => Ok(HeadingLevel(6)),
+            _ => Err(format!("Expected a heading level h1-h6, not {:?}", s)),
+        }
+    }
+}
+
+/// Something in the Markdown that separates slides.
+#[derive(Debug, Clone)]
+pub enum SlideDelimiter {
+    /// A thematic break, like `---`.
+    Rule,
+    /// An HTML comment with this body, like `slide` for `<!-- slide -->`.
+    Comment(String),
+    /// A paragraph whose text matches the regex.
+    Pattern(Regex),
+}
+
+/// A comma-separated list of slide delimiters, like `rule,comment`. A regex,
+/// written `/like this/`, or a comment can't be part of a list, since it
+/// might contain commas itself.
+#[derive(Debug, Clone)]
+pub struct SlideDelimiters(pub Vec<SlideDelimiter>);
+
+impl FromStr for SlideDelimiters {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(pattern) = s.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+            return Ok(SlideDelimiters(vec![SlideDelimiter::Pattern(regex)]));
+        }
+        if let Some(body) = comment_body(s) {
+            return Ok(SlideDelimiters(vec![SlideDelimiter::Comment(
+                body.to_owned(),
+            )]));
+        }
+        s.split(',')
+            .map(|delimiter| match delimiter.trim() {
+                "rule" => Ok(SlideDelimiter::Rule),
+                "comment" => Ok(SlideDelimiter::Comment("slide".to_owned())),
+                _ => Err(format!(
+                    "Expected a slide delimiter (`rule`, `comment`, an HTML comment like \
+                     `<!-- next -->`, or a /regex/), not {:?}",
+                    delimiter
+                )),
+            })
+            .collect::<Result<_, _>>()
+            .map(SlideDelimiters)
+    }
+}
+
+/// A comma-separated list of slide numbers and ranges, like `1-5,12,20-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlideRanges(Vec<(usize, Option<usize>)>);
+
+impl SlideRanges {
+    pub fn contains(&self, number: usize) -> bool {
+        self.0
+            .iter()
+            .any(|&(start, end)| start <= number && end.is_none_or(|end| number <= end))
+    }
+}
+
+impl FromStr for SlideRanges {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| match n.trim().parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Expected a slide number, not {:?}", n)),
+        };
+        s.split(',')
+            .map(|range| match range.split_once('-') {
+                Some((start, end)) => {
+                    let start = if start.trim().is_empty() {
+                        1
+                    } else {
+                        number(start)?
+                    };
+                    let end = if end.trim().is_empty() {
+                        None
+                    } else {
+                        Some(number(end)?)
+                    };
+                    match end {
+                        Some(end) if end < start => {
+                            Err(format!("Slide range {:?} ends before it starts", range))
+                        }
+                        _ => Ok((start, end)),
+                    }
+                }
+                None => number(range).map(|n| (n, Some(n))),
+            })
+            .collect::<Result<_, _>>()
+            .map(SlideRanges)
+    }
+}
+
+/// The template used when none is given, with minimal slide CSS and keyboard
+/// navigation.
+pub const DEFAULT_TEMPLATE: &str = include_str!("default-template.html");
+
+/// pulldown-cmark extensions, by the names they're given on the command line.
+const EXTENSIONS: &[(&str, Options)] = &[
+    ("footnotes", Options::ENABLE_FOOTNOTES),
+    ("tables", Options::ENABLE_TABLES),
+    ("strikethrough", Options::ENABLE_STRIKETHROUGH),
+    ("tasklists", Options::ENABLE_TASKLISTS),
+    ("smart-punctuation", Options::ENABLE_SMART_PUNCTUATION),
+    ("heading-attributes", Options::ENABLE_HEADING_ATTRIBUTES),
+];
+
+/// The names of all the extensions, including ours, for completing
+/// `--md-extension`.
+pub const EXTENSION_NAMES: &[&str] = &[
+    "footnotes",
+    "tables",
+    "strikethrough",
+    "tasklists",
+    "smart-punctuation",
+    "heading-attributes",
+    "mark",
+    "abbreviations",
+];
+
+/// The extensions enabled unless `--no-default-extensions` is given.
+pub const DEFAULT_EXTENSIONS: Options = Options::ENABLE_FOOTNOTES.union(Options::ENABLE_TABLES);
+
+/// A Markdown extension, written like `smart-punctuation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownExtension {
+    /// An extension pulldown-cmark parses.
+    Parser(Options),
+    /// `==marked==` text, which we handle ourselves.
+    Mark,
+    /// `*[abbr]: title` abbreviation definitions, which we handle ourselves.
+    Abbreviations,
+}
+
+impl FromStr for MarkdownExtension {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("mark") {
+            return Ok(MarkdownExtension::Mark);
+        }
+        if s.eq_ignore_ascii_case("abbreviations") {
+            return Ok(MarkdownExtension::Abbreviations);
+        }
+        EXTENSIONS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, options)| MarkdownExtension::Parser(options))
+            .ok_or_else(|| {
+                format!(
+                    "Expected a Markdown extension ({}, mark, abbreviations), not {:?}",
+                    extension_names(Options::all()).join(", "),
+                    s
+                )
+            })
+    }
+}
+
+/// The names of the extensions in `options`.
+fn extension_names(options: Options) -> Vec<&'static str> {
+    EXTENSIONS
+        .iter()
+        .filter(|(_, extension)| options.contains(*extension))
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+/// Options controlling how the Markdown is split into slides.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// What separates slides.
+    pub slide_delimiters: Vec<SlideDelimiter>,
+
+    /// A line starting a new vertical slide in the current stack, like `----`.
+    pub vertical_delimiter: String,
+
+    /// Start a new slide at each heading of this level or higher, in addition
+    /// to at the delimiters.
+    pub slide_on: Option<HeadingLevel>,
+
+    /// Reveal list items one at a time on every slide, rather than only on
+    /// slides containing a `<!-- fragments -->` directive.
+    pub fragments: bool,
+
+    /// Reveal the items of nested lists one at a time too, rather than only
+    /// top-level items.
+    pub nested_fragments: bool,
+
+    /// Emit slides with no content rather than dropping them.
+    pub keep_empty_slides: bool,
+
+    /// Emit draft slides, with a `draft` class, rather than dropping them.
+    pub include_drafts: bool,
+
+    /// Only emit these slides, by their numbers in the whole deck.
+    pub slides: Option<SlideRanges>,
+
+    /// Markdown for a header and footer on every slide, overriding the front
+    /// matter's `slide_header` and `slide_footer`.
+    pub slide_header: Option<String>,
+    pub slide_footer: Option<String>,
+
+    /// A TOML, JSON, or YAML file of variables to give the template.
+    pub data: Option<PathBuf>,
+
+    /// Variables to give the template, overriding the data file's.
+    pub vars: Vec<Var>,
+
+    /// Leave the build time out of the template context, so that rebuilding
+    /// unchanged input gives the same output.
+    pub reproducible: bool,
+
+    /// Give the template the git commit the input file is at, if it's in a
+    /// repository.
+    pub git_metadata: bool,
+
+    /// The build profiles whose `:::only{profile}` blocks are kept.
+    pub profiles: Vec<String>,
+
+    /// The name of the theme in `theme.css`, for the template.
+    pub theme: String,
+
+    /// Number headings like "2.3 Borrow Checker", unless the front matter
+    /// has `number_headings: false`.
+    pub number_headings: bool,
+
+    /// Start with a slide showing the front matter's title, author, and
+    /// date. The front matter can also ask for one with `title_slide: true`.
+    pub title_slide: bool,
+
+    /// A directory of `name.hbs` or `name.html` Handlebars partials to
+    /// register, like `title-slide.hbs` to replace the title slide's markup.
+    pub partials: Option<PathBuf>,
+
+    /// Fail on template variables that aren't defined, rather than rendering
+    /// them as nothing.
+    pub strict_templates: bool,
+
+    /// The environment variables the template may read, with `*` at the end
+    /// of a name matching any suffix; all of them if empty, and none if
+    /// `None`.
+    pub allow_env: Option<Vec<String>>,
+
+    /// Fingerprinted static files' names, by their original names, for the
+    /// `asset` helper.
+    pub fingerprints: Manifest,
+
+    /// Render images with titles in paragraphs of their own as `<figure>`s,
+    /// captioned with their titles.
+    pub figures: bool,
+
+    /// Draw Mermaid diagrams at build time with `mmdc`.
+    pub mermaid_cli: bool,
+
+    /// Render `$...$` and `$$...$$` math with KaTeX.
+    pub math: bool,
+
+    /// Replace shortcodes like `:rocket:` with emoji.
+    pub emoji: bool,
+
+    /// Render keyboard shortcuts like `[[Ctrl+C]]` as `<kbd>`s.
+    pub kbd: bool,
+
+    /// Render task list items with disabled checkboxes, rather than with
+    /// `task done` or `task todo` classes.
+    pub task_checkboxes: bool,
+
+    /// Keep HTML comments which aren't directives in the output, rather than
+    /// dropping them.
+    pub keep_comments: bool,
+
+    /// What to do with raw HTML in the Markdown, including speaker notes.
+    pub raw_html: RawHtml,
+
+    /// pulldown-cmark extensions to parse the Markdown with.
+    pub extensions: Options,
+
+    /// Render `==marked==` text as `<mark>`s.
+    pub mark: bool,
+
+    /// Render abbreviations defined like `*[HTML]: HyperText Markup Language`
+    /// as `<abbr>`s.
+    pub abbreviations: bool,
+
+    /// Open `http(s)://` links to other sites in new tabs.
+    pub external_blank: bool,
+
+    /// The URL the slideshow is published at, ending with a slash. Relative
+    /// links and images are prefixed with it, and links under it aren't to
+    /// other sites.
+    pub base_url: Option<String>,
+
+    /// The path from the slideshow to the output directory, with the static
+    /// files: `../` for one of several decks in their own directories, or
+    /// else nothing.
+    pub root: String,
+
+    /// The speaking rate to estimate the slideshow's length with.
+    pub words_per_minute: u32,
+
+    /// Flag slides with more words than this as likely to overflow; 0
+    /// disables the check.
+    pub max_words: usize,
+
+    /// Flag slides with more lines of code than this; 0 disables the check.
+    pub max_code_lines: usize,
+
+    /// Flag slides with more images than this; 0 disables the check.
+    pub max_images: usize,
+
+    /// Number the lines of every fenced code block, not just those with
+    /// `linenos` in their info strings.
+    pub code_line_numbers: bool,
+
+    /// Syntax-highlight fenced code blocks at build time, in this style.
+    pub highlight: Option<HighlightStyle>,
+
+    /// The syntect theme to highlight code with.
+    pub highlight_theme: String,
+
+    /// The directories of static files, which images and links may refer
+    /// to. Later directories' files replace earlier ones' with the same
+    /// names.
+    pub static_dirs: Vec<PathBuf>,
+
+    /// Check the slides for accessibility problems, like images without alt
+    /// text.
+    pub lint: bool,
+
+    /// Report links and images pointing to files that don't exist, rather
+    /// than failing on missing images.
+    pub check_links: bool,
+
+    /// Check external links with HEAD requests too.
+    pub check_external_links: bool,
+
+    /// Commands to pass the rendered slides through before the template is
+    /// rendered, in order.
+    pub filters: Vec<String>,
+
+    /// How long each filter has to finish.
+    pub filter_timeout: Duration,
+
+    /// Where warnings go, so they can be counted.
+    pub diagnostics: Diagnostics,
+}
+
+impl RenderOptions {
+    /// The static file at `path`, from the last static directory with one.
+    pub fn static_file(&self, path: &Path) -> Option<PathBuf> {
+        self.static_dirs
+            .iter()
+            .rev()
+            .map(|dir| dir.join(path))
+            .find(|path| path.is_file())
+    }
+}
+
+/// A rendered slideshow.
+#[derive(Debug)]
+pub struct Rendered {
+    pub html: String,
+    /// Local files referenced by the slideshow, relative to the input file's
+    /// directory.
+    pub assets: Vec<PathBuf>,
+    /// Links found to be broken, if `RenderOptions::check_links` is set.
+    pub broken_links: Vec<BrokenLink>,
+    /// Markdown files included by the input file, as absolute paths.
+    pub includes: Vec<PathBuf>,
+    /// A Markdown document listing each slide's speaker notes.
+    pub notes: String,
+    /// Statistics about the slides, besides drafts.
+    pub stats: Stats,
+    /// Accessibility problems found, if `RenderOptions::lint` is set.
+    pub lint: Vec<Finding>,
+    /// Slides which may have too much content to fit on the screen.
+    pub overflow: Vec<Finding>,
+    /// What the template was rendered with.
+    context: TemplateContext,
 }
 
 #[instrument(err)]
 pub fn render(
     input_file: impl AsRef<Path> + fmt::Debug,
-    template: impl AsRef<Path> + fmt::Debug,
-) -> Result<String, RenderError> {
-    let input = read(input_file)?;
-    let template = read(template)?;
+    template: Option<&Path>,
+    render_options: &RenderOptions,
+) -> Result<Rendered, RenderError> {
+    let input_file = input_file.as_ref();
+    render_source(&read(input_file)?, input_file, template, render_options)
+}
+
+/// Renders the Markdown `input` as if it were read from `input_file`, which
+/// relative paths in it are relative to. Standard input is `-`.
+#[instrument(err, skip(input))]
+pub fn render_source(
+    input: &str,
+    input_file: &Path,
+    template: Option<&Path>,
+    render_options: &RenderOptions,
+) -> Result<Rendered, RenderError> {
+    let input_dir = input_file
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_owned();
+    let reg = template_registry(template, render_options)?;
+    let template_name = template_name(template);
+    let template = read_template(template)?;
+    let (front_matter, input) = front_matter::split(input, &render_options.diagnostics);
+    // Abbreviation definitions are taken out of the source, including that
+    // of included files, before it's parsed.
+    let abbreviations = RefCell::new(Abbreviations::default());
+    let protect = |source: &str| {
+        if render_options.abbreviations {
+            protect(&abbreviations.borrow_mut().extract(source), render_options)
+        } else {
+            protect(source, render_options)
+        }
+    };
+    let input = &protect(input);
+    let highlighter = match render_options.highlight {
+        Some(style) => Some(Highlighter::new(&render_options.highlight_theme, style)?),
+        None => None,
+    };
 
-    let (rendered_markdown, mut html_output) = {
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_FOOTNOTES);
-        options.insert(Options::ENABLE_TABLES);
-        let parser = Slideshow::new(Parser::new_ext(&input, options));
+    let includes = Includes::load(input_file, input, render_options.extensions, &protect)?;
+    let abbreviations = abbreviations.into_inner();
 
+    let (
+        (openings, closing),
+        slide_count,
+        assets,
+        broken_links,
+        title,
+        notes,
+        stats,
+        lint,
+        overflow,
+        template_slides,
+    ) = {
+        let options = render_options.extensions;
+        let mut names = extension_names(options);
+        if render_options.mark {
+            names.push("mark");
+        }
+        if render_options.abbreviations {
+            names.push("abbreviations");
+        }
+        event!(Level::INFO, "Markdown extensions: {}", names.join(", "));
         let span = span!(Level::INFO, "render_markdown");
         let _guard = span.enter();
-        let mut markdown_html = String::with_capacity(input.len() * 2);
-        html::push_html(&mut markdown_html, parser);
-        let html_output = Vec::<u8>::with_capacity(template.len() + markdown_html.len());
-        (markdown_html, html_output)
+        let mut slides = Slideshow::new(
+            includes.events(
+                input_file,
+                input,
+                options,
+                &render_options.vertical_delimiter,
+            ),
+            render_options,
+            abbreviations,
+            highlighter.as_ref(),
+        )
+        .into_slides()?;
+        let heading = slides
+            .iter()
+            .flat_map(|slide| headings(&slide.events))
+            .find(|(level, _)| *level == 1)
+            .map(|(_, text)| text);
+        let title = match front_matter.get("title") {
+            Some(serde_json::Value::String(title)) => title.clone(),
+            _ => heading.unwrap_or_else(|| {
+                input_file
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            }),
+        };
+        if render_options.title_slide
+            || front_matter.get("title_slide") == Some(&serde_json::Value::Bool(true))
+        {
+            slides.insert(0, title_slide(&reg, &front_matter, &title)?);
+        }
+        if let Some(ranges) = &render_options.slides {
+            let count = slides.len();
+            slides = slides
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| ranges.contains(i + 1))
+                .map(|(i, mut slide)| {
+                    slide.source_number = Some(i + 1);
+                    slide
+                })
+                .collect();
+            if slides.is_empty() {
+                return Err(RenderError::NoSlidesSelected(count));
+            }
+        }
+        let number_headings = match front_matter.get("number_headings") {
+            Some(serde_json::Value::Bool(number_headings)) => *number_headings,
+            _ => render_options.number_headings,
+        };
+        if number_headings {
+            self::number_headings(&mut slides);
+        }
+        insert_tocs(&mut slides);
+        let overflow = check_overflow(&mut slides, render_options);
+        let header = render_options
+            .slide_header
+            .clone()
+            .or_else(|| front_matter_string(&front_matter, "slide_header"));
+        let footer = render_options
+            .slide_footer
+            .clone()
+            .or_else(|| front_matter_string(&front_matter, "slide_footer"));
+        set_headers_and_footers(&mut slides, header, footer);
+        set_layouts(&mut slides, &reg)?;
+
+        let slide_count = slides.len();
+        let (assets, mut broken_links) = local_assets(&input_dir, &slides, render_options)?;
+        if render_options.check_links {
+            broken_links.extend(check_links(&input_dir, &slides, render_options));
+        }
+        // Slides are only grouped into vertical stacks if there are any.
+        let stacks: Vec<_> = slides.iter().map(|slide| slide.stack).collect();
+        let stacked = stacks.windows(2).any(|pair| pair[0] == pair[1]);
+        let stack_start = |i: usize| i == 0 || stacks[i - 1] != stacks[i];
+        // Likewise, slides are only grouped into sections if there are any
+        // level-1 headings starting a stack.
+        let sections: Vec<_> = slides
+            .iter()
+            .enumerate()
+            .map(|(i, slide)| section_id(slide).filter(|_| stack_start(i)))
+            .collect();
+        let sectioned = sections.iter().any(Option::is_some);
+        let notes = notes_document(&title, &slides);
+        let counts: Vec<_> = slides
+            .iter()
+            .filter(|slide| !slide.draft)
+            .map(|slide| {
+                let counts = slide.counts();
+                SlideCounts {
+                    words: counts.words,
+                    images: slide.images.len(),
+                    code_blocks: counts.code_blocks,
+                }
+            })
+            .collect();
+        let stats = Stats::new(&counts, render_options.words_per_minute);
+        let findings = if render_options.lint {
+            lint(&slides)
+        } else {
+            Vec::new()
+        };
+        // The markup around the slides, for stacks and sections, is kept
+        // apart from them until they've been through the filters.
+        let mut openings = Vec::with_capacity(slide_count);
+        let mut template_slides = Vec::with_capacity(slide_count);
+        for (i, mut slide) in slides.into_iter().enumerate() {
+            let mut opening = String::new();
+            let new_section = sectioned && (i == 0 || sections[i].is_some());
+            if stacked && stack_start(i) && i > 0 {
+                opening.push_str("</section>\n");
+            }
+            if new_section {
+                if i > 0 {
+                    opening.push_str("</div>\n");
+                }
+                opening.push_str("<div class=\"deck-section\"");
+                if let Some(id) = &sections[i] {
+                    opening.push_str(&format!(r#" data-section="{}""#, html_escape(id)));
+                    if let Some(number) = &slide.section_number {
+                        opening.push_str(&format!(
+                            r#" data-section-number="{}""#,
+                            html_escape(number)
+                        ));
+                    }
+                }
+                opening.push_str(">\n");
+            }
+            if stacked && stack_start(i) {
+                opening.push_str("<section class=\"stack\">\n");
+            }
+            openings.push(opening);
+            let mut template_slide = filter::Slide {
+                html: String::new(),
+                number: i + 1,
+                id: slide.attributes.id.clone().unwrap_or_default(),
+                title: first_heading(&slide.events).map(|(_, title)| title),
+                notes: slide.notes_html(render_options).unwrap_or_default(),
+            };
+            match slide.layout.take() {
+                Some(layout) => slide.push_layout_html(
+                    &mut template_slide.html,
+                    &layout,
+                    i + 1,
+                    slide_count,
+                    &reg,
+                    render_options,
+                )?,
+                None => {
+                    slide.push_html(&mut template_slide.html, i + 1, slide_count, render_options)
+                }
+            }
+            template_slides.push(template_slide);
+        }
+        let mut closing = String::new();
+        if stacked {
+            closing.push_str("</section>\n");
+        }
+        if sectioned {
+            closing.push_str("</div>\n");
+        }
+        (
+            (openings, closing),
+            slide_count,
+            assets,
+            broken_links,
+            title,
+            notes,
+            stats,
+            findings,
+            overflow,
+            template_slides,
+        )
     };
 
+    let mut vars = front_matter;
+    if let Some(data) = &render_options.data {
+        vars.extend(vars::load(data)?);
+    }
+    vars.extend(render_options.vars.iter().map(|var| {
+        (
+            var.key.clone(),
+            serde_json::Value::String(var.value.clone()),
+        )
+    }));
+    let input_file_name = input_file
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let (title, vars, template_slides) = if render_options.filters.is_empty() {
+        (title, vars, template_slides)
+    } else {
+        let span = span!(Level::INFO, "filter");
+        let _guard = span.enter();
+        let mut document = filter::Document {
+            version: filter::VERSION,
+            input_file: input_file_name.clone(),
+            title,
+            metadata: vars,
+            slides: template_slides,
+        };
+        for command in &render_options.filters {
+            document = filter::run(command, &document, render_options.filter_timeout)?;
+        }
+        (document.title, document.metadata, document.slides)
+    };
+    let mut rendered_markdown = String::with_capacity(input.len() * 2);
+    for (opening, slide) in openings.iter().zip(&template_slides) {
+        rendered_markdown.push_str(opening);
+        rendered_markdown.push_str(&slide.html);
+    }
+    rendered_markdown.push_str(&closing);
+    let og_description = vars.get("description").and_then(plain_text);
+    let og_author = vars.get("author").and_then(plain_text);
+    let og_image = og_image(&vars, render_options);
+    let git = if render_options.git_metadata {
+        git::metadata(&input_dir)
+    } else {
+        None
+    };
     let ctx = TemplateContext {
+        vars,
+        og_title: title.clone(),
+        og_description,
+        og_author,
+        og_image,
+        title,
+        base_url: render_options.base_url.clone(),
+        root: render_options.root.clone(),
+        profile: render_options.profiles.join(" "),
+        theme: render_options.theme.clone(),
+        build_timestamp: if render_options.reproducible {
+            None
+        } else {
+            Some(Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))
+        },
+        git_commit: git.as_ref().map(|git| git.commit.clone()),
+        git_dirty: git.as_ref().map(|git| git.dirty),
+        git_branch: git.and_then(|git| git.branch),
+        input_file: input_file_name,
+        generator: concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")),
         content: rendered_markdown,
+        slides: template_slides,
+        slide_count,
     };
 
+    Ok(Rendered {
+        html: render_template(
+            &reg,
+            &template_name,
+            &template,
+            &ctx,
+            &render_options.diagnostics,
+        )?,
+        assets,
+        broken_links,
+        includes: includes.paths(),
+        notes,
+        stats,
+        lint,
+        overflow,
+        context: ctx,
+    })
+}
+
+impl Rendered {
+    /// The slideshow's title, from its front matter or first heading.
+    pub fn title(&self) -> &str {
+        &self.context.title
+    }
+
+    /// Renders the slideshow with another `template`, or the default one,
+    /// without parsing the Markdown again; for a handout, or after the
+    /// template changes.
+    pub fn render_template(
+        &self,
+        template: Option<&Path>,
+        render_options: &RenderOptions,
+    ) -> Result<String, RenderError> {
+        let reg = template_registry(template, render_options)?;
+        render_template(
+            &reg,
+            &template_name(template),
+            &read_template(template)?,
+            &self.context,
+            &render_options.diagnostics,
+        )
+    }
+}
+
+fn read_template(template: Option<&Path>) -> Result<String, RenderError> {
+    match template {
+        Some(template) => read(template),
+        None => Ok(DEFAULT_TEMPLATE.to_owned()),
+    }
+}
+
+/// What errors in `template`, or the default template, call it.
+fn template_name(template: Option<&Path>) -> String {
+    match template {
+        Some(template) => template.display().to_string(),
+        None => "the built-in template".to_owned(),
+    }
+}
+
+/// Renders `template`, named `name` in errors, with `ctx`.
+fn render_template(
+    reg: &Handlebars,
+    name: &str,
+    template: &str,
+    ctx: &TemplateContext,
+    diagnostics: &Diagnostics,
+) -> Result<String, RenderError> {
     let span = span!(Level::INFO, "render_handlebars");
     let _guard = span.enter();
-    let reg = Handlebars::new();
-    reg.render_template_source_to_write(&mut template.as_bytes(), &ctx, &mut html_output)?;
+    let mut html_output = Vec::with_capacity(template.len() + ctx.content.len());
+    reg.render_template_source_to_write(&mut template.as_bytes(), ctx, &mut html_output)
+        .map_err(|mut err| {
+            // Templates rendered from their source are unnamed, unlike
+            // partials.
+            let template_name = match &mut err {
+                TemplateRenderError::TemplateError(err) => &mut err.template_name,
+                TemplateRenderError::RenderError(err) => &mut err.template_name,
+                _ => return err,
+            };
+            template_name.get_or_insert_with(|| name.to_owned());
+            err
+        })?;
+    let html = String::from_utf8(html_output)?;
+    // Templates include the slides either all at once as `content`, or one
+    // by one from `slides`; either way, the first one should be there.
+    if let Some(slide) = ctx.slides.first() {
+        if !html.contains(&slide.html) {
+            if reg.strict_mode() {
+                return Err(RenderError::NoContent);
+            }
+            diagnostics.warn(RenderError::NoContent);
+        }
+    }
+    Ok(html)
+}
 
-    Ok(String::from_utf8(html_output)?)
+/// Drops the blocks in Markdown `source` for other build profiles, and
+/// replaces the math and keyboard shortcuts with placeholders, if they're
+/// enabled.
+fn protect(source: &str, render_options: &RenderOptions) -> String {
+    let mut source = profile::filter(
+        source,
+        &render_options.profiles,
+        &render_options.diagnostics,
+    );
+    if render_options.math {
+        source = math::protect(&source);
+    }
+    if render_options.kbd {
+        source = kbd::protect(&source);
+    }
+    source
 }
 
-#[derive(Serialize, Debug)]
-struct TemplateContext {
-    content: String,
+/// The Handlebars registry to render `template` with, with the partials and
+/// helpers registered.
+fn template_registry(
+    template: Option<&Path>,
+    render_options: &RenderOptions,
+) -> Result<Handlebars<'static>, RenderError> {
+    let template_dir = template
+        .and_then(Path::parent)
+        .unwrap_or_else(|| Path::new(""))
+        .to_owned();
+    let mut reg = registry(render_options.partials.as_deref())?;
+    reg.set_strict_mode(render_options.strict_templates);
+    helpers::register(&mut reg, template_dir, render_options);
+    Ok(reg)
 }
 
-fn read(path: impl AsRef<Path>) -> Result<String, RenderError> {
-    let mut file = File::open(&path).map_err(|e| RenderError::Read(path.as_ref().into(), e))?;
-    let len = file.metadata().map(|m| m.len() as usize).unwrap_or(4096);
-    let mut content = String::with_capacity(len);
-    file.read_to_string(&mut content)
-        .map_err(|e| RenderError::Read(path.as_ref().into(), e))
-        .map(|_| content)
+/// A Handlebars registry with the `name.hbs` and `name.html` partials in
+/// `partials` registered.
+fn registry(partials: Option<&Path>) -> Result<Handlebars<'static>, RenderError> {
+    let mut reg = Handlebars::new();
+    let partials = match partials {
+        Some(partials) => partials,
+        None => return Ok(reg),
+    };
+    let entries = fs::read_dir(partials).map_err(|e| RenderError::Read(partials.into(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| RenderError::Read(partials.into(), e))?
+            .path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "hbs" || extension == "html")
+        {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            event!(Level::DEBUG, %name, "registering partial");
+            reg.register_partial(&name, read(&path)?)
+                .map_err(|err| RenderError::Partial(path.clone(), Box::new(err)))?;
+        }
+    }
+    Ok(reg)
 }
 
-struct Slideshow<'a> {
-    parser: Parser<'a>,
-    next_events: VecDeque<Event<'a>>,
-    in_slide: bool,
-    slide_number: u32,
+/// The generated title slide, rendered with the `title-slide` partial if
+/// there is one.
+fn title_slide<'a>(
+    reg: &Handlebars,
+    front_matter: &FrontMatter,
+    title: &str,
+) -> Result<Slide<'a>, RenderError> {
+    let html = if reg.has_template("title-slide") {
+        let mut ctx = front_matter.clone();
+        ctx.insert("title".to_owned(), title.into());
+        reg.render("title-slide", &ctx)
+            .map_err(TemplateRenderError::from)?
+    } else {
+        let mut html = format!("<h1>{}</h1>\n", html_escape(title));
+        for (key, class) in &[("author", "byline"), ("date", "date")] {
+            if let Some(text) = front_matter.get(*key).and_then(plain_text) {
+                html.push_str(&format!(
+                    "<p class=\"{}\">{}</p>\n",
+                    class,
+                    html_escape(&text)
+                ));
+            }
+        }
+        html
+    };
+    Ok(Slide {
+        attributes: Attributes {
+            id: Some("title-slide".to_owned()),
+            classes: vec!["title-slide".to_owned()],
+            ..Default::default()
+        },
+        events: vec![Event::Html(html.into())],
+        ..Default::default()
+    })
 }
 
-impl<'a> Slideshow<'a> {
-    fn new(parser: Parser<'a>) -> Self {
-        let mut ret = Self {
-            parser,
-            next_events: Default::default(),
-            in_slide: false,
-            slide_number: 0,
-        };
-        ret.start_slide();
-        ret
+/// A front matter value as text, with lists (like several authors) joined
+/// by commas.
+fn plain_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Array(values) => {
+            let values: Vec<_> = values.iter().filter_map(plain_text).collect();
+            Some(values.join(", ")).filter(|s| !s.is_empty())
+        }
+        _ => None,
     }
+}
 
-    fn start_slide(&mut self) {
-        self.slide_number += 1;
-        self.in_slide = true;
-        self.next_events.push_back(Event::Html(
-            format!(
-                r#"<section class="slide" id="slide-{}"><div class="slide-inner">"#,
-                self.slide_number
-            )
-            .into(),
+/// The absolute URL of the front matter's `image`, a file in the static
+/// directory, for link previews.
+fn og_image(front_matter: &FrontMatter, render_options: &RenderOptions) -> Option<String> {
+    let image = front_matter_string(front_matter, "image")?;
+    if image.starts_with("http://") || image.starts_with("https://") {
+        return Some(image);
+    }
+    let path = image.trim_start_matches('/');
+    if render_options.static_file(Path::new(path)).is_none() {
+        render_options.diagnostics.warn(format_args!(
+            "Leaving out the preview image {}, which isn't in the static files directories",
+            image,
         ));
+        return None;
     }
+    match &render_options.base_url {
+        Some(base_url) => Some(format!("{}{}", base_url, path)),
+        None => {
+            render_options.diagnostics.warn(format_args!(
+                "Leaving out the preview image {}, which needs `--base-url` for an absolute URL",
+                image
+            ));
+            None
+        }
+    }
+}
 
-    fn end_slide(&mut self) {
-        self.next_events
-            .push_back(Event::Html(r#"</div></section>"#.into()));
-        self.in_slide = false;
+/// A Markdown document with a section for each slide, numbered as in the
+/// rendered deck, holding its speaker notes.
+fn notes_document(title: &str, slides: &[Slide<'_>]) -> String {
+    let mut notes = format!("# {}\n", title);
+    for (i, slide) in slides.iter().enumerate() {
+        notes.push_str(&format!("\n## Slide {}", i + 1));
+        if let Some((_, heading)) = first_heading(&slide.events) {
+            notes.push_str(&format!(": {}", heading));
+        }
+        notes.push_str("\n\n");
+        if slide.notes.is_empty() {
+            notes.push_str("(no notes)\n");
+        } else {
+            let slide_notes: Vec<_> = slide.notes.iter().map(|note| note.trim()).collect();
+            notes.push_str(&slide_notes.join("\n\n"));
+            notes.push('\n');
+        }
     }
+    notes
+}
 
-    fn transform(&mut self, event: Event<'a>) {
-        match event {
-            Event::Rule => {
-                if self.in_slide {
-                    self.end_slide();
+fn front_matter_string(front_matter: &FrontMatter, key: &str) -> Option<String> {
+    match front_matter.get(key) {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Gives each slide the header and footer, unless it overrides them with a
+/// directive like `<!-- .slide: footer="" -->`. Empty ones are left out.
+fn set_headers_and_footers(
+    slides: &mut [Slide<'_>],
+    header: Option<String>,
+    footer: Option<String>,
+) {
+    for slide in slides {
+        let mut header = header.clone();
+        let mut footer = footer.clone();
+        slide
+            .attributes
+            .other
+            .retain(|(key, value)| match key.as_str() {
+                "header" => {
+                    header = Some(value.clone());
+                    false
                 }
-                self.start_slide();
+                "footer" => {
+                    footer = Some(value.clone());
+                    false
+                }
+                _ => true,
+            });
+        slide.header = header.filter(|header| !header.trim().is_empty());
+        slide.footer = footer.filter(|footer| !footer.trim().is_empty());
+    }
+}
+
+/// Takes the layouts slides select with directives like `<!-- .slide:
+/// layout="quote" -->`, checking there's a `layout-quote` partial for each.
+fn set_layouts(slides: &mut [Slide<'_>], reg: &Handlebars) -> Result<(), RenderError> {
+    for (i, slide) in slides.iter_mut().enumerate() {
+        let mut layout = None;
+        slide.attributes.other.retain(|(key, value)| {
+            if key == "layout" {
+                layout = Some(value.clone());
+                false
+            } else {
+                true
             }
-            _ => {
-                self.next_events.push_back(event);
+        });
+        if let Some(name) = &layout {
+            if !reg.has_template(&format!("layout-{}", name)) {
+                return Err(RenderError::UnknownLayout {
+                    name: name.clone(),
+                    slide: i + 1,
+                });
             }
         }
+        slide.layout = layout;
     }
+    Ok(())
 }
 
-impl<'a> Iterator for Slideshow<'a> {
-    type Item = <Parser<'a> as Iterator>::Item;
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next_events.pop_front().or_else(|| {
-            let event = self.parser.next()?;
-            self.transform(event);
-            self.next()
-        })
+/// Marks slides with so much content they might not fit on the screen with
+/// `data-overflow-risk` attributes, listing what there's too much of, and
+/// warns about them.
+fn check_overflow(slides: &mut [Slide<'_>], render_options: &RenderOptions) -> Vec<Finding> {
+    let mut risky = Vec::new();
+    let mut findings = Vec::new();
+    for (i, slide) in slides.iter_mut().enumerate() {
+        let counts = slide.counts();
+        let limits = [
+            ("words", counts.words, render_options.max_words),
+            (
+                "code-lines",
+                counts.code_lines,
+                render_options.max_code_lines,
+            ),
+            ("images", slide.images.len(), render_options.max_images),
+        ];
+        let risks: Vec<_> = limits
+            .iter()
+            .filter(|(_, count, max)| *max > 0 && count > max)
+            .map(|(name, _, _)| *name)
+            .collect();
+        if !risks.is_empty() {
+            findings.push(Finding {
+                slide: i + 1,
+                message: format!("Too much content to fit: {}", risks.join(", ")),
+            });
+            slide
+                .attributes
+                .other
+                .push(("data-overflow-risk".to_owned(), risks.join(" ")));
+            risky.push((i + 1).to_string());
+        }
+    }
+    if !risky.is_empty() {
+        render_options.diagnostics.warn(format_args!(
+            "Slides {} may have too much content to fit",
+            risky.join(", ")
+        ));
+    }
+    findings
+}
+
+/// Checks the slides for accessibility problems: images without alt text,
+/// links without useful text, skipped heading levels, and slides without
+/// headings.
+fn lint(slides: &[Slide<'_>]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut last_level = None;
+    for (i, slide) in slides.iter().enumerate() {
+        let mut finding = |message| {
+            findings.push(Finding {
+                slide: i + 1,
+                message,
+            })
+        };
+        // The title slide's heading is raw HTML.
+        if slide.attributes.classes.iter().any(|c| c == "title-slide") {
+            last_level = Some(1);
+            continue;
+        }
+        for (alt, dest) in slide.image_alts.iter().zip(&slide.images) {
+            if alt.trim().is_empty() {
+                finding(format!("Image {} has no alt text", dest));
+            }
+        }
+        for text in &slide.link_texts {
+            if let Some(message) = lint::link_text(text) {
+                finding(message);
+            }
+        }
+        let mut has_heading = false;
+        for (level, text) in headings(&slide.events) {
+            has_heading = true;
+            if let Some(last) = last_level.filter(|last| level > last + 1) {
+                finding(format!(
+                    "Heading {:?} skips from level {} to level {}",
+                    text, last, level
+                ));
+            }
+            last_level = Some(level);
+        }
+        if !has_heading {
+            finding("Slide has no heading".to_owned());
+        }
+    }
+    findings
+}
+
+/// Finds the local files the slides' images refer to, which need copying
+/// into the output directory, and any images which don't exist.
+fn local_assets(
+    input_dir: &Path,
+    slides: &[Slide<'_>],
+    render_options: &RenderOptions,
+) -> Result<(Vec<PathBuf>, Vec<BrokenLink>), RenderError> {
+    let mut assets = Vec::new();
+    let mut broken = Vec::new();
+    for (i, slide) in slides.iter().enumerate() {
+        for dest in &slide.images {
+            let path = match assets::local_path(dest, &render_options.diagnostics) {
+                Some(path) => path,
+                None => continue,
+            };
+            if input_dir.join(&path).is_file() {
+                if !assets.contains(&path) {
+                    assets.push(path);
+                }
+            } else if render_options.static_file(&path).is_some() {
+                // Already copied with the rest of the static files.
+            } else if render_options.check_links {
+                broken.push(BrokenLink {
+                    slide: i + 1,
+                    dest: dest.clone(),
+                    reason: format!("Image {} doesn't exist", path.display()),
+                });
+            } else {
+                return Err(RenderError::MissingImage { path, slide: i + 1 });
+            }
+        }
+    }
+    Ok((assets, broken))
+}
+
+/// Checks the destinations of the slides' links.
+fn check_links(
+    input_dir: &Path,
+    slides: &[Slide<'_>],
+    render_options: &RenderOptions,
+) -> Vec<BrokenLink> {
+    let checker = LinkChecker {
+        input_dir,
+        static_dirs: &render_options.static_dirs,
+        ids: slides
+            .iter()
+            .filter_map(|slide| slide.attributes.id.as_deref())
+            .collect(),
+        external: render_options.check_external_links,
+        diagnostics: &render_options.diagnostics,
+    };
+    let mut broken = Vec::new();
+    for (i, slide) in slides.iter().enumerate() {
+        for dest in &slide.links {
+            if let Some(reason) = checker.check(dest) {
+                broken.push(BrokenLink {
+                    slide: i + 1,
+                    dest: dest.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+    broken
+}
+
+#[derive(Serialize, Debug)]
+struct TemplateContext {
+    /// The front matter, overridden by the data file's variables and then
+    /// `--var`s, passed through as top-level keys. Declared first so that the
+    /// fields below win if they use the same names.
+    #[serde(flatten)]
+    vars: FrontMatter,
+    /// Plain text, so `{{title}}` escapes it.
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_url: Option<String>,
+    /// The path to the output directory, for static files like
+    /// `{{root}}style.css`.
+    root: String,
+    /// The active build profiles, separated by spaces.
+    profile: String,
+    /// Open Graph metadata for link previews, from the front matter's
+    /// `title`, `description`, `author`, and `image`. The image is an
+    /// absolute URL, so it's only given with a base URL.
+    og_title: String,
+    og_description: Option<String>,
+    og_author: Option<String>,
+    og_image: Option<String>,
+    /// The theme written to `theme.css`.
+    theme: String,
+    /// When the slideshow was built, unless the build is reproducible.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_timestamp: Option<String>,
+    /// The git commit the input file is at, if it's in a repository.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_dirty: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_branch: Option<String>,
+    /// The input file's name.
+    input_file: String,
+    /// The name and version of this program.
+    generator: &'static str,
+    content: String,
+    /// Each slide, for templates to lay out themselves.
+    slides: Vec<filter::Slide>,
+    slide_count: usize,
+}
+
+/// A slide as its layout partial sees it.
+#[derive(Serialize, Debug)]
+struct LayoutContext {
+    /// The slide's content, without the `<section>` around it.
+    html: String,
+    number: usize,
+    count: usize,
+    id: String,
+    /// The `<section>`'s classes, including `slide` and any from directives.
+    classes: String,
+    /// All of the `<section>`'s attributes, like ` class="slide" id="intro"`,
+    /// for `<section{{{attributes}}}>`.
+    attributes: String,
+    /// The text of the slide's first heading.
+    title: Option<String>,
+    /// The slide's speaker notes as HTML, or nothing.
+    notes: String,
+    header: Option<String>,
+    footer: Option<String>,
+}
+
+fn read(path: impl AsRef<Path>) -> Result<String, RenderError> {
+    let mut file = File::open(&path).map_err(|e| RenderError::Read(path.as_ref().into(), e))?;
+    let len = file.metadata().map(|m| m.len() as usize).unwrap_or(4096);
+    let mut content = String::with_capacity(len);
+    file.read_to_string(&mut content)
+        .map_err(|e| RenderError::Read(path.as_ref().into(), e))
+        .map(|_| content)
+}
+
+/// Returns the level and plain text of each heading in `events`, without
+/// any inline formatting.
+fn headings<'e>(events: &'e [Event<'_>]) -> impl Iterator<Item = (u32, String)> + 'e {
+    events.iter().enumerate().filter_map(move |(start, event)| {
+        let level = match event {
+            Event::Start(Tag::Heading(level, _, _)) => *level as u32,
+            _ => return None,
+        };
+        let mut text = String::new();
+        for event in &events[start + 1..] {
+            match event {
+                Event::End(Tag::Heading(..)) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                Event::SoftBreak | Event::HardBreak => text.push(' '),
+                _ => {}
+            }
+        }
+        Some((level, text))
+    })
+}
+
+fn first_heading(events: &[Event<'_>]) -> Option<(u32, String)> {
+    headings(events).next()
+}
+
+/// If `slide` starts a section of the deck, by opening with a level-1
+/// heading, the section's ID, which is the slide's own.
+fn section_id(slide: &Slide<'_>) -> Option<String> {
+    match first_heading(&slide.events) {
+        Some((1, _)) => slide.attributes.id.clone(),
+        _ => None,
+    }
+}
+
+/// Returns the inner text of `html` if it consists of a single HTML comment.
+pub fn comment_body(html: &str) -> Option<&str> {
+    let html = html.trim();
+    if html.starts_with("<!--") && html.ends_with("-->") && html.len() >= 7 {
+        Some(html[4..html.len() - 3].trim())
+    } else {
+        None
+    }
+}
+
+/// A slide's content, collected from the Markdown before being written out.
+#[derive(Default)]
+struct Slide<'a> {
+    /// Attributes for the slide's `<section>` tag, besides the `slide` class.
+    attributes: Attributes,
+    events: Vec<Event<'a>>,
+    /// Markdown source of the slide's speaker notes.
+    notes: Vec<String>,
+    /// Destinations of the slide's images.
+    images: Vec<String>,
+    /// Destinations of the slide's links.
+    links: Vec<String>,
+    /// Alt text of the slide's images and videos.
+    image_alts: Vec<String>,
+    /// Visible text of the slide's links.
+    link_texts: Vec<String>,
+    /// Lines of code in blocks rendered to HTML ourselves.
+    code_lines: usize,
+    /// The number of code blocks rendered to HTML ourselves.
+    code_blocks: usize,
+    /// Tables of contents to insert into the slide.
+    tocs: Vec<Toc>,
+    /// Is the slide a draft, left out unless drafts are included?
+    draft: bool,
+    /// The number of the vertical stack the slide is in.
+    stack: u32,
+    /// The slide's number in the whole deck, if only some slides are being
+    /// rendered.
+    source_number: Option<usize>,
+    /// The number of the section the slide starts, if headings are
+    /// numbered.
+    section_number: Option<String>,
+    /// Markdown for the slide's header and footer.
+    header: Option<String>,
+    footer: Option<String>,
+    /// The name of the `layout-name` partial to write the slide with, in
+    /// place of the default `<section>`.
+    layout: Option<String>,
+    /// Attributes from `{#id .class}` groups after headings, by the heading's
+    /// index among the slide's headings.
+    heading_attributes: HashMap<usize, Attributes>,
+}
+
+/// A `<!-- toc -->` directive.
+struct Toc {
+    /// Where in the slide's events to insert the table of contents.
+    index: usize,
+    /// Only list headings of this level or higher.
+    depth: Option<u32>,
+}
+
+/// Numbers the slides' headings like "2.3 Borrow Checker", counting each
+/// level across the whole deck and restarting a level's count after each
+/// higher-level heading.
+fn number_headings(slides: &mut [Slide<'_>]) {
+    let mut counts = [0; 6];
+    for slide in slides {
+        let mut first = true;
+        let mut i = 0;
+        while i < slide.events.len() {
+            let level = match slide.events[i] {
+                Event::Start(Tag::Heading(level, _, _)) => level as usize,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            counts[level - 1] += 1;
+            counts[level..].iter_mut().for_each(|count| *count = 0);
+            // Leave out the counts of levels the deck doesn't start with.
+            let number = counts[..level]
+                .iter()
+                .skip_while(|count| **count == 0)
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(".");
+            if level == 1 && first {
+                slide.section_number = Some(number.clone());
+            }
+            first = false;
+            match slide.events.get_mut(i + 1) {
+                Some(Event::Text(text)) => *text = format!("{} {}", number, text).into(),
+                _ => {
+                    slide
+                        .events
+                        .insert(i + 1, Event::Text(format!("{} ", number).into()));
+                    // Keep the tables of contents where they were.
+                    for toc in slide.tocs.iter_mut().filter(|toc| toc.index > i) {
+                        toc.index += 1;
+                    }
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Inserts each slide's tables of contents, which list the first heading of
+/// every other slide.
+fn insert_tocs(slides: &mut [Slide<'_>]) {
+    let headings: Vec<_> = slides
+        .iter()
+        .filter_map(|slide| Some((slide.attributes.id.clone()?, first_heading(&slide.events)?)))
+        .collect();
+    for slide in slides.iter_mut() {
+        let own_id = slide.attributes.id.clone();
+        for toc in mem::take(&mut slide.tocs).into_iter().rev() {
+            let mut html = String::from("<ul>\n");
+            for (id, (level, text)) in &headings {
+                if Some(id) != own_id.as_ref() && toc.depth.is_none_or(|depth| *level <= depth) {
+                    html.push_str(&format!(
+                        "<li><a href=\"#{}\">{}</a></li>\n",
+                        html_escape(id),
+                        html_escape(text)
+                    ));
+                }
+            }
+            html.push_str("</ul>\n");
+            slide.events.insert(toc.index, Event::Html(html.into()));
+        }
+    }
+}
+
+/// How much content is on a slide.
+struct Counts {
+    words: usize,
+    code_lines: usize,
+    code_blocks: usize,
+}
+
+impl<'a> Slide<'a> {
+    /// The slide's speaker notes as HTML, if it has any.
+    fn notes_html(&self, render_options: &RenderOptions) -> Option<String> {
+        if self.notes.is_empty() {
+            None
+        } else {
+            Some(markdown_html(&self.notes.join("\n\n"), render_options))
+        }
+    }
+
+    fn counts(&self) -> Counts {
+        let mut counts = Counts {
+            words: 0,
+            code_lines: self.code_lines,
+            code_blocks: self.code_blocks,
+        };
+        let mut in_code_block = false;
+        for event in &self.events {
+            match event {
+                Event::Start(Tag::CodeBlock(_)) => {
+                    in_code_block = true;
+                    counts.code_blocks += 1;
+                }
+                Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+                Event::Text(text) if in_code_block => counts.code_lines += text.lines().count(),
+                Event::Text(text) | Event::Code(text) => {
+                    counts.words += text.split_whitespace().count()
+                }
+                _ => {}
+            }
+        }
+        counts
+    }
+
+    /// Writes the slide as the `number`th of `count`.
+    fn push_html(
+        mut self,
+        html: &mut String,
+        number: usize,
+        count: usize,
+        render_options: &RenderOptions,
+    ) {
+        let notes = self.notes_html(render_options);
+        let attributes = self.section_attributes(number, count);
+
+        let mut start = format!("<section{}>", attributes);
+        if let Some(header) = &self.header {
+            start.push_str(&format!(
+                r#"<header class="slide-header">{}</header>"#,
+                markdown_html(header, render_options)
+            ));
+        }
+        start.push_str(r#"<div class="slide-inner">"#);
+        let start = Event::Html(start.into());
+        let notes = notes.map(|notes| {
+            let aside = format!(r#"<aside class="notes">{}</aside>"#, notes);
+            Event::Html(aside.into())
+        });
+        let mut end = String::from("</div>");
+        if let Some(footer) = &self.footer {
+            end.push_str(&format!(
+                r#"<footer class="slide-footer">{}</footer>"#,
+                markdown_html(footer, render_options)
+            ));
+        }
+        end.push_str("</section>");
+        let end = Event::Html(end.into());
+
+        html::push_html(
+            html,
+            iter::once(start)
+                .chain(self.content_events())
+                .chain(notes)
+                .chain(iter::once(end)),
+        );
+    }
+
+    /// Writes the slide as the `number`th of `count` with the
+    /// `layout-{layout}` partial.
+    fn push_layout_html(
+        mut self,
+        html: &mut String,
+        layout: &str,
+        number: usize,
+        count: usize,
+        reg: &Handlebars,
+        render_options: &RenderOptions,
+    ) -> Result<(), RenderError> {
+        let notes = self.notes_html(render_options).unwrap_or_default();
+        let title = first_heading(&self.events).map(|(_, title)| title);
+        let attributes = self.section_attributes(number, count);
+        let mut content = String::new();
+        html::push_html(&mut content, self.content_events().into_iter());
+        let ctx = LayoutContext {
+            html: content,
+            number,
+            count,
+            id: attributes.id.clone().unwrap_or_default(),
+            classes: attributes.classes.join(" "),
+            attributes: attributes.to_string(),
+            title,
+            notes,
+            header: self
+                .header
+                .as_deref()
+                .map(|header| markdown_html(header, render_options)),
+            footer: self
+                .footer
+                .as_deref()
+                .map(|footer| markdown_html(footer, render_options)),
+        };
+        html.push_str(
+            &reg.render(&format!("layout-{}", layout), &ctx)
+                .map_err(TemplateRenderError::from)?,
+        );
+        Ok(())
+    }
+
+    /// The attributes of the slide's `<section>`, taking the ones from its
+    /// directives.
+    fn section_attributes(&mut self, number: usize, count: usize) -> Attributes {
+        let mut attributes = Attributes {
+            classes: vec!["slide".to_owned()],
+            ..Default::default()
+        };
+        attributes.merge(mem::take(&mut self.attributes));
+        attributes
+            .other
+            .push(("data-slide-number".to_owned(), number.to_string()));
+        attributes
+            .other
+            .push(("data-slide-count".to_owned(), count.to_string()));
+        if let Some(source_number) = self.source_number {
+            attributes
+                .other
+                .push(("data-source-slide".to_owned(), source_number.to_string()));
+        }
+        attributes
+    }
+
+    /// Takes the slide's content, with its headings' attribute groups
+    /// applied.
+    fn content_events(&mut self) -> Vec<Event<'a>> {
+        // Headings with attribute groups are written as HTML.
+        let mut heading_attributes = mem::take(&mut self.heading_attributes);
+        let mut heading = 0;
+        let mut open_heading = None;
+        let events = mem::take(&mut self.events)
+            .into_iter()
+            .map(move |event| match event {
+                Event::Start(Tag::Heading(level, _, _)) => {
+                    let attributes = heading_attributes.remove(&heading);
+                    heading += 1;
+                    match attributes {
+                        Some(attributes) => {
+                            open_heading = Some(level);
+                            Event::Html(format!("<{}{}>", level, attributes).into())
+                        }
+                        None => event,
+                    }
+                }
+                Event::End(Tag::Heading(..)) if open_heading.is_some() => {
+                    Event::Html(format!("</{}>\n", open_heading.take().unwrap()).into())
+                }
+                _ => event,
+            });
+        sanitize::sanitize_events(events.collect())
+    }
+}
+
+/// Renders Markdown from outside the slides' own content, like speaker notes,
+/// treating its raw HTML as `RenderOptions::raw_html` says.
+fn markdown_html(markdown: &str, render_options: &RenderOptions) -> String {
+    events_html(
+        Parser::new_ext(markdown, render_options.extensions),
+        render_options,
+    )
+}
+
+/// Renders Markdown like `markdown_html`, but without the paragraph around
+/// it if it's a single paragraph, for use inside other elements.
+pub fn inline_markdown_html(markdown: &str, render_options: &RenderOptions) -> String {
+    let mut events: Vec<_> = Parser::new_ext(markdown, render_options.extensions).collect();
+    let paragraphs = events
+        .iter()
+        .filter(|event| matches!(event, Event::Start(Tag::Paragraph)))
+        .count();
+    if paragraphs == 1
+        && matches!(events.first(), Some(Event::Start(Tag::Paragraph)))
+        && matches!(events.last(), Some(Event::End(Tag::Paragraph)))
+    {
+        events.pop();
+        events.remove(0);
+    }
+    events_html(events.into_iter(), render_options)
+}
+
+fn events_html<'e>(
+    events: impl Iterator<Item = Event<'e>>,
+    render_options: &RenderOptions,
+) -> String {
+    let mut ret = String::new();
+    match render_options.raw_html {
+        RawHtml::Keep => html::push_html(&mut ret, events),
+        RawHtml::Sanitize => {
+            html::push_html(&mut ret, events);
+            ret = sanitize::sanitize(&ret);
+        }
+        RawHtml::Drop => html::push_html(
+            &mut ret,
+            events.filter(|event| !matches!(event, Event::Html(_))),
+        ),
+    }
+    ret
+}
+
+/// `:::name` container markers on their own lines in a paragraph. Each is a
+/// container to open, or `None` to close one.
+struct ContainerMarkers {
+    /// The markers and the runs of text between them, in order.
+    parts: Vec<ContainerPart>,
+    /// The number of events in the paragraph, including its end tag.
+    len: usize,
+}
+
+/// A line of a paragraph with container markers in it, or the lines of text
+/// between markers.
+enum ContainerPart {
+    Marker(Option<Container>),
+    /// Lines of text, as indices into the paragraph's events.
+    Text(Range<usize>),
+}
+
+/// If a line of `events` is a `:::name` container marker, returns the
+/// container it opens, or `None` for a bare `:::`. Emphasis and code in the
+/// line are written back out as Markdown, for the container's title.
+fn container_marker(events: &[Event<'_>]) -> Option<Option<Container>> {
+    let mut line = String::new();
+    for event in events {
+        match event {
+            Event::Text(text) => line.push_str(text),
+            Event::Code(code) => {
+                let ticks = if code.contains('`') { "`` " } else { "`" };
+                line.push_str(ticks);
+                line.push_str(code);
+                line.extend(ticks.chars().rev());
+            }
+            Event::Start(Tag::Strong) | Event::End(Tag::Strong) => line.push_str("**"),
+            Event::Start(Tag::Emphasis) | Event::End(Tag::Emphasis) => line.push('*'),
+            Event::Start(Tag::Strikethrough) | Event::End(Tag::Strikethrough) => {
+                line.push_str("~~")
+            }
+            _ => return None,
+        }
+    }
+    container::parse_marker(&line)
+}
+
+struct Slideshow<'a> {
+    events: vec::IntoIter<Event<'a>>,
+    slide_delimiters: Vec<SlideDelimiter>,
+    slide_on: Option<HeadingLevel>,
+    all_fragments: bool,
+    nested_fragments: bool,
+    keep_empty_slides: bool,
+    include_drafts: bool,
+    figures: bool,
+    mermaid_cli: bool,
+    emoji: bool,
+    keep_comments: bool,
+    mark: bool,
+    task_checkboxes: bool,
+    code_line_numbers: bool,
+    external_blank: bool,
+    base_url: Option<String>,
+    abbreviations: Abbreviations,
+    highlighter: Option<&'a Highlighter>,
+    diagnostics: Diagnostics,
+    /// The slides collected so far.
+    slides: Vec<Slide<'a>>,
+    /// The current slide, which is held until it ends so that directives
+    /// inside it can change its `<section>` tag.
+    slide: Slide<'a>,
+    /// Has the current slide emitted anything visible yet?
+    has_content: bool,
+    /// Should the current slide's list items be revealed one at a time?
+    fragments: bool,
+    /// Are we inside a code block that pulldown-cmark will render?
+    in_code_block: bool,
+    /// Are we inside an image's alt text?
+    in_image: bool,
+    /// Are we inside a paragraph, heading, or table cell, where HTML is
+    /// inline?
+    in_text: bool,
+    /// Depth of nested lists we're inside of.
+    list_depth: u32,
+    /// Depth of nested block quotes and footnote definitions we're inside of,
+    /// where list items are never made into fragments.
+    quote_depth: u32,
+    /// The number of the current slide in the source, counting empty ones.
+    slide_number: u32,
+    /// The number of the current vertical stack, which only `<!-- vertical
+    /// -->` directives start a slide without changing.
+    stack: u32,
+    /// Slide IDs used so far.
+    slugs: Slugs,
+    /// An HTML comment split across several `Event::Html`s, waiting for its
+    /// closing `-->`.
+    comment: Option<String>,
+    /// The contents of a ```` ```notes ```` code block we're inside of.
+    notes_block: Option<String>,
+    /// The info string and contents of a fenced code block we're inside of
+    /// and will render ourselves when it ends.
+    code_block: Option<(CodeInfo, String)>,
+    /// The caption of the `<figure>` we're inside of.
+    figure: Option<String>,
+    /// The label of the footnote definition we're inside of, where its events
+    /// start in the current slide, and whether the slide had content before
+    /// it.
+    footnote: Option<(String, usize, bool)>,
+    /// Footnote definitions, by lowercased label, to be moved to the slides
+    /// that first reference them.
+    footnotes: HashMap<String, (String, Vec<Event<'a>>)>,
+    raw_html: RawHtml,
+    /// How many pieces of raw HTML have been dropped.
+    dropped_html: usize,
+    /// The `:::name` containers we're inside of.
+    containers: Vec<Container>,
+    /// The first error found in the document.
+    error: Option<RenderError>,
+}
+
+impl<'a> Slideshow<'a> {
+    fn new(
+        events: Vec<Event<'a>>,
+        render_options: &RenderOptions,
+        abbreviations: Abbreviations,
+        highlighter: Option<&'a Highlighter>,
+    ) -> Self {
+        let mut ret = Self {
+            events: events.into_iter(),
+            slide_delimiters: render_options.slide_delimiters.clone(),
+            slide_on: render_options.slide_on,
+            all_fragments: render_options.fragments,
+            nested_fragments: render_options.nested_fragments,
+            keep_empty_slides: render_options.keep_empty_slides,
+            include_drafts: render_options.include_drafts,
+            figures: render_options.figures,
+            mermaid_cli: render_options.mermaid_cli,
+            emoji: render_options.emoji,
+            keep_comments: render_options.keep_comments,
+            mark: render_options.mark,
+            task_checkboxes: render_options.task_checkboxes,
+            code_line_numbers: render_options.code_line_numbers,
+            external_blank: render_options.external_blank,
+            base_url: render_options.base_url.clone(),
+            abbreviations,
+            highlighter,
+            diagnostics: render_options.diagnostics.clone(),
+            slides: Vec::new(),
+            slide: Slide::default(),
+            has_content: false,
+            fragments: false,
+            in_code_block: false,
+            in_image: false,
+            in_text: false,
+            list_depth: 0,
+            quote_depth: 0,
+            slide_number: 0,
+            stack: 0,
+            slugs: Slugs::default(),
+            comment: None,
+            notes_block: None,
+            code_block: None,
+            figure: None,
+            footnote: None,
+            footnotes: HashMap::new(),
+            raw_html: render_options.raw_html,
+            dropped_html: 0,
+            containers: Vec::new(),
+            error: None,
+        };
+        ret.start_slide();
+        ret
+    }
+
+    /// Reads the whole document, returning its slides.
+    fn into_slides(mut self) -> Result<Vec<Slide<'a>>, RenderError> {
+        while let Some(event) = self.events.next() {
+            self.transform(event);
+        }
+        if let Some(comment) = self.comment.take() {
+            self.push_raw_html(comment.into());
+        }
+        if self.dropped_html > 0 {
+            self.diagnostics.warn(format_args!(
+                "Dropped {} pieces of raw HTML",
+                self.dropped_html
+            ));
+        }
+        self.end_slide();
+        self.place_footnotes();
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.slides),
+        }
+    }
+
+    /// Moves each footnote definition to the end of the first slide
+    /// referencing it.
+    fn place_footnotes(&mut self) {
+        for slide in &mut self.slides {
+            let mut footnotes = Vec::new();
+            for event in &slide.events {
+                if let Event::FootnoteReference(label) = event {
+                    if let Some((_, events)) = self.footnotes.remove(&label.to_lowercase()) {
+                        footnotes.extend(events);
+                    }
+                }
+            }
+            if !footnotes.is_empty() {
+                slide
+                    .events
+                    .push(Event::Html("<div class=\"footnotes\">\n".into()));
+                slide.events.extend(footnotes);
+                slide.events.push(Event::Html("</div>\n".into()));
+            }
+        }
+        for (label, _) in self.footnotes.values() {
+            self.diagnostics.warn(format_args!(
+                "Dropping footnote {:?}, which is never referenced",
+                label
+            ));
+        }
+    }
+
+    fn start_slide(&mut self) {
+        self.slide_number += 1;
+        self.stack += 1;
+        self.has_content = false;
+        self.fragments = self.all_fragments;
+    }
+
+    fn end_slide(&mut self) {
+        while let Some(container) = self.containers.pop() {
+            self.diagnostics.warn(format_args!(
+                "Slide {}: `:::{}` is never closed; closing it at the end of the slide",
+                self.slide_number, container.name
+            ));
+            self.slide
+                .events
+                .push(Event::Html(container.close_tag().into()));
+        }
+        let mut slide = mem::take(&mut self.slide);
+        if !self.has_content && !self.keep_empty_slides {
+            event!(
+                Level::DEBUG,
+                slide = self.slide_number,
+                "dropping empty slide"
+            );
+            // Any notes carry over to the next slide.
+            self.slide.notes = slide.notes;
+            return;
+        }
+        // `<!-- .slide: hidden -->` marks a draft too, but would hide the
+        // slide even when drafts are included.
+        let hidden = slide.attributes.other.len();
+        slide.attributes.other.retain(|(key, _)| key != "hidden");
+        slide.draft |= slide.attributes.other.len() != hidden;
+        if slide.draft {
+            if !self.include_drafts {
+                event!(
+                    Level::DEBUG,
+                    slide = self.slide_number,
+                    "dropping draft slide"
+                );
+                return;
+            }
+            slide.attributes.classes.push("draft".to_owned());
+        }
+        slide.attributes.id = Some(self.slide_id(&mut slide));
+        slide.stack = self.stack;
+        self.slides.push(slide);
+    }
+
+    /// The current slide's ID: one given by a directive, or else a slug of
+    /// its first heading, or else its number.
+    fn slide_id(&mut self, slide: &mut Slide<'a>) -> String {
+        if let Some(id) = slide.attributes.id.take() {
+            if !self.slugs.insert(id.clone()) {
+                self.diagnostics.warn(format_args!(
+                    "Slide {}: Duplicate slide ID {:?}",
+                    self.slide_number, id
+                ));
+            }
+            return id;
+        }
+        let heading_id = slide
+            .heading_attributes
+            .get_mut(&0)
+            .and_then(|attributes| attributes.id.take());
+        if let Some(id) = heading_id {
+            return self.slugs.unique(id);
+        }
+        let slug = first_heading(&slide.events)
+            .map(|(_, heading)| slugify(&heading))
+            .filter(|slug| !slug.is_empty())
+            .unwrap_or_else(|| format!("slide-{}", self.slides.len() + 1));
+        self.slugs.unique(slug)
+    }
+
+    /// Pushes the start of a heading, taking any `{#id .class}` attribute
+    /// group off the end of its text.
+    fn push_heading(&mut self, event: Event<'a>) {
+        self.has_content = true;
+        if let Some(attributes) = self.take_heading_attributes() {
+            let heading = self
+                .slide
+                .events
+                .iter()
+                .filter(|event| matches!(event, Event::Start(Tag::Heading(..))))
+                .count();
+            self.slide.heading_attributes.insert(heading, attributes);
+        }
+        self.slide.events.push(event);
+    }
+
+    /// Parses and removes the attribute group at the end of the heading about
+    /// to be read, if it has a well-formed one. Groups in code spans are left
+    /// alone, since they aren't in `Event::Text`s.
+    fn take_heading_attributes(&mut self) -> Option<Attributes> {
+        let events = self.events.as_mut_slice();
+        let end = events
+            .iter()
+            .position(|event| matches!(event, Event::End(Tag::Heading(..))))?;
+        let text = match events[..end].last_mut()? {
+            Event::Text(text) => text,
+            _ => return None,
+        };
+        let trimmed = text.trim_end();
+        let group = trimmed.strip_suffix('}')?;
+        let start = group.rfind('{')?;
+        let attributes = Attributes::parse_braced(&group[start + 1..]).ok()?;
+        *text = trimmed[..start].trim_end().to_owned().into();
+        Some(attributes)
+    }
+
+    /// Finds the lines of `:::name` container markers at the start and end of
+    /// the paragraph about to be read, if there are any.
+    fn container_markers(&self) -> Option<ContainerMarkers> {
+        let events = self.events.as_slice();
+        let len = 1 + events
+            .iter()
+            .position(|event| matches!(event, Event::End(Tag::Paragraph)))?;
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for (i, event) in events[..len].iter().enumerate() {
+            if matches!(event, Event::SoftBreak | Event::End(Tag::Paragraph)) {
+                lines.push(start..i);
+                start = i + 1;
+            }
+        }
+        let mut parts = Vec::new();
+        let mut has_marker = false;
+        for line in lines {
+            match container_marker(&events[line.clone()]) {
+                Some(marker) => {
+                    has_marker = true;
+                    parts.push(ContainerPart::Marker(marker));
+                }
+                // Lines of text are joined up with the ones before them.
+                None => match parts.last_mut() {
+                    Some(ContainerPart::Text(text)) => text.end = line.end,
+                    _ => parts.push(ContainerPart::Text(line)),
+                },
+            }
+        }
+        if !has_marker {
+            return None;
+        }
+        Some(ContainerMarkers { parts, len })
+    }
+
+    fn container_marker(&mut self, container: Option<Container>) {
+        self.has_content = true;
+        match container {
+            Some(container) => {
+                self.slide
+                    .events
+                    .push(Event::Html(container.open_tag().into()));
+                self.containers.push(container);
+            }
+            None => match self.containers.pop() {
+                Some(container) => self
+                    .slide
+                    .events
+                    .push(Event::Html(container.close_tag().into())),
+                None => self.diagnostics.warn(format_args!(
+                    "Slide {}: Ignoring `:::` outside of any container",
+                    self.slide_number
+                )),
+            },
+        }
+    }
+
+    /// The text of the events about to be read, up to the first one
+    /// `is_end` matches.
+    fn peek_text(&self, is_end: impl Fn(&Event<'a>) -> bool) -> String {
+        let mut text = String::new();
+        for event in self.events.as_slice() {
+            match event {
+                event if is_end(event) => break,
+                Event::Text(t) | Event::Code(t) => text.push_str(t),
+                Event::SoftBreak | Event::HardBreak => text.push(' '),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// If the paragraph about to be read consists of a single image with a
+    /// title, and maybe its attribute group, returns the title.
+    fn figure_caption(&self) -> Option<String> {
+        let events = self.events.as_slice();
+        let title = match events.first()? {
+            Event::Start(Tag::Image(_, _, title)) if !title.is_empty() => title,
+            _ => return None,
+        };
+        let alt_len = events
+            .iter()
+            .position(|event| matches!(event, Event::End(Tag::Image(..))))?;
+        let mut text = String::new();
+        for event in &events[alt_len + 1..] {
+            match event {
+                Event::Text(t) => text.push_str(t),
+                Event::End(Tag::Paragraph) => break,
+                _ => return None,
+            }
+        }
+        let is_group = text.starts_with('{') && text.find('}') == Some(text.len() - 1);
+        if text.is_empty() || is_group {
+            Some(title.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// If the image about to be read is followed by a `{...}` attribute group,
+    /// reads up to the end of the group and returns the image's alt text, its
+    /// attributes, and the text after the group.
+    fn take_image_attributes(&mut self) -> Option<(Vec<Event<'a>>, Attributes, String)> {
+        let events = self.events.as_slice();
+        let alt_len = events
+            .iter()
+            .position(|event| matches!(event, Event::End(Tag::Image(..))))?;
+        let mut text = String::new();
+        let mut text_len = 0;
+        for event in &events[alt_len + 1..] {
+            match event {
+                Event::Text(t) if !text.contains('}') => {
+                    text.push_str(t);
+                    text_len += 1;
+                }
+                _ => break,
+            }
+        }
+        let group = text.strip_prefix('{')?;
+        let end = group.find('}')?;
+        let (group, rest) = (&group[..end], &group[end + 1..]);
+
+        let attributes = Attributes::parse_braced(group).unwrap_or_else(|err| {
+            self.diagnostics.warn(format_args!(
+                "Slide {}: Ignoring malformed image attributes {:?}: {}",
+                self.slide_number, group, err
+            ));
+            Attributes::default()
+        });
+        let rest = rest.to_owned();
+        let alt = self.events.by_ref().take(alt_len).collect();
+        // Skip the end of the image and the text holding the group.
+        self.events.nth(text_len);
+        Some((alt, attributes, rest))
+    }
+
+    /// Is the image about to be read a QR code, with the alt text `qr`?
+    fn is_qr_code(&self) -> bool {
+        matches!(
+            self.events.as_slice(),
+            [Event::Text(alt), Event::End(Tag::Image(..)), ..] if alt.eq_ignore_ascii_case("qr")
+        )
+    }
+
+    /// Handles `==marked==` text opened in `text`, which may be closed in a
+    /// later text event at the same level of inline nesting. Returns `false`
+    /// if there isn't any.
+    fn mark(&mut self, text: &str) -> bool {
+        let start = match text.find("==") {
+            Some(start) => start,
+            None => return false,
+        };
+        let (before, after) = (&text[..start], &text[start + 2..]);
+        if let Some(end) = after.find("==").filter(|&end| end > 0) {
+            self.transform(Event::Text(before.to_owned().into()));
+            self.has_content = true;
+            self.slide.events.push(Event::Html("<mark>".into()));
+            self.transform(Event::Text(after[..end].to_owned().into()));
+            self.slide.events.push(Event::Html("</mark>".into()));
+            self.transform(Event::Text(after[end + 2..].to_owned().into()));
+            return true;
+        }
+        if after.contains("==") {
+            return false;
+        }
+        let mut depth = 0;
+        let mut close = None;
+        for (i, event) in self.events.as_slice().iter().enumerate() {
+            match event {
+                Event::Text(text) if depth == 0 => {
+                    if let Some(end) = text.find("==") {
+                        close = Some((i, end));
+                        break;
+                    }
+                }
+                Event::Text(_) | Event::SoftBreak | Event::HardBreak => {}
+                Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => {
+                    depth += 1
+                }
+                Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                // Don't match across code spans or out of the block.
+                _ => break,
+            }
+        }
+        let (len, end) = match close {
+            Some(close) => close,
+            None => return false,
+        };
+        let between: Vec<_> = self.events.by_ref().take(len).collect();
+        let closing = match self.events.next() {
+            Some(Event::Text(text)) => text,
+            _ => unreachable!("The closing `==` is in a text event"),
+        };
+        self.transform(Event::Text(before.to_owned().into()));
+        self.has_content = true;
+        self.slide.events.push(Event::Html("<mark>".into()));
+        self.transform(Event::Text(after.to_owned().into()));
+        // Marks can't nest, and any `==` inside would look past this one.
+        self.mark = false;
+        for event in between {
+            self.transform(event);
+        }
+        self.mark = true;
+        self.transform(Event::Text(closing[..end].to_owned().into()));
+        self.slide.events.push(Event::Html("</mark>".into()));
+        self.transform(Event::Text(closing[end + 2..].to_owned().into()));
+        true
+    }
+
+    /// Are we inside a code block, whose text is left alone?
+    fn in_code(&self) -> bool {
+        self.in_code_block || self.code_block.is_some() || self.notes_block.is_some()
+    }
+
+    fn is_fragment_item(&self) -> bool {
+        self.fragments && self.quote_depth == 0 && (self.list_depth == 1 || self.nested_fragments)
+    }
+
+    /// `dest`, prefixed with the base URL if it's relative.
+    fn with_base_url(&self, dest: CowStr<'a>) -> CowStr<'a> {
+        match &self.base_url {
+            Some(base_url) => assets::with_base_url(&dest, base_url).map_or(dest, CowStr::from),
+            None => dest,
+        }
+    }
+
+    /// If the list item about to be read starts with a task list checkbox,
+    /// whether it's checked.
+    fn task_item(&self) -> Option<bool> {
+        match self.events.as_slice() {
+            [Event::TaskListMarker(checked), ..]
+            | [Event::Start(Tag::Paragraph), Event::TaskListMarker(checked), ..] => Some(*checked),
+            _ => None,
+        }
+    }
+
+    /// Classes for the `<li>` of the list item about to be read.
+    fn item_classes(&self) -> Vec<&'static str> {
+        let mut classes = Vec::new();
+        if self.is_fragment_item() {
+            classes.push("fragment");
+        }
+        match self.task_item().filter(|_| !self.task_checkboxes) {
+            Some(true) => classes.extend(&["task", "done"]),
+            Some(false) => classes.extend(&["task", "todo"]),
+            None => {}
+        }
+        classes
+    }
+
+    /// Are we outside of every list, block quote, and footnote definition,
+    /// where slides can be split?
+    fn at_top_level(&self) -> bool {
+        self.list_depth == 0 && self.quote_depth == 0
+    }
+
+    /// Can a directive split the slide here, at the top level and outside of
+    /// any text?
+    fn can_split(&self) -> bool {
+        self.at_top_level() && !self.in_text
+    }
+
+    fn track_containers(&mut self, event: &Event<'a>) {
+        match event {
+            Event::Start(Tag::List(_)) => self.list_depth += 1,
+            Event::End(Tag::List(_)) => self.list_depth -= 1,
+            Event::Start(Tag::CodeBlock(_)) => self.in_code_block = true,
+            Event::End(Tag::Image(..)) => self.in_image = false,
+            Event::End(Tag::CodeBlock(_)) => self.in_code_block = false,
+            Event::Start(Tag::BlockQuote) | Event::Start(Tag::FootnoteDefinition(_)) => {
+                self.quote_depth += 1
+            }
+            Event::End(Tag::BlockQuote) | Event::End(Tag::FootnoteDefinition(_)) => {
+                self.quote_depth -= 1
+            }
+            _ => {}
+        }
+    }
+
+    fn code_block(&mut self, info: &CodeInfo, code: &str) {
+        self.has_content = true;
+        self.slide.code_lines += code.lines().count();
+        if info.lang == "mermaid" {
+            let html = if self.mermaid_cli {
+                mermaid::svg(code).unwrap_or_else(|err| {
+                    self.error.get_or_insert(RenderError::Mermaid {
+                        slide: self.slide_number,
+                        err,
+                    });
+                    String::new()
+                })
+            } else {
+                mermaid::div(code)
+            };
+            self.slide.events.push(Event::Html(html.into()));
+            return;
+        }
+        self.slide.code_blocks += 1;
+        match code::render(self.highlighter, info, code, &self.diagnostics) {
+            Ok(html) => self.slide.events.push(Event::Html(html.into())),
+            Err(err) => {
+                self.diagnostics.warn(format_args!(
+                    "Slide {}: Couldn't highlight {} code: {}",
+                    self.slide_number, info.lang, err
+                ));
+                let tag = Tag::CodeBlock(CodeBlockKind::Fenced(info.full_lang().into()));
+                self.slide.events.push(Event::Start(tag.clone()));
+                self.slide.events.push(Event::Text(code.to_owned().into()));
+                self.slide.events.push(Event::End(tag));
+            }
+        }
+    }
+
+    /// Pushes raw HTML from the Markdown, as `RenderOptions::raw_html` says.
+    fn push_raw_html(&mut self, html: CowStr<'a>) {
+        match self.raw_html {
+            RawHtml::Keep => self.slide.events.push(Event::Html(html)),
+            RawHtml::Sanitize => {
+                let html = sanitize::mark(&html);
+                self.slide.events.push(Event::Html(html.into()));
+            }
+            RawHtml::Drop => self.dropped_html += 1,
+        }
+    }
+
+    fn is_delimiter_comment(&self, body: &str) -> bool {
+        self.slide_delimiters.iter().any(
+            |delimiter| matches!(delimiter, SlideDelimiter::Comment(comment) if comment == body),
+        )
+    }
+
+    /// If the paragraph about to be read is plain text matching a delimiter
+    /// pattern, returns the number of events making it up.
+    fn delimiter_paragraph(&self) -> Option<usize> {
+        let mut text = String::new();
+        for (i, event) in self.events.as_slice().iter().enumerate() {
+            match event {
+                Event::Text(t) => text.push_str(t),
+                Event::SoftBreak | Event::HardBreak => text.push('\n'),
+                Event::End(Tag::Paragraph) => {
+                    let is_delimiter = self.slide_delimiters.iter().any(|delimiter| {
+                        matches!(delimiter, SlideDelimiter::Pattern(regex) if regex.is_match(&text))
+                    });
+                    return Some(i + 1).filter(|_| is_delimiter);
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    /// Handles a complete HTML comment, which may be a directive.
+    fn comment(&mut self, html: String) {
+        match comment_body(&html) {
+            Some(body) if self.is_delimiter_comment(body) && self.can_split() => {
+                self.end_slide();
+                self.start_slide();
+            }
+            Some(body) if body.starts_with("notes:") => {
+                event!(Level::DEBUG, slide = self.slide_number, "speaker notes");
+                self.slide
+                    .notes
+                    .push(body["notes:".len()..].trim().to_owned());
+            }
+            Some("vertical") if self.can_split() => {
+                self.end_slide();
+                let stack = self.stack;
+                self.start_slide();
+                self.stack = stack;
+            }
+            Some("fragments") => {
+                self.fragments = true;
+            }
+            Some("draft") => {
+                self.slide.draft = true;
+            }
+            Some(body) if body == "toc" || body.starts_with("toc ") => {
+                let depth = match body["toc".len()..].trim() {
+                    "" => Ok(None),
+                    arg => arg
+                        .strip_prefix("depth=")
+                        .and_then(|depth| depth.parse().ok())
+                        .map(Some)
+                        .ok_or(arg),
+                };
+                match depth {
+                    Ok(depth) => {
+                        self.has_content = true;
+                        let toc = Toc {
+                            index: self.slide.events.len(),
+                            depth,
+                        };
+                        self.slide.tocs.push(toc);
+                    }
+                    Err(arg) => {
+                        self.diagnostics.warn(format_args!(
+                            "Slide {}: Expected `depth=N` in table of contents directive, not {:?}",
+                            self.slide_number, arg
+                        ));
+                        self.plain_comment(html);
+                    }
+                }
+            }
+            Some(body) if body.starts_with(".slide:") => {
+                match Attributes::parse_html(&body[".slide:".len()..]) {
+                    Ok(attributes) => self.slide.attributes.merge(attributes),
+                    Err(err) => {
+                        self.diagnostics.warn(format_args!(
+                            "Slide {}: Malformed slide directive {:?}: {}",
+                            self.slide_number, body, err
+                        ));
+                        self.plain_comment(html);
+                    }
+                }
+            }
+            _ => self.plain_comment(html),
+        }
+    }
+
+    /// Handles an HTML comment which isn't a directive, dropping it unless
+    /// comments are kept.
+    fn plain_comment(&mut self, html: String) {
+        if self.keep_comments || comment_body(&html).is_none() {
+            self.push_raw_html(html.into());
+        } else {
+            event!(Level::DEBUG, slide = self.slide_number, "dropping comment");
+        }
+    }
+
+    fn transform(&mut self, mut event: Event<'a>) {
+        if let Event::Text(text) | Event::Html(text) = &event {
+            if math::is_protected(text) {
+                if let (Event::Text(_), false) = (&event, self.in_code()) {
+                    for event in math::render(text, &self.diagnostics) {
+                        match event {
+                            Event::Html(_) => {
+                                self.has_content = true;
+                                self.slide.events.push(event);
+                            }
+                            _ => self.transform(event),
+                        }
+                    }
+                    return;
+                }
+                let text = math::restore(text).into();
+                event = match event {
+                    Event::Html(_) => Event::Html(text),
+                    _ => Event::Text(text),
+                };
+            }
+        }
+        if let Event::Text(text) | Event::Html(text) = &event {
+            if kbd::is_protected(text) {
+                if let (Event::Text(_), false) = (&event, self.in_code()) {
+                    for event in kbd::render(text) {
+                        match event {
+                            Event::Html(_) => {
+                                self.has_content = true;
+                                self.slide.events.push(event);
+                            }
+                            _ => self.transform(event),
+                        }
+                    }
+                    return;
+                }
+                let text = kbd::restore(text).into();
+                event = match event {
+                    Event::Html(_) => Event::Html(text),
+                    _ => Event::Text(text),
+                };
+            }
+        }
+        if let Event::Text(text) = &event {
+            if self.emoji && !self.in_code() {
+                if let Some(text) = emoji::replace(text) {
+                    event = Event::Text(text.into());
+                }
+            }
+        }
+        if let Event::Text(text) = &event {
+            if !self.abbreviations.is_empty() && !self.in_code() && !self.in_image {
+                if let Some(pieces) = self.abbreviations.pieces(text) {
+                    for (title, text) in pieces {
+                        match title {
+                            Some(title) => {
+                                self.has_content = true;
+                                self.slide.events.extend(abbr::events(text, &title));
+                            }
+                            None => self.transform(Event::Text(text.into())),
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+        if let Event::Text(text) = &event {
+            if self.mark && !self.in_code() && self.mark(&text.clone()) {
+                return;
+            }
+        }
+
+        if let Some(comment) = &mut self.comment {
+            if let Event::Html(html) = &event {
+                comment.push_str(html);
+                if html.contains("-->") {
+                    let comment = self.comment.take().unwrap();
+                    self.comment(comment);
+                }
+                return;
+            }
+            // The comment was never closed; emit it as-is.
+            let comment = self.comment.take().unwrap();
+            self.push_raw_html(comment.into());
+        }
+
+        if let Some(notes) = &mut self.notes_block {
+            match event {
+                Event::End(Tag::CodeBlock(_)) => {
+                    let notes = self.notes_block.take().unwrap();
+                    self.slide.notes.push(notes);
+                }
+                Event::Text(text) => notes.push_str(&text),
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some((_, code)) = &mut self.code_block {
+            match event {
+                Event::End(Tag::CodeBlock(_)) => {
+                    let (info, code) = self.code_block.take().unwrap();
+                    self.code_block(&info, &code);
+                }
+                Event::Text(text) => code.push_str(&text),
+                _ => {}
+            }
+            return;
+        }
+
+        if let Event::Start(Tag::Paragraph) = event {
+            if let Some(len) = self.delimiter_paragraph().filter(|_| self.at_top_level()) {
+                self.events.nth(len - 1);
+                self.end_slide();
+                self.start_slide();
+                return;
+            }
+            if let Some(markers) = self.container_markers() {
+                let paragraph: Vec<_> = self.events.by_ref().take(markers.len).collect();
+                for part in markers.parts {
+                    match part {
+                        ContainerPart::Marker(container) => self.container_marker(container),
+                        ContainerPart::Text(text) if !text.is_empty() => {
+                            self.slide.events.push(Event::Start(Tag::Paragraph));
+                            self.in_text = true;
+                            for event in paragraph[text].iter().cloned() {
+                                self.transform(event);
+                            }
+                            self.in_text = false;
+                            self.slide.events.push(Event::End(Tag::Paragraph));
+                        }
+                        ContainerPart::Text(_) => {}
+                    }
+                }
+                return;
+            }
+            if let Some(caption) = self.figure_caption().filter(|_| self.figures) {
+                self.has_content = true;
+                self.slide.events.push(Event::Html("<figure>".into()));
+                self.figure = Some(caption);
+                return;
+            }
+        }
+
+        match event {
+            Event::Start(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => self.in_text = true,
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => self.in_text = false,
+            _ => {}
+        }
+        match event {
+            Event::Rule
+                if self.at_top_level()
+                    && self
+                        .slide_delimiters
+                        .iter()
+                        .any(|delimiter| matches!(delimiter, SlideDelimiter::Rule)) =>
+            {
+                self.end_slide();
+                self.start_slide();
+            }
+            Event::Html(html) if html.trim_start().starts_with("<!--") => {
+                if html.contains("-->") {
+                    self.comment(html.into_string());
+                } else {
+                    self.comment = Some(html.into_string());
+                }
+            }
+            Event::Html(html) => {
+                self.has_content = true;
+                self.push_raw_html(html);
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if lang.trim() == "notes" => {
+                self.notes_block = Some(String::new());
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let mut info = CodeInfo::parse(&info, &self.diagnostics);
+                if self.code_line_numbers && info.lang != "mermaid" {
+                    info.line_numbers.get_or_insert(1);
+                }
+                self.has_content = true;
+                if info.is_annotated()
+                    || info.lang == "mermaid"
+                    || (self.highlighter.is_some() && !info.lang.is_empty())
+                {
+                    self.code_block = Some((info, String::new()));
+                } else {
+                    self.in_code_block = true;
+                    self.slide
+                        .events
+                        .push(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(
+                            info.lang.into(),
+                        ))));
+                }
+            }
+            Event::Start(Tag::Heading(level, _, _))
+                if self.has_content
+                    && self.at_top_level()
+                    && self
+                        .slide_on
+                        .is_some_and(|slide_on| level as u32 <= slide_on.0) =>
+            {
+                self.end_slide();
+                self.start_slide();
+                self.push_heading(event);
+            }
+            Event::Start(Tag::Heading(..)) => self.push_heading(event),
+            Event::End(Tag::Paragraph) if self.figure.is_some() => {
+                let caption = self.figure.take().unwrap();
+                let html = format!(
+                    "<figcaption>{}</figcaption></figure>\n",
+                    html_escape(&caption)
+                );
+                self.slide.events.push(Event::Html(html.into()));
+            }
+            Event::Start(Tag::FootnoteDefinition(ref label)) => {
+                self.footnote =
+                    Some((label.to_string(), self.slide.events.len(), self.has_content));
+                self.track_containers(&event);
+                self.slide.events.push(event);
+            }
+            Event::End(Tag::FootnoteDefinition(_)) if self.footnote.is_some() => {
+                let (label, start, has_content) = self.footnote.take().unwrap();
+                self.track_containers(&event);
+                self.slide.events.push(event);
+                let events = self.slide.events.split_off(start);
+                self.has_content = has_content;
+                self.footnotes
+                    .entry(label.to_lowercase())
+                    .or_insert((label, events));
+            }
+            Event::Start(Tag::Item) => {
+                self.has_content = true;
+                let classes = self.item_classes();
+                if classes.is_empty() {
+                    self.slide.events.push(event);
+                } else {
+                    let html = format!(r#"<li class="{}">"#, classes.join(" "));
+                    self.slide.events.push(Event::Html(html.into()));
+                }
+            }
+            Event::TaskListMarker(_) if !self.task_checkboxes => {}
+            Event::Start(Tag::Image(_, dest, _)) if self.is_qr_code() => {
+                self.slide.links.push(dest.to_string());
+                self.has_content = true;
+                let (attributes, rest) = match self.take_image_attributes() {
+                    Some((_, attributes, rest)) => (attributes, rest),
+                    None => {
+                        // Skip the alt text and the end of the image.
+                        self.events.nth(1);
+                        (Attributes::default(), String::new())
+                    }
+                };
+                let size = attributes
+                    .other
+                    .iter()
+                    .find(|(key, _)| key == "size")
+                    .and_then(|(_, size)| size.trim_end_matches("px").parse().ok())
+                    .unwrap_or(qr::DEFAULT_SIZE);
+                match qr::html(&dest, size) {
+                    Ok(html) => self.slide.events.push(Event::Html(html.into())),
+                    Err(err) => {
+                        self.error.get_or_insert(RenderError::QrCode {
+                            url: dest.to_string(),
+                            slide: self.slide_number,
+                            err,
+                        });
+                    }
+                }
+                if !rest.is_empty() {
+                    self.slide.events.push(Event::Text(rest.into()));
+                }
+            }
+            Event::Start(Tag::Image(_, dest, _)) if Video::parse(&dest).is_some() => {
+                self.has_content = true;
+                let alt = self.peek_text(|event| matches!(event, Event::End(Tag::Image(..))));
+                self.slide.image_alts.push(alt);
+                let video = Video::parse(&dest).unwrap();
+                let (alt, attributes, rest) = match self.take_image_attributes() {
+                    Some(taken) => taken,
+                    None => {
+                        let alt = self
+                            .events
+                            .by_ref()
+                            .take_while(|event| !matches!(event, Event::End(Tag::Image(..))))
+                            .collect();
+                        (alt, Attributes::default(), String::new())
+                    }
+                };
+                let html = match video {
+                    Video::File(src) => {
+                        self.slide.images.push(src.to_owned());
+                        let src = self.with_base_url(src.to_owned().into());
+                        Video::File(&src).html(&alt, attributes)
+                    }
+                    youtube => youtube.html(&alt, attributes),
+                };
+                self.slide.events.push(Event::Html(html.into()));
+                if !rest.is_empty() {
+                    self.slide.events.push(Event::Text(rest.into()));
+                }
+            }
+            Event::Start(Tag::Image(link_type, dest, title)) => {
+                self.slide.images.push(dest.to_string());
+                let alt = self.peek_text(|event| matches!(event, Event::End(Tag::Image(..))));
+                self.slide.image_alts.push(alt);
+                self.has_content = true;
+                let dest = self.with_base_url(dest);
+                match self.take_image_attributes() {
+                    Some((alt, attributes, rest)) => {
+                        let html = image::img_tag(&dest, &title, &alt, attributes);
+                        self.slide.events.push(Event::Html(html.into()));
+                        if !rest.is_empty() {
+                            self.slide.events.push(Event::Text(rest.into()));
+                        }
+                    }
+                    None => {
+                        self.in_image = true;
+                        self.slide
+                            .events
+                            .push(Event::Start(Tag::Image(link_type, dest, title)))
+                    }
+                }
+            }
+            Event::Start(Tag::Link(link_type, dest, title)) => {
+                self.slide.links.push(dest.to_string());
+                let text = self.peek_text(|event| matches!(event, Event::End(Tag::Link(..))));
+                self.slide.link_texts.push(text);
+                self.has_content = true;
+                let dest = self.with_base_url(dest);
+                if self.external_blank && links::is_off_site(&dest, self.base_url.as_deref()) {
+                    let html = links::blank_link_tag(&dest, &title);
+                    self.slide.events.push(Event::Html(html.into()));
+                } else {
+                    self.slide
+                        .events
+                        .push(Event::Start(Tag::Link(link_type, dest, title)));
+                }
+            }
+            _ => {
+                self.track_containers(&event);
+                self.has_content = true;
+                self.slide.events.push(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn options() -> RenderOptions {
+        RenderOptions {
+            slide_delimiters: vec![SlideDelimiter::Rule],
+            vertical_delimiter: "----".to_owned(),
+            slide_on: None,
+            fragments: false,
+            nested_fragments: false,
+            keep_empty_slides: false,
+            include_drafts: false,
+            slides: None,
+            slide_header: None,
+            slide_footer: None,
+            data: None,
+            vars: Vec::new(),
+            reproducible: false,
+            git_metadata: false,
+            profiles: Vec::new(),
+            theme: "light".to_owned(),
+            number_headings: false,
+            title_slide: false,
+            partials: None,
+            strict_templates: false,
+            allow_env: None,
+            fingerprints: Manifest::new(),
+            figures: true,
+            mermaid_cli: false,
+            math: false,
+            emoji: false,
+            kbd: false,
+            task_checkboxes: false,
+            keep_comments: false,
+            raw_html: RawHtml::Keep,
+            extensions: Options::ENABLE_TABLES | Options::ENABLE_FOOTNOTES,
+            mark: false,
+            abbreviations: false,
+            external_blank: false,
+            base_url: None,
+            root: String::new(),
+            words_per_minute: 130,
+            max_words: 0,
+            max_code_lines: 0,
+            max_images: 0,
+            code_line_numbers: false,
+            highlight: None,
+            highlight_theme: String::new(),
+            static_dirs: Vec::new(),
+            lint: false,
+            check_links: false,
+            check_external_links: false,
+            filters: Vec::new(),
+            filter_timeout: Duration::from_secs(30),
+            diagnostics: Diagnostics::default(),
+        }
+    }
+
+    /// Renders `markdown` into the HTML of each of its slides.
+    pub fn slides(markdown: &str) -> Vec<String> {
+        slides_with(options(), markdown)
+    }
+
+    /// Renders `markdown` with `options` into the HTML of each of its slides.
+    pub fn slides_with(options: RenderOptions, markdown: &str) -> Vec<String> {
+        let events = Parser::new_ext(markdown, options.extensions).collect();
+        let slides = Slideshow::new(events, &options, Abbreviations::default(), None)
+            .into_slides()
+            .unwrap();
+        let count = slides.len();
+        slides
+            .into_iter()
+            .enumerate()
+            .map(|(i, slide)| {
+                let mut html = String::new();
+                slide.push_html(&mut html, i + 1, count, &options);
+                html
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_containers_without_blank_lines() {
+        let slides = slides(":::columns\n:::column\nleft\n:::\n:::column\nright\n:::\n:::");
+        assert!(slides[0].contains(
+            "<div class=\"columns\">\n\
+             <div class=\"column\">\n<p>left</p>\n</div>\n\
+             <div class=\"column\">\n<p>right</p>\n</div>\n\
+             </div>\n"
+        ));
+    }
+
+    #[test]
+    fn top_level_rule_splits_slides() {
+        assert_eq!(slides("one\n\n---\n\ntwo").len(), 2);
+    }
+
+    #[test]
+    fn rule_in_block_quote_is_kept() {
+        let slides = slides("> one\n>\n> ---\n>\n> two\n\nthree");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<blockquote>\n<p>one</p>\n<hr />\n<p>two</p>\n</blockquote>"));
+    }
+
+    #[test]
+    fn rule_in_nested_list_is_kept() {
+        let slides = slides("- one\n  - two\n\n    ---\n\n    three\n- four");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<hr />"));
+        assert!(slides[0].contains("four"));
+    }
+
+    #[test]
+    fn rule_in_footnote_definition_is_kept() {
+        let slides = slides("one[^note]\n\n[^note]: ---\n\ntwo");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<p>two</p>"));
+        assert!(slides[0].contains("<div class=\"footnote-definition\" id=\"note\"><sup class=\"footnote-definition-label\">1</sup>\n<hr />\n</div>"));
+    }
+
+    fn comment_delimiter() -> RenderOptions {
+        RenderOptions {
+            slide_delimiters: vec![SlideDelimiter::Comment("slide".to_owned())],
+            ..options()
+        }
+    }
+
+    #[test]
+    fn delimiter_comment_splits_slides() {
+        assert_eq!(
+            slides_with(comment_delimiter(), "a\n\n<!-- slide -->\n\nb").len(),
+            2
+        );
+    }
+
+    #[test]
+    fn delimiter_comment_in_paragraph_is_kept() {
+        let slides = slides_with(comment_delimiter(), "a <!-- slide --> b");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<p>a  b</p>"), "{}", slides[0]);
+    }
+
+    #[test]
+    fn delimiter_comment_in_block_quote_is_kept() {
+        let slides = slides_with(comment_delimiter(), "> a\n>\n> <!-- slide -->\n>\n> b");
+        assert_eq!(slides.len(), 1);
+        assert!(
+            slides[0].contains("<blockquote>\n<p>a</p>\n<p>b</p>\n</blockquote>"),
+            "{}",
+            slides[0]
+        );
+    }
+
+    #[test]
+    fn delimiter_pattern_in_block_quote_is_kept() {
+        let options = RenderOptions {
+            slide_delimiters: vec![SlideDelimiter::Pattern(Regex::new("^--$").unwrap())],
+            ..options()
+        };
+        assert_eq!(slides_with(options.clone(), "a\n\n--\n\nb").len(), 2);
+        let slides = slides_with(options, "> a\n>\n> --\n>\n> b");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<p>--</p>"), "{}", slides[0]);
+    }
+
+    #[test]
+    fn vertical_directive_in_heading_is_kept() {
+        assert_eq!(slides("# a\n\n<!-- vertical -->\n\n# b").len(), 2);
+        let slides = slides("# h <!-- vertical -->\n\ntext");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<h1>h </h1>"), "{}", slides[0]);
+        assert!(slides[0].contains("<p>text</p>"), "{}", slides[0]);
+    }
+
+    fn slide_on_h2() -> RenderOptions {
+        RenderOptions {
+            slide_on: Some(HeadingLevel(2)),
+            ..options()
+        }
+    }
+
+    #[test]
+    fn heading_in_block_quote_is_kept() {
+        let slides = slides_with(slide_on_h2(), "# a\n\n> ## b\n> text");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<blockquote>\n<h2"), "{}", slides[0]);
+        assert!(
+            slides[0].contains("<p>text</p>\n</blockquote>"),
+            "{}",
+            slides[0]
+        );
+    }
+
+    #[test]
+    fn heading_in_list_is_kept() {
+        let slides = slides_with(slide_on_h2(), "# a\n\n- ## b\n- text");
+        assert_eq!(slides.len(), 1);
+        assert!(slides[0].contains("<li>\n<h2"), "{}", slides[0]);
+        assert!(slides[0].contains("<li>text</li>\n</ul>"), "{}", slides[0]);
+    }
+
+    #[test]
+    fn toc_after_numbered_heading_starting_with_emphasis() {
+        let options = options();
+        let markdown = "# *Intro* words\n\n<!-- toc -->\n\n---\n\n# Next";
+        let events = Parser::new_ext(markdown, options.extensions).collect();
+        let mut slides = Slideshow::new(events, &options, Abbreviations::default(), None)
+            .into_slides()
+            .unwrap();
+        number_headings(&mut slides);
+        insert_tocs(&mut slides);
+        let mut html = String::new();
+        slides.remove(0).push_html(&mut html, 1, 2, &options);
+        assert!(
+            html.contains(
+                "1 <em>Intro</em> words</h1>\n<ul>\n<li><a href=\"#next\">2 Next</a></li>"
+            ),
+            "{}",
+            html
+        );
     }
 }
@@ -0,0 +1,214 @@
+//! `$inline$` and `$$display$$` LaTeX math, rendered with KaTeX at build time.
+//!
+//! Math is swapped out of the Markdown source for placeholders before it's
+//! parsed, so that Markdown doesn't eat its backslashes or turn its
+//! underscores into emphasis; the placeholders are then rendered wherever they
+//! turn up in the text.
+
+use std::fmt::Write;
+
+use pulldown_cmark::Event;
+
+use crate::diagnostics::Diagnostics;
+
+const INLINE: char = '\u{E000}';
+const DISPLAY: char = '\u{E001}';
+const END: char = '\u{E002}';
+
+/// Replaces the math in Markdown `source` with placeholders. Math in code
+/// spans and fenced code blocks is left alone, as are dollar signs escaped
+/// like `\$` and those without a closing `$` on the same line.
+pub fn protect(source: &str) -> String {
+    let mut ret = String::with_capacity(source.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+        if i == 0 || source.as_bytes()[i - 1] == b'\n' {
+            let line = &rest[..rest.find('\n').map_or(rest.len(), |end| end + 1)];
+            match (fence, fence_marker(line)) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if marker.starts_with(open) => fence = None,
+                _ => {}
+            }
+            if fence.is_some() || fence_marker(line).is_some() {
+                ret.push_str(line);
+                i += line.len();
+                continue;
+            }
+        }
+
+        let len = if let Some(escaped) = rest.strip_prefix('\\') {
+            1 + escaped.chars().next().map_or(0, char::len_utf8)
+        } else if rest.starts_with('`') {
+            let ticks = rest.len() - rest.trim_start_matches('`').len();
+            code_span_len(rest, ticks).unwrap_or(ticks)
+        } else if let Some(display) = rest.strip_prefix("$$") {
+            match display
+                .find("$$")
+                .filter(|&end| !display[..end].trim().is_empty())
+            {
+                Some(end) => {
+                    push_placeholder(&mut ret, DISPLAY, &display[..end]);
+                    i += end + 4;
+                    continue;
+                }
+                None => 2,
+            }
+        } else if let Some(inline) = rest.strip_prefix('$') {
+            match inline_math_len(inline) {
+                Some(end) => {
+                    push_placeholder(&mut ret, INLINE, &inline[..end]);
+                    i += end + 2;
+                    continue;
+                }
+                None => 1,
+            }
+        } else {
+            rest.chars().next().map_or(1, char::len_utf8)
+        };
+        ret.push_str(&rest[..len]);
+        i += len;
+    }
+    ret
+}
+
+/// If `line` opens or closes a fenced code block, the run of backticks or
+/// tildes doing so.
+pub fn fence_marker(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let marker = &trimmed[..trimmed.len() - trimmed.trim_start_matches(c).len()];
+    Some(marker).filter(|marker| marker.len() >= 3)
+}
+
+/// The length of the code span starting `s`, which opens with `ticks`
+/// backticks, if it's closed.
+pub fn code_span_len(s: &str, ticks: usize) -> Option<usize> {
+    let mut i = ticks;
+    while let Some(start) = s[i..].find('`') {
+        let start = i + start;
+        let run = s[start..].len() - s[start..].trim_start_matches('`').len();
+        if run == ticks {
+            return Some(start + run);
+        }
+        i = start + run;
+    }
+    None
+}
+
+/// The length of the inline math at the start of `s`, which follows an
+/// opening `$`. Like Pandoc, the opening `$` must be followed by a
+/// non-space, and the closing `$` must follow a non-space and not be
+/// followed by a digit, so amounts like "$5 and $10" aren't math.
+/// Inline math can't contain backticks.
+fn inline_math_len(s: &str) -> Option<usize> {
+    if s.starts_with(char::is_whitespace) || s.starts_with('$') {
+        return None;
+    }
+    let line = &s[..s.find('\n').unwrap_or(s.len())];
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            // Leave code spans to Markdown.
+            '`' => return None,
+            '$' if i > 0
+                && !line[..i].ends_with(char::is_whitespace)
+                && !line[i + 1..].starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                return Some(i);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Pushes a placeholder for `math`, hex-encoded so that Markdown leaves it
+/// alone.
+fn push_placeholder(s: &mut String, kind: char, math: &str) {
+    s.push(kind);
+    for byte in math.bytes() {
+        // Writing to a `String` can't fail.
+        write!(s, "{:02x}", byte).unwrap();
+    }
+    s.push(END);
+}
+
+/// Does `text` contain any math placeholders?
+pub fn is_protected(text: &str) -> bool {
+    text.contains(END)
+}
+
+/// Splits `text` into its math placeholders, as `Some(display)` and the math,
+/// and the text between them.
+fn pieces(text: &str) -> Vec<(Option<bool>, String)> {
+    let mut ret = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find([INLINE, DISPLAY]) {
+        let end = match rest[start..].find(END) {
+            Some(end) => start + end,
+            None => break,
+        };
+        if start > 0 {
+            ret.push((None, rest[..start].to_owned()));
+        }
+        let display = rest[start..].starts_with(DISPLAY);
+        let hex = &rest[start + INLINE.len_utf8()..end];
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .filter_map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect();
+        ret.push((Some(display), String::from_utf8(bytes).unwrap_or_default()));
+        rest = &rest[end + END.len_utf8()..];
+    }
+    if !rest.is_empty() {
+        ret.push((None, rest.to_owned()));
+    }
+    ret
+}
+
+fn delimited(display: bool, math: &str) -> String {
+    let delimiter = if display { "$$" } else { "$" };
+    format!("{}{}{}", delimiter, math, delimiter)
+}
+
+/// Puts the math back into `text`, where it shouldn't be rendered.
+pub fn restore(text: &str) -> String {
+    pieces(text)
+        .into_iter()
+        .map(|(display, text)| match display {
+            Some(display) => delimited(display, &text),
+            None => text,
+        })
+        .collect()
+}
+
+/// Renders the math in `text`, returning events for it and the text around
+/// it.
+pub fn render(text: &str, diagnostics: &Diagnostics) -> Vec<Event<'static>> {
+    pieces(text)
+        .into_iter()
+        .map(|(display, text)| match display {
+            Some(display) => {
+                let opts = katex::Opts::builder()
+                    .display_mode(display)
+                    .build()
+                    .expect("KaTeX options are valid");
+                match katex::render_with_opts(&text, opts) {
+                    Ok(html) => Event::Html(html.into()),
+                    Err(err) => {
+                        diagnostics.warn(format_args!("Failed to render math {:?}: {}", text, err));
+                        Event::Text(delimited(display, &text).into())
+                    }
+                }
+            }
+            None => Event::Text(text.into()),
+        })
+        .collect()
+}
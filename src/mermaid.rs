@@ -0,0 +1,72 @@
+//! Fenced code blocks of Mermaid diagrams, left for mermaid.js to draw in the
+//! browser or drawn at build time with the Mermaid CLI, `mmdc`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use handlebars::html_escape;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MermaidError {
+    #[error("Failed to run `mmdc`: {0}")]
+    Spawn(#[source] io::Error),
+
+    #[error("`mmdc` exited unsuccessfully ({status}): {stderr}")]
+    Failed {
+        status: process::ExitStatus,
+        stderr: String,
+    },
+
+    #[error("Error passing a diagram to `mmdc`: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A `<div>` for mermaid.js to find and draw.
+pub fn div(diagram: &str) -> String {
+    format!("<div class=\"mermaid\">{}</div>\n", html_escape(diagram))
+}
+
+/// Draws the diagram as an inline SVG with `mmdc`.
+pub fn svg(diagram: &str) -> Result<String, MermaidError> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let stem = env::temp_dir().join(format!(
+        "slideshow-mermaid-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let input = stem.with_extension("mmd");
+    let output = stem.with_extension("svg");
+
+    fs::write(&input, diagram)?;
+    let result = Command::new("mmdc")
+        .arg("--input")
+        .arg(&input)
+        .arg("--output")
+        .arg(&output)
+        .output();
+    let _ = fs::remove_file(&input);
+    let result = result.map_err(MermaidError::Spawn)?;
+    if !result.status.success() {
+        let _ = fs::remove_file(&output);
+        return Err(MermaidError::Failed {
+            status: result.status,
+            stderr: String::from_utf8_lossy(&result.stderr).trim().to_owned(),
+        });
+    }
+    let svg = fs::read_to_string(&output);
+    let _ = fs::remove_file(&output);
+    let svg = svg?;
+    // An XML declaration isn't allowed in the middle of an HTML document.
+    let svg = match svg.find("<svg") {
+        Some(start) => &svg[start..],
+        None => &svg,
+    };
+    Ok(format!(
+        "<div class=\"mermaid-diagram\">{}</div>\n",
+        svg.trim()
+    ))
+}
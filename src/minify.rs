@@ -0,0 +1,291 @@
+//! Shrinking stylesheets for inlining into the template, and the rendered
+//! slideshow for `--minify`.
+
+use thiserror::Error;
+
+/// Drops the comments and insignificant whitespace in `css`. Strings are
+/// kept as they are, and spaces are only dropped where they can't separate
+/// parts of a selector or value.
+pub fn css(css: &str) -> String {
+    let mut ret = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    let mut space = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            c if c.is_whitespace() => space = true,
+            c => {
+                if space
+                    && !ret.is_empty()
+                    && !ret.ends_with(['{', '}', ';', ',', ':', '('])
+                    && !"{};,)".contains(c)
+                {
+                    ret.push(' ');
+                }
+                space = false;
+                if c == '}' && ret.ends_with(';') {
+                    ret.pop();
+                }
+                ret.push(c);
+                if c == '"' || c == '\'' {
+                    while let Some(s) = chars.next() {
+                        ret.push(s);
+                        if s == '\\' {
+                            ret.extend(chars.next());
+                        } else if s == c {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    ret
+}
+
+#[derive(Error, Debug)]
+pub enum MinifyErr {
+    #[error("Unclosed comment at byte {0}")]
+    Comment(usize),
+
+    #[error("Unclosed tag at byte {0}")]
+    Tag(usize),
+
+    #[error("Unclosed `<{0}>` at byte {1}")]
+    Element(String, usize),
+}
+
+/// Elements whose contents are copied as they are, except stylesheets,
+/// which are minified with `css`.
+const RAW: &[&str] = &["pre", "code", "textarea", "script", "style"];
+
+/// Elements which whitespace around doesn't separate anything.
+const BLOCK: &[&str] = &[
+    "!doctype",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "br",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "style",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "ul",
+];
+
+/// Drops the comments and insignificant whitespace in `html`: whitespace
+/// next to block elements like `<div>`, and more than one space in a row
+/// elsewhere. Comments starting with `<!--!`, like a build-info comment, are
+/// kept. `<pre>`, `<code>`, `<textarea>`, and `<script>` contents are kept as
+/// they are, and `<style>` contents are minified with `css`.
+pub fn html(html: &str) -> Result<String, MinifyErr> {
+    // ASCII-lowercasing keeps byte offsets the same.
+    let lower = html.to_ascii_lowercase();
+    let mut ret = String::with_capacity(html.len());
+    // The name of the last tag, or empty at the start of the document.
+    let mut last = "";
+    let mut i = 0;
+    while i < html.len() {
+        let start = lower[i..].find('<').map_or(html.len(), |lt| i + lt);
+        let next = if start < html.len() {
+            tag_name(&lower[start..])
+        } else {
+            ""
+        };
+        push_text(&mut ret, &html[i..start], last, next);
+        if start == html.len() {
+            break;
+        }
+
+        i = start;
+        let rest = &html[i..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment
+                .find("-->")
+                .map(|end| end + 7)
+                .ok_or(MinifyErr::Comment(i))?;
+            if rest.starts_with("<!--!") {
+                ret.push_str(&rest[..end]);
+            }
+            i += end;
+            continue;
+        }
+        let end = tag_end(rest).ok_or(MinifyErr::Tag(i))?;
+        push_tag(&mut ret, &rest[..end]);
+        i += end;
+        last = next;
+
+        if !rest.starts_with("</") && RAW.contains(&next) {
+            let close = format!("</{}", next);
+            let content_end = lower[i..]
+                .find(&close)
+                .map(|end| i + end)
+                .ok_or_else(|| MinifyErr::Element(next.to_owned(), start))?;
+            let content = &html[i..content_end];
+            if next == "style" {
+                ret.push_str(&css(content));
+            } else {
+                ret.push_str(content);
+            }
+            i = content_end;
+        }
+    }
+    Ok(ret)
+}
+
+/// The lowercase name of the tag `tag` starts with, without a leading `/`.
+fn tag_name(tag: &str) -> &str {
+    let name = tag[1..].trim_start_matches('/');
+    let end = name
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+/// The length of the tag `rest` starts with, up to and including its `>`,
+/// skipping any `>`s in quoted attribute values.
+fn tag_end(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Pushes `tag` with the whitespace between its attributes collapsed.
+fn push_tag(ret: &mut String, tag: &str) {
+    let mut quote = None;
+    let mut space = false;
+    for c in tag.chars() {
+        if quote.is_none() && c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space && c != '>' && c != '/' {
+            ret.push(' ');
+        }
+        space = false;
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+        ret.push(c);
+    }
+}
+
+/// Pushes `text`, between the tags named `last` and `next`, with its
+/// whitespace collapsed, and dropped next to block elements.
+fn push_text(ret: &mut String, text: &str, last: &str, next: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    for (i, word) in text.split_whitespace().enumerate() {
+        if i > 0 {
+            collapsed.push(' ');
+        }
+        collapsed.push_str(word);
+    }
+    let leading = text.starts_with(char::is_whitespace);
+    let trailing = text.ends_with(char::is_whitespace);
+    if leading
+        && !ret.is_empty()
+        && !ret.ends_with(' ')
+        && !last.is_empty()
+        && !BLOCK.contains(&last)
+    {
+        ret.push(' ');
+    }
+    ret.push_str(&collapsed);
+    if trailing && !collapsed.is_empty() && !BLOCK.contains(&next) {
+        ret.push(' ');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_blocks_are_kept() {
+        let code = "<pre><code class=\"language-python\">def f():\n\n    \
+                    return  1\n\t# tab\n</code></pre>";
+        let input = format!(
+            "<!DOCTYPE html>\n<html>\n  <body>\n    <!-- slides -->\n    \
+             <section>\n      <h1>A  <em>b</em>   c</h1>\n      {}\n    \
+             </section>\n    <p>Run <code>  x  </code> now</p>\n  </body>\n</html>\n",
+            code
+        );
+        assert_eq!(
+            html(&input).unwrap(),
+            format!(
+                "<!DOCTYPE html><html><body><section><h1>A <em>b</em> c</h1>{}\
+                 </section><p>Run <code>  x  </code> now</p></body></html>",
+                code
+            )
+        );
+    }
+
+    #[test]
+    fn build_info_comments_are_kept() {
+        assert_eq!(
+            html("<p>\n  <!--! built by builder -->\n  <!-- not this -->\n</p>").unwrap(),
+            "<p><!--! built by builder --></p>"
+        );
+    }
+
+    #[test]
+    fn unclosed_code_blocks_are_errors() {
+        assert!(matches!(
+            html("<pre>  x"),
+            Err(MinifyErr::Element(name, 0)) if name == "pre"
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+//! Opening the built slideshow in a browser, for `--open`.
+
+use std::ffi::OsStr;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use tracing::{event, Level};
+
+/// The program which opens files and URLs with the default application, and
+/// the arguments it needs before them.
+fn opener() -> (&'static str, &'static [&'static str]) {
+    if cfg!(target_os = "macos") {
+        ("open", &[])
+    } else if cfg!(windows) {
+        // `start`'s first quoted argument is the window's title.
+        ("cmd", &["/C", "start", ""])
+    } else {
+        ("xdg-open", &[])
+    }
+}
+
+/// Opens `target`, a file or URL. Failing to is only a warning; the
+/// slideshow is built either way.
+pub fn open(target: impl AsRef<OsStr>) {
+    let target = target.as_ref();
+    let (program, args) = opener();
+    let command = [program]
+        .iter()
+        .chain(args)
+        .map(|arg| arg.to_string())
+        .chain(Some(target.to_string_lossy().into_owned()))
+        .collect::<Vec<_>>();
+    event!(
+        Level::INFO,
+        "Opening the slideshow with `{}`",
+        command.join(" ")
+    );
+    let child = Command::new(program)
+        .args(args)
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            event!(Level::WARN, %err, "Couldn't run `{}` to open the slideshow", program);
+            return;
+        }
+    };
+    let target = target.to_owned();
+    thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => event!(
+            Level::WARN,
+            %status,
+            "`{}` couldn't open {}",
+            program,
+            target.to_string_lossy()
+        ),
+        Ok(_) => {}
+        Err(err) => event!(Level::WARN, %err, "Couldn't wait for `{}`", program),
+    });
+}
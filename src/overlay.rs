@@ -0,0 +1,74 @@
+//! Showing build errors in the slideshow itself while watching, so they're
+//! seen in the browser: an overlay over the last slideshow built, which the
+//! next successful build takes away.
+
+use handlebars::html_escape;
+
+const START: &str = "<!-- slideshow-error-overlay -->";
+const END: &str = "<!-- /slideshow-error-overlay -->";
+
+/// The overlay for an error described by `messages`, the error and what
+/// caused it, at `location`.
+pub fn overlay(messages: &[&str], location: Option<&str>) -> String {
+    let mut html = format!(
+        "{}\n<div role=\"alert\" style=\"position: fixed; inset: 0; z-index: 2147483647; \
+         overflow: auto; box-sizing: border-box; padding: 2em; \
+         background: rgba(24, 24, 24, 0.94); color: #f2f2f2; \
+         font: 16px/1.5 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; \
+         text-align: left\">\n\
+         <p style=\"margin: 0 0 1em; color: #ff6b6b; font-weight: bold; font-size: 1.25em\">\
+         The slideshow failed to build</p>\n",
+        START
+    );
+    if let Some(location) = location {
+        html.push_str(&format!(
+            "<p style=\"margin: 0 0 1em; color: #ffd166\">In {}</p>\n",
+            html_escape(location)
+        ));
+    }
+    for (depth, message) in messages.iter().enumerate() {
+        html.push_str(&format!(
+            "<pre style=\"margin: 0 0 0.5em {}em; white-space: pre-wrap; font: inherit\">{}{}</pre>\n",
+            2 * depth,
+            if depth == 0 { "" } else { "caused by: " },
+            html_escape(message)
+        ));
+    }
+    html.push_str(
+        "<p style=\"margin: 1em 0 0; color: #aaa\">Still watching for changes; this goes away \
+         once the slideshow builds.</p>\n</div>\n",
+    );
+    html.push_str(END);
+    html.push('\n');
+    html
+}
+
+/// `page` with `overlay` over it, in place of any overlay it had.
+pub fn inject(page: &str, overlay: &str) -> String {
+    let page = remove(page).unwrap_or_else(|| page.to_owned());
+    match page.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &page[..end], overlay, &page[end..]),
+        None => format!("{}{}", page, overlay),
+    }
+}
+
+/// A page of only `overlay`, for when there's no slideshow to put it over.
+pub fn page(overlay: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Build failed</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        overlay
+    )
+}
+
+/// `page` without its overlay, if it has one.
+pub fn remove(page: &str) -> Option<String> {
+    let start = page.find(START)?;
+    let end = start + page[start..].find(END)? + END.len();
+    let end = if page[end..].starts_with('\n') {
+        end + 1
+    } else {
+        end
+    };
+    Some(format!("{}{}", &page[..start], &page[end..]))
+}
@@ -0,0 +1,119 @@
+//! Content for some build profiles only, in `:::only{long}` or `:::not{short}`
+//! blocks or between `<!-- only: long -->` and `<!-- /only -->` comments.
+//!
+//! Blocks are dropped from the Markdown source before it's parsed, so that
+//! they can hold whole slides, delimiters and all.
+
+use crate::diagnostics::Diagnostics;
+use crate::math::fence_marker;
+
+/// A block opened in the source.
+enum Block {
+    /// An `only` or `not` block, and whether its content is kept.
+    Profile { comment: bool, keep: bool },
+    /// Any other `:::name` container, whose `:::` closes it rather than the
+    /// enclosing profile block.
+    Container,
+}
+
+/// Drops the blocks in Markdown `source` not for any of the active
+/// `profiles`.
+pub fn filter(source: &str, profiles: &[String], diagnostics: &Diagnostics) -> String {
+    let mut ret = String::with_capacity(source.len());
+    let mut blocks = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in source.split_inclusive('\n') {
+        let keep = blocks
+            .iter()
+            .all(|block| !matches!(block, Block::Profile { keep: false, .. }));
+        match (fence, fence_marker(line)) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some(open), Some(marker)) if marker.starts_with(open) => fence = None,
+            (Some(_), _) => {}
+            (None, None) => {
+                if let Some(block) = marker(line.trim(), profiles) {
+                    match block {
+                        Marker::Open(block) => {
+                            let is_profile = matches!(block, Block::Profile { .. });
+                            blocks.push(block);
+                            if is_profile {
+                                continue;
+                            }
+                        }
+                        Marker::Close { comment } => match blocks.last() {
+                            Some(Block::Profile { comment: c, .. }) if *c == comment => {
+                                blocks.pop();
+                                continue;
+                            }
+                            Some(Block::Container) if !comment => {
+                                blocks.pop();
+                            }
+                            _ => {}
+                        },
+                    }
+                }
+            }
+        }
+        if keep {
+            ret.push_str(line);
+        }
+    }
+    if blocks
+        .iter()
+        .any(|block| matches!(block, Block::Profile { .. }))
+    {
+        diagnostics.warn("A profile block is never closed; it runs to the end of the file");
+    }
+    ret
+}
+
+enum Marker {
+    Open(Block),
+    Close { comment: bool },
+}
+
+/// Parses a line opening or closing a block.
+fn marker(line: &str, profiles: &[String]) -> Option<Marker> {
+    if let Some(body) = line
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.strip_suffix("-->"))
+    {
+        let body = body.trim();
+        if body == "/only" || body == "/not" {
+            return Some(Marker::Close { comment: true });
+        }
+        let (only, names) = match (body.strip_prefix("only:"), body.strip_prefix("not:")) {
+            (Some(names), _) => (true, names),
+            (_, Some(names)) => (false, names),
+            _ => return None,
+        };
+        return Some(Marker::Open(Block::Profile {
+            comment: true,
+            keep: matches(names, profiles) == only,
+        }));
+    }
+
+    let rest = line.strip_prefix(":::")?.trim_start();
+    if rest.is_empty() {
+        return Some(Marker::Close { comment: false });
+    }
+    let (only, group) = match (rest.strip_prefix("only{"), rest.strip_prefix("not{")) {
+        (Some(group), _) => (true, group),
+        (_, Some(group)) => (false, group),
+        _ => return Some(Marker::Open(Block::Container)),
+    };
+    let names = group.strip_suffix('}')?;
+    Some(Marker::Open(Block::Profile {
+        comment: false,
+        keep: matches(names, profiles) == only,
+    }))
+}
+
+/// Does a list of profile names like `long, workshop` include any of the
+/// active `profiles`?
+fn matches(names: &str, profiles: &[String]) -> bool {
+    names
+        .split(',')
+        .map(str::trim)
+        .any(|name| profiles.iter().any(|profile| profile == name))
+}
@@ -0,0 +1,32 @@
+//! QR codes for links, written like images: `![qr](https://example.com)`.
+
+use pulldown_cmark::escape::{escape_href, escape_html};
+use qrcode::render::svg;
+use qrcode::types::QrError;
+use qrcode::QrCode;
+
+/// The default width and height of a QR code, in pixels.
+pub const DEFAULT_SIZE: u32 = 200;
+
+/// Renders a QR code for `url` as an inline SVG at least `size` pixels
+/// square, followed by the link itself for anyone who can't scan it.
+pub fn html(url: &str, size: u32) -> Result<String, QrError> {
+    let code = QrCode::new(url)?;
+    let svg = code
+        .render::<svg::Color>()
+        .min_dimensions(size, size)
+        .build();
+    // Drop the XML declaration, which doesn't belong in HTML.
+    let svg = &svg[svg.find("<svg").unwrap_or(0)..];
+    let mut html =
+        String::from(r#"<span class="qr-code"><svg role="img" aria-label="QR code for "#);
+    escape_html(&mut html, url).unwrap();
+    html.push('"');
+    html.push_str(&svg["<svg".len()..]);
+    html.push_str(r#"<a class="qr-link" href=""#);
+    escape_href(&mut html, url).unwrap();
+    html.push_str(r#"">"#);
+    escape_html(&mut html, url).unwrap();
+    html.push_str("</a></span>");
+    Ok(html)
+}
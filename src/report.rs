@@ -0,0 +1,158 @@
+//! A machine-readable report of what a build produced, for `--report`.
+//!
+//! Other tools read the report, so fields are only ever added to it; anything
+//! else changes `VERSION`.
+
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The version of the report's format.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub version: u32,
+    /// The slideshows and handouts written.
+    pub outputs: Vec<Output>,
+    /// The static files and referenced files copied into the output
+    /// directory.
+    pub static_files: Vec<StaticFile>,
+    /// How many slides there are in all the slideshows.
+    pub slides: usize,
+    pub warnings: Vec<Warning>,
+    /// How long each part of the last build or update took, in order.
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaticFile {
+    pub source: String,
+    pub destination: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// The input file the slide is in.
+    pub input: String,
+    pub slide: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningKind {
+    /// An accessibility problem, from `--lint`.
+    Lint,
+    /// A slide with too much content to fit.
+    Overflow,
+    /// A link to a missing file or heading, or a site that didn't respond.
+    BrokenLink,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Phase {
+    pub name: String,
+    /// Wall-clock time.
+    pub milliseconds: f64,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            outputs: Vec::new(),
+            static_files: Vec::new(),
+            slides: 0,
+            warnings: Vec::new(),
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl Report {
+    /// Notes that `path` was written, replacing what was noted about it
+    /// before.
+    pub fn output(&mut self, path: &Path, bytes: u64) {
+        let path = path.display().to_string();
+        self.outputs.retain(|output| output.path != path);
+        self.outputs.push(Output { path, bytes });
+    }
+
+    /// Notes that `source` was copied to `destination`, replacing what was
+    /// noted about `destination` before.
+    pub fn copied(&mut self, source: &Path, destination: &Path, bytes: u64) {
+        let destination = destination.display().to_string();
+        self.static_files
+            .retain(|file| file.destination != destination);
+        self.static_files.push(StaticFile {
+            source: source.display().to_string(),
+            destination,
+            bytes,
+        });
+    }
+
+    pub fn phase(&mut self, name: &str, duration: Duration) {
+        self.phases.push(Phase {
+            name: name.to_owned(),
+            milliseconds: duration.as_secs_f64() * 1000.0,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut report = Report::default();
+        report.output(Path::new("out/index.html"), 2048);
+        report.copied(
+            Path::new("static/style.css"),
+            Path::new("out/style.css"),
+            512,
+        );
+        report.slides = 12;
+        report.warnings.push(Warning {
+            kind: WarningKind::BrokenLink,
+            input: "slides.md".to_owned(),
+            slide: 3,
+            message: "Broken link to missing.png: No such file".to_owned(),
+        });
+        report.phase("render", Duration::from_millis(15));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "outputs": [{"path": "out/index.html", "bytes": 2048}],
+                "static_files": [{
+                    "source": "static/style.css",
+                    "destination": "out/style.css",
+                    "bytes": 512,
+                }],
+                "slides": 12,
+                "warnings": [{
+                    "kind": "broken-link",
+                    "input": "slides.md",
+                    "slide": 3,
+                    "message": "Broken link to missing.png: No such file",
+                }],
+                "phases": [{"name": "render", "milliseconds": 15.0}],
+            })
+        );
+        let parsed: Report = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, report);
+    }
+}
@@ -0,0 +1,84 @@
+//! Raw HTML in the Markdown, which can be kept, sanitized, or dropped.
+
+use pulldown_cmark::{html, Event};
+
+/// What to do with raw HTML in the Markdown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawHtml {
+    Keep,
+    /// Run it through an allowlist sanitizer.
+    Sanitize,
+    Drop,
+}
+
+/// Starts raw HTML waiting to be sanitized, to tell it apart from the HTML we
+/// generate.
+const MARKER: char = '\u{E010}';
+
+/// Marks raw HTML to be sanitized by `sanitize_events`.
+pub fn mark(html: &str) -> String {
+    format!("{}{}", MARKER, html)
+}
+
+fn is_marked(event: &Event<'_>) -> bool {
+    matches!(event, Event::Html(html) if html.starts_with(MARKER))
+}
+
+fn unmark(event: Event<'_>) -> Event<'_> {
+    match event {
+        Event::Html(html) if html.starts_with(MARKER) => {
+            Event::Html(html[MARKER.len_utf8()..].to_owned().into())
+        }
+        _ => event,
+    }
+}
+
+pub fn sanitize(html: &str) -> String {
+    ammonia::Builder::default()
+        .add_generic_attributes(&["class"])
+        .clean(html)
+        .to_string()
+}
+
+/// Sanitizes the marked raw HTML in `events`. Since tags in raw HTML needn't
+/// be balanced within an event, consecutive raw HTML blocks are sanitized
+/// together, and blocks with raw HTML inside them are rendered and sanitized
+/// whole.
+pub fn sanitize_events(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    if !events.iter().any(is_marked) {
+        return events;
+    }
+    let mut ret = Vec::with_capacity(events.len());
+    let mut block = Vec::new();
+    let mut depth = 0;
+    for event in events {
+        // A run of raw HTML blocks ends at the first event that isn't one.
+        if depth == 0 && block.first().is_some_and(is_marked) && !is_marked(&event) {
+            ret.push(sanitized(&mut block));
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        block.push(event);
+        if depth == 0 && !block.first().is_some_and(is_marked) {
+            if block.iter().any(is_marked) {
+                ret.push(sanitized(&mut block));
+            } else {
+                ret.append(&mut block);
+            }
+        }
+    }
+    if !block.is_empty() {
+        ret.push(sanitized(&mut block));
+    }
+    ret
+}
+
+/// Renders and sanitizes `events`, leaving it empty.
+fn sanitized<'a>(events: &mut Vec<Event<'a>>) -> Event<'a> {
+    let mut html = String::new();
+    html::push_html(&mut html, events.drain(..).map(unmark));
+    Event::Html(sanitize(&html).into())
+}
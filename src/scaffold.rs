@@ -0,0 +1,111 @@
+//! Starting a new slideshow, for `new`.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::config;
+use crate::markdown;
+use crate::theme::Theme;
+
+#[derive(Error, Debug)]
+pub enum ScaffoldErr {
+    #[error("Error creating directory {0}: {1}")]
+    CreateDir(PathBuf, #[source] io::Error),
+
+    #[error("Error reading directory {0}: {1}")]
+    ReadDir(PathBuf, #[source] io::Error),
+
+    #[error("{0} isn't empty; pass --force to write the new slideshow's files into it anyway")]
+    NotEmpty(PathBuf),
+
+    #[error("Error writing {0}: {1}")]
+    Write(PathBuf, #[source] io::Error),
+}
+
+const SLIDES: &str = r#"---
+title: My Talk
+author: Me
+---
+
+# Hello!
+
+Slides are separated by `---` lines.
+
+<!-- notes: Speaker notes go in comments like this one. -->
+
+---
+
+# Code
+
+```rust
+fn main() {
+    println!("Hello, world!");
+}
+```
+"#;
+
+/// Creates a slideshow in `dir`: a `slides.md`, the default template, a
+/// `static/style.css` starting from `theme`, and a `slideshow.toml` tying
+/// them together. Then prints how to build it.
+///
+/// `dir` has to be empty, unless `force` is given, in which case files with
+/// the same names are overwritten.
+pub fn new(dir: &Path, theme: Theme, force: bool) -> Result<(), ScaffoldErr> {
+    if dir.exists() && !force {
+        let mut entries = fs::read_dir(dir).map_err(|e| ScaffoldErr::ReadDir(dir.to_owned(), e))?;
+        if entries.next().is_some() {
+            return Err(ScaffoldErr::NotEmpty(dir.to_owned()));
+        }
+    }
+    let static_dir = dir.join("static");
+    fs::create_dir_all(&static_dir).map_err(|e| ScaffoldErr::CreateDir(static_dir, e))?;
+
+    // The starter stylesheet comes last, so it can override the template.
+    let template = markdown::DEFAULT_TEMPLATE.replacen(
+        "</head>",
+        "<link rel=\"stylesheet\" href=\"{{root}}style.css\">\n</head>",
+        1,
+    );
+    let style = format!(
+        "/* Styles for the slideshow, starting from the {} theme's colors and\n \
+         * fonts. */\n{}",
+        theme.name(),
+        theme.css().lines().skip(1).collect::<Vec<_>>().join("\n")
+    );
+    let config = format!(
+        "# Options for building the slideshow; the command line overrides them.\n\
+         static_dir = \"static\"\n\
+         template = \"template.html\"\n\
+         output_dir = \"out\"\n\
+         theme = \"{}\"\n\
+         title_slide = true\n",
+        theme.name()
+    );
+    let files = [
+        ("slides.md", SLIDES),
+        ("template.html", &template),
+        ("static/style.css", &style),
+        (config::FILE_NAME, &config),
+    ];
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|e| ScaffoldErr::Write(path, e))?;
+    }
+
+    // A relative path to the program, like `./builder`, is wrong after the
+    // `cd`; a bare name is found on the `PATH` wherever we are.
+    let program = env::args_os()
+        .next()
+        .map(PathBuf::from)
+        .filter(|program| program.components().count() > 1)
+        .and_then(|program| program.canonicalize().ok())
+        .map(|program| program.display().to_string())
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_owned());
+    println!("Created a slideshow in {}; build it with:", dir.display());
+    println!("    cd {} && {} build slides.md", dir.display(), program);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! A small HTTP server for previewing the output directory, for `serve`.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+
+use percent_encoding::percent_decode_str;
+use tracing::{event, Level};
+
+use crate::embed;
+
+/// Serves the files in `root` on `addr` from a background thread, returning
+/// the address it's listening on.
+pub fn spawn(root: PathBuf, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &root));
+            if let Err(err) = result {
+                event!(Level::DEBUG, %err, "Failed to respond to request");
+            }
+        }
+    });
+    Ok(addr)
+}
+
+/// Answers a request with the file it asks for, or an error.
+fn respond(mut stream: TcpStream, root: &Path) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers don't matter, but are read so that closing the connection
+    // doesn't reset it.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    event!(Level::DEBUG, %method, %target, "Request");
+    if method != "GET" && method != "HEAD" {
+        return write_response(&mut stream, "405 Method Not Allowed", "text/plain", b"");
+    }
+    match file(root, target) {
+        Some(path) => {
+            let body = fs::read(&path)?;
+            let body = if method == "HEAD" { &[][..] } else { &body };
+            write_response(&mut stream, "200 OK", embed::mime_type(&path), body)
+        }
+        None => write_response(&mut stream, "404 Not Found", "text/plain", b"Not found"),
+    }
+}
+
+/// The file in `root` a request `target` like `/img/a%20b.png?v=2` asks
+/// for, with directories' `index.html`s standing in for them.
+fn file(root: &Path, target: &str) -> Option<PathBuf> {
+    let end = target.find(['?', '#']).unwrap_or(target.len());
+    let decoded = percent_decode_str(&target[..end]).decode_utf8().ok()?;
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let mut path = root.join(relative);
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    Some(path).filter(|path| path.is_file())
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    let charset = if content_type.starts_with("text/") {
+        "; charset=utf-8"
+    } else {
+        ""
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}{}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        charset,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
@@ -0,0 +1,114 @@
+//! URL-friendly identifiers generated from text.
+
+use std::collections::HashSet;
+
+/// Latin letters with diacritics, and the ASCII they're written as in slugs.
+const FOLDS: &[(&str, &str)] = &[
+    ("àáâãäåāăą", "a"),
+    ("çćĉċč", "c"),
+    ("ďđð", "d"),
+    ("èéêëēĕėęě", "e"),
+    ("ĝğġģ", "g"),
+    ("ĥħ", "h"),
+    ("ìíîïĩīĭįı", "i"),
+    ("ĵ", "j"),
+    ("ķ", "k"),
+    ("ĺļľŀł", "l"),
+    ("ñńņňŉ", "n"),
+    ("òóôõöøōŏő", "o"),
+    ("ŕŗř", "r"),
+    ("śŝşšș", "s"),
+    ("ţťŧț", "t"),
+    ("ùúûüũūŭůűų", "u"),
+    ("ŵ", "w"),
+    ("ýÿŷ", "y"),
+    ("źżž", "z"),
+    ("ß", "ss"),
+    ("æ", "ae"),
+    ("œ", "oe"),
+    ("þ", "th"),
+];
+
+/// Lowercases `text` and joins its words with hyphens, dropping punctuation
+/// and emoji. Accented Latin letters are written without their accents;
+/// letters in other scripts are kept.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if let Some((_, ascii)) = FOLDS.iter().find(|(letters, _)| letters.contains(c)) {
+            slug.push_str(ascii);
+        } else if c.is_alphanumeric() {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_owned()
+}
+
+/// Hands out unique slugs, adding a numeric suffix to repeats.
+#[derive(Debug, Default)]
+pub struct Slugs {
+    used: HashSet<String>,
+}
+
+impl Slugs {
+    /// Marks `slug` as taken, returning whether it was free.
+    pub fn insert(&mut self, slug: String) -> bool {
+        self.used.insert(slug)
+    }
+
+    /// Returns `slug`, or `slug-1`, `slug-2`, ... if it's already taken.
+    pub fn unique(&mut self, slug: String) -> String {
+        if self.used.insert(slug.clone()) {
+            return slug;
+        }
+        let mut n = 1;
+        loop {
+            let candidate = format!("{}-{}", slug, n);
+            if self.used.insert(candidate.clone()) {
+                return candidate;
+            }
+            n += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_words() {
+        assert_eq!(slugify("Why Rust?"), "why-rust");
+        assert_eq!(
+            slugify("  Ownership -- and   borrowing_rules "),
+            "ownership-and-borrowing-rules"
+        );
+    }
+
+    #[test]
+    fn slugify_accents() {
+        assert_eq!(slugify("Café Crème"), "cafe-creme");
+        assert_eq!(slugify("Straße Ærø"), "strasse-aero");
+        assert_eq!(slugify("ÉCOLE"), "ecole");
+    }
+
+    #[test]
+    fn slugify_other_scripts() {
+        assert_eq!(slugify("Привет мир"), "привет-мир");
+    }
+
+    #[test]
+    fn slugify_emoji() {
+        assert_eq!(slugify("🚀 Launch 🚀 day"), "launch-day");
+        assert_eq!(slugify("🎉"), "");
+    }
+
+    #[test]
+    fn slugify_slugs() {
+        assert_eq!(slugify("already-a-slug"), "already-a-slug");
+        assert_eq!(slugify("step-2"), "step-2");
+        assert_eq!(slugify(&slugify("Café Crème")), "cafe-creme");
+    }
+}
@@ -0,0 +1,81 @@
+//! Statistics about a rendered slideshow, for `--stats` and `--stats-json`.
+
+use std::fmt;
+
+use serde::Serialize;
+
+/// What's on one slide.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SlideCounts {
+    pub words: usize,
+    pub images: usize,
+    pub code_blocks: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub slides: usize,
+    pub words: WordStats,
+    pub images: usize,
+    pub code_blocks: usize,
+    pub words_per_minute: u32,
+    /// How long the slides' words take to say, at `words_per_minute`.
+    pub estimated_minutes: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WordStats {
+    pub total: usize,
+    /// The fewest words on a slide.
+    pub min: usize,
+    pub mean: f64,
+    pub max: usize,
+}
+
+impl Stats {
+    pub fn new(slides: &[SlideCounts], words_per_minute: u32) -> Self {
+        let total = slides.iter().map(|slide| slide.words).sum();
+        let mean = if slides.is_empty() {
+            0.0
+        } else {
+            total as f64 / slides.len() as f64
+        };
+        let estimated_minutes = if words_per_minute == 0 {
+            0.0
+        } else {
+            total as f64 / words_per_minute as f64
+        };
+        Self {
+            slides: slides.len(),
+            words: WordStats {
+                total,
+                min: slides.iter().map(|slide| slide.words).min().unwrap_or(0),
+                mean,
+                max: slides.iter().map(|slide| slide.words).max().unwrap_or(0),
+            },
+            images: slides.iter().map(|slide| slide.images).sum(),
+            code_blocks: slides.iter().map(|slide| slide.code_blocks).sum(),
+            words_per_minute,
+            estimated_minutes,
+        }
+    }
+}
+
+/// Formats the statistics as a table.
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<16} {}", "Slides", self.slides)?;
+        writeln!(
+            f,
+            "{:<16} {} (min {}, mean {:.1}, max {} per slide)",
+            "Words", self.words.total, self.words.min, self.words.mean, self.words.max
+        )?;
+        writeln!(f, "{:<16} {}", "Images", self.images)?;
+        writeln!(f, "{:<16} {}", "Code blocks", self.code_blocks)?;
+        writeln!(
+            f,
+            "{:<16} ~{:.1} minutes at {} words per minute",
+            "Speaking time", self.estimated_minutes, self.words_per_minute
+        )
+    }
+}
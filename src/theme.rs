@@ -0,0 +1,59 @@
+//! Stylesheets built into the binary for the default template, picked with
+//! `--theme`.
+
+use std::str::FromStr;
+
+/// A built-in theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Light,
+    Dark,
+    Solarized,
+    Plain,
+}
+
+impl Theme {
+    const ALL: &'static [Theme] = &[Theme::Light, Theme::Dark, Theme::Solarized, Theme::Plain];
+
+    /// The themes' names, for completing `--theme`.
+    pub const NAMES: &'static [&'static str] = &["light", "dark", "solarized", "plain"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::Solarized => "solarized",
+            Theme::Plain => "plain",
+        }
+    }
+
+    /// The theme's stylesheet, which sets the CSS variables the default
+    /// template uses, like `--accent`.
+    pub fn css(self) -> &'static str {
+        match self {
+            Theme::Light => include_str!("themes/light.css"),
+            Theme::Dark => include_str!("themes/dark.css"),
+            Theme::Solarized => include_str!("themes/solarized.css"),
+            Theme::Plain => include_str!("themes/plain.css"),
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Theme::ALL
+            .iter()
+            .copied()
+            .find(|theme| theme.name() == s)
+            .ok_or_else(|| {
+                let available: Vec<_> = Theme::ALL.iter().map(|theme| theme.name()).collect();
+                format!(
+                    "Unknown theme {:?}; available themes are: {}",
+                    s,
+                    available.join(", ")
+                )
+            })
+    }
+}
@@ -0,0 +1,66 @@
+//! Template variables from `--var key=value` flags and `--data` files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DataError {
+    #[error("Error reading data file {0}: {1}")]
+    Read(PathBuf, #[source] io::Error),
+
+    #[error("Error parsing data file {0}: {1}")]
+    Parse(PathBuf, String),
+
+    #[error("Data file {0} should contain a table of variables")]
+    NotATable(PathBuf),
+
+    #[error("Data file {0} should end with .toml, .json, .yaml, or .yml")]
+    UnknownFormat(PathBuf),
+}
+
+/// A `key=value` template variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Var {
+    pub key: String,
+    pub value: String,
+}
+
+impl FromStr for Var {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => Ok(Self {
+                key: key.trim().to_owned(),
+                value: value.to_owned(),
+            }),
+            _ => Err(format!("Expected `key=value`, not {:?}", s)),
+        }
+    }
+}
+
+/// Reads the variables in a TOML, JSON, or YAML data file, by its extension.
+pub fn load(path: &Path) -> Result<Map<String, Value>, DataError> {
+    let source = fs::read_to_string(path).map_err(|e| DataError::Read(path.to_owned(), e))?;
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let parse_err = |err: String| DataError::Parse(path.to_owned(), err);
+    let value: Value = match extension.as_deref() {
+        Some("toml") => toml::from_str(&source).map_err(|e| parse_err(e.to_string()))?,
+        Some("json") => serde_json::from_str(&source).map_err(|e| parse_err(e.to_string()))?,
+        Some("yaml") | Some("yml") => {
+            serde_yaml::from_str(&source).map_err(|e| parse_err(e.to_string()))?
+        }
+        _ => return Err(DataError::UnknownFormat(path.to_owned())),
+    };
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => Err(DataError::NotATable(path.to_owned())),
+    }
+}
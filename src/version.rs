@@ -0,0 +1,57 @@
+//! What this program was built from, for `--version` and `--version-json`,
+//! from the environment variables `build.rs` sets.
+
+use serde::Serialize;
+
+/// `--version`'s output, after the program's name.
+pub const LONG: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    "\ncommit: ",
+    env!("SLIDESHOW_BUILD_COMMIT"),
+    "\nfeatures: ",
+    env!("SLIDESHOW_BUILD_FEATURES"),
+    "\npulldown-cmark: ",
+    env!("SLIDESHOW_BUILD_PULLDOWN_CMARK"),
+    "\nhandlebars: ",
+    env!("SLIDESHOW_BUILD_HANDLEBARS"),
+    "\ntarget: ",
+    env!("SLIDESHOW_BUILD_TARGET"),
+    "\nrustc: ",
+    env!("SLIDESHOW_BUILD_RUSTC"),
+);
+
+/// `--version-json`'s output. Anything unknown is `null`.
+#[derive(Debug, Serialize)]
+pub struct Version {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub commit: Option<&'static str>,
+    /// The enabled cargo features.
+    pub features: Vec<&'static str>,
+    pub pulldown_cmark: Option<&'static str>,
+    pub handlebars: Option<&'static str>,
+    pub target: Option<&'static str>,
+    pub rustc: Option<&'static str>,
+}
+
+impl Version {
+    pub fn get() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            commit: known(env!("SLIDESHOW_BUILD_COMMIT")),
+            features: match env!("SLIDESHOW_BUILD_FEATURES") {
+                "none" => Vec::new(),
+                features => features.split(',').collect(),
+            },
+            pulldown_cmark: known(env!("SLIDESHOW_BUILD_PULLDOWN_CMARK")),
+            handlebars: known(env!("SLIDESHOW_BUILD_HANDLEBARS")),
+            target: known(env!("SLIDESHOW_BUILD_TARGET")),
+            rustc: known(env!("SLIDESHOW_BUILD_RUSTC")),
+        }
+    }
+}
+
+fn known(value: &'static str) -> Option<&'static str> {
+    Some(value).filter(|value| *value != "unknown")
+}
@@ -0,0 +1,220 @@
+//! Running the built binary, as scripts do.
+
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A new, empty directory to run the binary in.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("simple-slideshow-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_builder"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn missing_input_is_a_usage_error() {
+    let dir = scratch_dir("missing-input");
+    let output = run(&dir, &["build", "missing.md"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.md"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_name_names_the_slideshow() {
+    let dir = scratch_dir("output-name");
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    let output = run(
+        &dir,
+        &["build", "slides.md", "--output-name", "rustconf.html"],
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(dir.join("out/rustconf.html").is_file());
+    assert!(!dir.join("out/index.html").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_name_with_a_directory_is_rejected() {
+    let dir = scratch_dir("output-name-dir");
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    let output = run(
+        &dir,
+        &["build", "slides.md", "--output-name", "talks/rustconf.html"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--output-name"));
+    assert!(!dir.join("out").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn completions_are_generated_for_each_shell() {
+    let dir = env::temp_dir();
+    for shell in &["bash", "zsh", "fish", "powershell"] {
+        let output = run(&dir, &["completions", shell]);
+        assert_eq!(output.status.code(), Some(0), "{}", shell);
+        let script = String::from_utf8_lossy(&output.stdout);
+        assert!(!script.is_empty(), "{}", shell);
+        assert!(script.contains("builder"), "{}", shell);
+        assert!(script.contains("debounce-ms"), "{}", shell);
+    }
+}
+
+#[test]
+fn deny_warnings_lists_every_warning() {
+    let dir = scratch_dir("deny-warnings");
+    fs::create_dir(dir.join("static")).unwrap();
+    fs::write(
+        dir.join("slides.md"),
+        "# One\n\n:::\n\n---\n\n# Two\n\n:::\n",
+    )
+    .unwrap();
+    let output = run(&dir, &["build", "slides.md", "--quiet"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run(&dir, &["build", "slides.md", "--quiet", "--deny-warnings"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("warning: Slide").count(), 2, "{}", stderr);
+    assert!(stderr.contains("Found 2 warning(s)"), "{}", stderr);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Builds `slides.md` in `dir` with `args`, which should be refused for
+/// having the output directory overlap the sources, with a message
+/// containing `message`.
+fn assert_overlap_refused(dir: &PathBuf, args: &[&str], message: &str) {
+    let output = run(dir, args);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(message), "{}", stderr);
+    assert!(!dir.join("index.html").exists());
+}
+
+#[test]
+fn output_dir_cant_be_the_input_dir() {
+    let dir = scratch_dir("output-is-input");
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    assert_overlap_refused(
+        &dir,
+        &["build", "slides.md", "."],
+        "contains the input file",
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_dir_cant_contain_the_input_dir() {
+    let dir = scratch_dir("output-contains-input");
+    fs::create_dir(dir.join("talk")).unwrap();
+    fs::write(dir.join("talk/slides.md"), "# Hello\n").unwrap();
+    assert_overlap_refused(
+        &dir,
+        &["build", "talk/slides.md", "."],
+        "contains the input file",
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn output_dir_cant_contain_the_static_dir() {
+    let dir = scratch_dir("output-contains-static");
+    fs::create_dir_all(dir.join("out/static")).unwrap();
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    assert_overlap_refused(
+        &dir,
+        &["build", "slides.md", "--static-dir", "out/static"],
+        "contains the static files directory",
+    );
+    assert!(!dir.join("out/index.html").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn static_dir_cant_contain_the_output_dir() {
+    let dir = scratch_dir("static-contains-output");
+    fs::create_dir(dir.join("static")).unwrap();
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    assert_overlap_refused(
+        &dir,
+        &["build", "slides.md", "static/out"],
+        "The static files directory",
+    );
+    // The output directory was only made to find its path.
+    assert!(!dir.join("static/out").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Waits up to 10 seconds for the file at `path` to contain `text`.
+fn wait_for_contents(path: &Path, text: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if fs::read_to_string(path).is_ok_and(|contents| contents.contains(text)) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn watching_survives_a_broken_template() {
+    let dir = scratch_dir("watch-broken-template");
+    fs::write(dir.join("slides.md"), "# Hello\n").unwrap();
+    fs::write(dir.join("template.html"), "first {{{content}}}").unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_builder"))
+        .args([
+            "watch",
+            "slides.md",
+            "--template",
+            "./template.html",
+            "--debounce-ms",
+            "50",
+            "--quiet",
+        ])
+        .current_dir(&dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    let stderr = child.stderr.take().unwrap();
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            let _ = tx.send(line);
+        }
+    });
+    let index = dir.join("out/index.html");
+    assert!(wait_for_contents(&index, "first"));
+
+    fs::write(dir.join("template.html"), "broken {{#if content}}").unwrap();
+    let error = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(error.starts_with("error:"), "{}", error);
+    assert!(child.try_wait().unwrap().is_none());
+
+    fs::write(dir.join("template.html"), "second {{{content}}}").unwrap();
+    let fixed = wait_for_contents(&index, "second");
+    let running = child.try_wait().unwrap().is_none();
+    let _ = child.kill();
+    let _ = child.wait();
+    assert!(fixed);
+    assert!(running);
+    assert!(fs::read_to_string(&index).unwrap().contains("<h1"));
+    fs::remove_dir_all(&dir).unwrap();
+}